@@ -1,4 +1,18 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::hash::{hash, hashv};
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+use anchor_lang::system_program;
+use anchor_lang::AccountsClose;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::metadata::mpl_token_metadata::types::DataV2;
+use anchor_spl::metadata::{
+    create_master_edition_v3, create_metadata_accounts_v3, CreateMasterEditionV3,
+    CreateMetadataAccountsV3, Metadata,
+};
+use anchor_spl::token::{self, FreezeAccount, Mint, MintTo, Token, TokenAccount, Transfer};
 
 declare_id!("SoLMnToR11111111111111111111111111111111111");
 
@@ -6,261 +20,11130 @@ declare_id!("SoLMnToR11111111111111111111111111111111111");
 pub mod solmentor {
     use super::*;
 
+    /// Create the protocol configuration that holds the XP economy parameters
+    pub fn initialize_config(
+        ctx: Context<InitializeConfig>,
+        xp_per_correct: u64,
+        perfect_score_bonus: u64,
+        level_curve: LevelCurve,
+        streak_window_secs: i64,
+        tier_bonuses: [u64; 5],
+    ) -> Result<()> {
+        require!(level_curve.is_valid(), ErrorCode::InvalidConfig);
+        require!(streak_window_secs > 0, ErrorCode::InvalidConfig);
+        
+        let config = &mut ctx.accounts.protocol_config;
+        config.authority = ctx.accounts.authority.key();
+        config.achievement_authority = ctx.accounts.authority.key();
+        config.xp_per_correct = xp_per_correct;
+        config.perfect_score_bonus = perfect_score_bonus;
+        config.level_curve = level_curve;
+        config.streak_window_secs = streak_window_secs;
+        config.tier_bonuses = tier_bonuses;
+        config.streak_milestones = DEFAULT_STREAK_MILESTONES;
+        config.streak_bonus_xp = DEFAULT_STREAK_BONUS_XP;
+        config.hint_penalty_xp = DEFAULT_HINT_PENALTY_XP;
+        config.retake_decay_bps = DEFAULT_RETAKE_DECAY_BPS;
+        config.min_retake_multiplier_bps = DEFAULT_MIN_RETAKE_MULTIPLIER_BPS;
+        config.difficulty_multipliers_bps = DEFAULT_DIFFICULTY_MULTIPLIERS_BPS;
+        config.min_mentor_level = DEFAULT_MIN_MENTOR_LEVEL;
+        config.session_fee_bps = DEFAULT_SESSION_FEE_BPS;
+        config.xp_event_multiplier = BASE_MULTIPLIER_BPS;
+        config.referral_bonus_xp = DEFAULT_REFERRAL_BONUS_XP;
+        config.max_course_reward_xp = DEFAULT_MAX_COURSE_REWARD_XP;
+        config.course_approval_xp = DEFAULT_COURSE_APPROVAL_XP;
+        config.version = CONFIG_VERSION;
+        
+        msg!("Protocol config initialized by: {}", config.authority);
+        Ok(())
+    }
+
+    /// Update the XP economy parameters (config authority only)
+    pub fn update_config(
+        ctx: Context<UpdateConfig>,
+        xp_per_correct: u64,
+        perfect_score_bonus: u64,
+        level_curve: LevelCurve,
+        streak_window_secs: i64,
+        tier_bonuses: [u64; 5],
+    ) -> Result<()> {
+        require!(level_curve.is_valid(), ErrorCode::InvalidConfig);
+        require!(streak_window_secs > 0, ErrorCode::InvalidConfig);
+        
+        let config = &mut ctx.accounts.protocol_config;
+        config.xp_per_correct = xp_per_correct;
+        config.perfect_score_bonus = perfect_score_bonus;
+        config.level_curve = level_curve;
+        config.streak_window_secs = streak_window_secs;
+        config.tier_bonuses = tier_bonuses;
+        
+        msg!(
+            "Protocol config updated: {} XP per correct, {} perfect bonus, {}s streak window",
+            xp_per_correct,
+            perfect_score_bonus,
+            streak_window_secs
+        );
+        msg!(
+            "Level curve: {} base XP per level, {} bps growth",
+            config.level_curve.base_xp,
+            config.level_curve.growth_factor_bps
+        );
+        Ok(())
+    }
+
+    /// Designate a key allowed to award achievements alongside the config authority
+    pub fn set_achievement_authority(
+        ctx: Context<UpdateConfig>,
+        achievement_authority: Pubkey,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.protocol_config;
+        config.achievement_authority = achievement_authority;
+        
+        msg!("Achievement authority set to: {}", achievement_authority);
+        Ok(())
+    }
+
+    /// Set the streak window and the grace period after it (config authority only)
+    pub fn set_streak_window(
+        ctx: Context<UpdateConfig>,
+        window_secs: i64,
+        grace_secs: i64,
+    ) -> Result<()> {
+        require!(window_secs > 0, ErrorCode::InvalidConfig);
+        require!((0..window_secs).contains(&grace_secs), ErrorCode::InvalidConfig);
+        
+        let config = &mut ctx.accounts.protocol_config;
+        config.streak_window_secs = window_secs;
+        config.streak_grace_secs = grace_secs;
+        
+        msg!("Streak window set to {}s with {}s grace", window_secs, grace_secs);
+        Ok(())
+    }
+
+    /// Set the minimum time between two quiz submissions (config authority only)
+    pub fn set_quiz_cooldown(ctx: Context<UpdateConfig>, cooldown_secs: i64) -> Result<()> {
+        require!(cooldown_secs >= 0, ErrorCode::InvalidConfig);
+        ctx.accounts.protocol_config.quiz_cooldown_secs = cooldown_secs;
+        
+        msg!("Quiz cooldown set to {}s", cooldown_secs);
+        Ok(())
+    }
+
+    /// Set the streak lengths that earn bonus XP and the bonus for each
+    /// (config authority only). Unused slots hold a milestone of 0.
+    pub fn set_streak_milestones(
+        ctx: Context<UpdateConfig>,
+        streak_milestones: [u64; 8],
+        streak_bonus_xp: [u64; 8],
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.protocol_config;
+        config.streak_milestones = streak_milestones;
+        config.streak_bonus_xp = streak_bonus_xp;
+        
+        msg!("Streak milestones updated: {:?}", streak_milestones);
+        Ok(())
+    }
+
+    /// Set the XP price of a streak freeze; 0 disables buying (config authority only)
+    pub fn set_streak_freeze_cost(ctx: Context<UpdateConfig>, cost_xp: u64) -> Result<()> {
+        ctx.accounts.protocol_config.streak_freeze_cost_xp = cost_xp;
+        
+        msg!("Streak freeze cost set to {} XP", cost_xp);
+        Ok(())
+    }
+
+    /// Set the XP granted by `claim_daily_bonus` (config authority only)
+    pub fn set_daily_bonus(ctx: Context<UpdateConfig>, daily_bonus_xp: u64) -> Result<()> {
+        ctx.accounts.protocol_config.daily_bonus_xp = daily_bonus_xp;
+        
+        msg!("Daily bonus set to {} XP", daily_bonus_xp);
+        Ok(())
+    }
+
+    /// Set the speed bonus: attempts faster than `threshold_seconds` that
+    /// score at least one answer earn `bonus_xp` (config authority only)
+    pub fn set_speed_bonus(
+        ctx: Context<UpdateConfig>,
+        threshold_seconds: u32,
+        bonus_xp: u64,
+        min_time_taken_seconds: u32,
+    ) -> Result<()> {
+        require!(
+            threshold_seconds == 0 || min_time_taken_seconds < threshold_seconds,
+            ErrorCode::InvalidConfig
+        );
+        
+        let config = &mut ctx.accounts.protocol_config;
+        config.speed_threshold_seconds = threshold_seconds;
+        config.speed_bonus_xp = bonus_xp;
+        config.min_time_taken_seconds = min_time_taken_seconds;
+        
+        msg!(
+            "Speed bonus set to up to {} XP under {}s, fastest accepted time {}s",
+            bonus_xp,
+            threshold_seconds,
+            min_time_taken_seconds
+        );
+        Ok(())
+    }
+
+    /// Set the XP deducted per hint used on a quiz attempt (config authority only)
+    pub fn set_hint_penalty(ctx: Context<UpdateConfig>, hint_penalty_xp: u64) -> Result<()> {
+        ctx.accounts.protocol_config.hint_penalty_xp = hint_penalty_xp;
+        
+        msg!("Hint penalty set to {} XP", hint_penalty_xp);
+        Ok(())
+    }
+
+    /// Set how quickly XP decays on retakes of the same quiz (config authority only)
+    pub fn set_retake_decay(
+        ctx: Context<UpdateConfig>,
+        retake_decay_bps: u16,
+        min_retake_multiplier_bps: u16,
+    ) -> Result<()> {
+        require!(retake_decay_bps <= BASE_MULTIPLIER_BPS, ErrorCode::InvalidConfig);
+        require!(min_retake_multiplier_bps <= BASE_MULTIPLIER_BPS, ErrorCode::InvalidConfig);
+        
+        let config = &mut ctx.accounts.protocol_config;
+        config.retake_decay_bps = retake_decay_bps;
+        config.min_retake_multiplier_bps = min_retake_multiplier_bps;
+        
+        msg!(
+            "Retake decay set to {} bps per retake, floor {} bps",
+            retake_decay_bps,
+            min_retake_multiplier_bps
+        );
+        Ok(())
+    }
+
+    /// Set the XP multiplier for each quiz difficulty, in basis points
+    /// (config authority only)
+    pub fn set_difficulty_multipliers(
+        ctx: Context<UpdateConfig>,
+        difficulty_multipliers_bps: [u16; 4],
+    ) -> Result<()> {
+        require!(
+            difficulty_multipliers_bps.iter().all(|&multiplier| multiplier > 0),
+            ErrorCode::InvalidConfig
+        );
+        ctx.accounts.protocol_config.difficulty_multipliers_bps = difficulty_multipliers_bps;
+        
+        msg!("Difficulty multipliers set to {:?} bps", difficulty_multipliers_bps);
+        Ok(())
+    }
+
+    /// Set the level a user must reach to register as a mentor (config authority only)
+    pub fn set_min_mentor_level(ctx: Context<UpdateConfig>, min_mentor_level: u64) -> Result<()> {
+        ctx.accounts.protocol_config.min_mentor_level = min_mentor_level;
+        
+        msg!("Minimum mentor level set to {}", min_mentor_level);
+        Ok(())
+    }
+
+    /// Set the protocol fee taken from completed mentor sessions, in basis
+    /// points of the escrow (config authority only)
+    pub fn set_session_fee(ctx: Context<UpdateConfig>, session_fee_bps: u16) -> Result<()> {
+        require!(session_fee_bps <= BASE_MULTIPLIER_BPS, ErrorCode::InvalidConfig);
+        ctx.accounts.protocol_config.session_fee_bps = session_fee_bps;
+        
+        msg!("Session fee set to {} bps", session_fee_bps);
+        Ok(())
+    }
+
+    /// Schedule a limited-time quiz XP multiplier event (config authority only)
+    pub fn set_xp_event(
+        ctx: Context<UpdateConfig>,
+        multiplier_bps: u16,
+        start: i64,
+        end: i64,
+    ) -> Result<()> {
+        require!(multiplier_bps > 0, ErrorCode::InvalidConfig);
+        require!(start <= end, ErrorCode::InvalidConfig);
+        
+        let config = &mut ctx.accounts.protocol_config;
+        config.xp_event_multiplier = multiplier_bps;
+        config.xp_event_start = start;
+        config.xp_event_end = end;
+        
+        msg!("XP event set: {} bps from {} to {}", multiplier_bps, start, end);
+        Ok(())
+    }
+
+    /// Cap the reward tokens accrued by one user per UTC day; 0 removes the
+    /// cap (config authority only)
+    pub fn set_daily_token_cap(ctx: Context<UpdateConfig>, daily_token_cap: u64) -> Result<()> {
+        ctx.accounts.protocol_config.daily_token_cap = daily_token_cap;
+        
+        msg!("Daily token cap set to {}", daily_token_cap);
+        Ok(())
+    }
+
+    /// Set the XP granted to both the new user and the referrer on a
+    /// referral (config authority only)
+    pub fn set_referral_bonus(ctx: Context<UpdateConfig>, referral_bonus_xp: u64) -> Result<()> {
+        ctx.accounts.protocol_config.referral_bonus_xp = referral_bonus_xp;
+        
+        msg!("Referral bonus set to {} XP", referral_bonus_xp);
+        Ok(())
+    }
+
+    /// Set the smallest pending reward balance that can be claimed (config
+    /// authority only)
+    pub fn set_min_reward_claim(ctx: Context<UpdateConfig>, min_reward_claim: u64) -> Result<()> {
+        ctx.accounts.protocol_config.min_reward_claim = min_reward_claim;
+        
+        msg!("Minimum reward claim set to {} tokens", min_reward_claim);
+        Ok(())
+    }
+
+    /// Set the bonus XP for each achievement tier, Bronze to Diamond (config
+    /// authority only)
+    pub fn set_tier_bonuses(ctx: Context<UpdateConfig>, tier_bonuses: [u64; 5]) -> Result<()> {
+        ctx.accounts.protocol_config.tier_bonuses = tier_bonuses;
+        
+        msg!("Tier bonuses set to {:?}", tier_bonuses);
+        Ok(())
+    }
+
+    /// Set the royalty on certificate NFTs for each achievement tier, in basis
+    /// points (config authority only)
+    pub fn set_tier_royalties(
+        ctx: Context<UpdateConfig>,
+        tier_royalty_bps: [u16; 5],
+    ) -> Result<()> {
+        require!(
+            tier_royalty_bps.iter().all(|&royalty| royalty <= BASE_MULTIPLIER_BPS),
+            ErrorCode::InvalidConfig
+        );
+        ctx.accounts.protocol_config.tier_royalty_bps = tier_royalty_bps;
+        
+        msg!("Certificate royalties set to {:?} bps", tier_royalty_bps);
+        Ok(())
+    }
+
+    /// Configure the XP boost from staking reward tokens: `boost_bps_per_thousand`
+    /// extra XP per 1,000 whole tokens staked, capped at `max_boost_bps`, for
+    /// `boost_secs` after staking; stakes are locked for `min_lock_secs`
+    /// (config authority only)
+    pub fn set_stake_boost(
+        ctx: Context<UpdateConfig>,
+        boost_bps_per_thousand: u16,
+        max_boost_bps: u16,
+        boost_secs: i64,
+        min_lock_secs: i64,
+    ) -> Result<()> {
+        require!(boost_secs >= 0 && min_lock_secs >= 0, ErrorCode::InvalidConfig);
+        
+        let config = &mut ctx.accounts.protocol_config;
+        config.stake_boost_bps_per_thousand = boost_bps_per_thousand;
+        config.max_stake_boost_bps = max_boost_bps;
+        config.stake_boost_secs = boost_secs;
+        config.stake_min_lock_secs = min_lock_secs;
+        
+        msg!(
+            "Stake boost set to {} bps per 1000 tokens (max {} bps) for {}s, {}s lock",
+            boost_bps_per_thousand,
+            max_boost_bps,
+            boost_secs,
+            min_lock_secs
+        );
+        Ok(())
+    }
+
+    /// Set the backend key whose attestations self-reported quiz submissions
+    /// require; the default pubkey turns attestation off (config authority only)
+    pub fn set_attestor(ctx: Context<UpdateConfig>, attestor: Pubkey) -> Result<()> {
+        ctx.accounts.protocol_config.attestor = attestor;
+        
+        msg!("Quiz attestor set to {}", attestor);
+        Ok(())
+    }
+
+    /// Set the largest course completion reward, and the reward above which
+    /// a new course waits for `approve_course` (config authority only)
+    pub fn set_course_reward_limits(
+        ctx: Context<UpdateConfig>,
+        max_course_reward_xp: u64,
+        course_approval_xp: u64,
+    ) -> Result<()> {
+        require!(course_approval_xp <= max_course_reward_xp, ErrorCode::InvalidConfig);
+        
+        let config = &mut ctx.accounts.protocol_config;
+        config.max_course_reward_xp = max_course_reward_xp;
+        config.course_approval_xp = course_approval_xp;
+        
+        msg!(
+            "Course rewards capped at {} XP, approval needed above {} XP",
+            max_course_reward_xp,
+            course_approval_xp
+        );
+        Ok(())
+    }
+
+    /// Grow a config created under an older layout to the current size
+    /// (config authority only).
+    ///
+    /// New trailing fields are zero-initialized, except the course reward
+    /// limits, which start at their defaults. Running it on an up-to-date
+    /// config only refreshes the version.
+    pub fn migrate_config(ctx: Context<MigrateConfig>) -> Result<()> {
+        let config_info = ctx.accounts.protocol_config.to_account_info();
+        let new_len = 8 + ProtocolConfig::INIT_SPACE;
+        
+        if config_info.data_len() < new_len {
+            let rent_exempt_lamports = Rent::get()?.minimum_balance(new_len);
+            let lamports_needed = rent_exempt_lamports.saturating_sub(config_info.lamports());
+            if lamports_needed > 0 {
+                system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        system_program::Transfer {
+                            from: ctx.accounts.authority.to_account_info(),
+                            to: config_info.clone(),
+                        },
+                    ),
+                    lamports_needed,
+                )?;
+            }
+            config_info.realloc(new_len, true)?;
+        }
+        
+        let mut config = ProtocolConfig::try_deserialize(&mut &config_info.data.borrow()[..])?;
+        require_keys_eq!(config.authority, ctx.accounts.authority.key(), ErrorCode::Unauthorized);
+        let old_version = config.version;
+        if old_version < 2 {
+            config.max_course_reward_xp = DEFAULT_MAX_COURSE_REWARD_XP;
+            config.course_approval_xp = DEFAULT_COURSE_APPROVAL_XP;
+        }
+        config.version = CONFIG_VERSION;
+        
+        config.try_serialize(&mut &mut config_info.data.borrow_mut()[..])?;
+        
+        msg!("Protocol config migrated from version {} to {}", old_version, CONFIG_VERSION);
+        Ok(())
+    }
+
+    /// Pause or resume every state-changing user instruction (config authority only)
+    pub fn set_pause(ctx: Context<UpdateConfig>, paused: bool) -> Result<()> {
+        ctx.accounts.protocol_config.paused = paused;
+        
+        msg!("Program paused: {}", paused);
+        Ok(())
+    }
+
+    /// Enable token rewards: `tokens_per_xp` base units of `reward_mint` accrue
+    /// per XP earned on attempts scoring at least `min_reward_score_bps` of the
+    /// available points, and are minted by `claim_rewards`. The mint authority
+    /// must be the program's `mint_authority` PDA.
+    pub fn set_reward_mint(
+        ctx: Context<SetRewardMint>,
+        tokens_per_xp: u64,
+        min_reward_score_bps: u16,
+    ) -> Result<()> {
+        require!(min_reward_score_bps <= BASE_MULTIPLIER_BPS, ErrorCode::InvalidConfig);
+        let (mint_authority, mint_authority_bump) =
+            Pubkey::find_program_address(&[b"mint_authority"], ctx.program_id);
+        require!(
+            ctx.accounts.reward_mint.mint_authority == Some(mint_authority).into(),
+            ErrorCode::InvalidRewardMint
+        );
+        
+        let config = &mut ctx.accounts.protocol_config;
+        config.reward_mint = ctx.accounts.reward_mint.key();
+        config.tokens_per_xp = tokens_per_xp;
+        config.mint_authority_bump = mint_authority_bump;
+        config.min_reward_score_bps = min_reward_score_bps;
+        
+        msg!(
+            "Reward mint set to: {} ({} tokens per XP from {} bps score)",
+            config.reward_mint,
+            tokens_per_xp,
+            min_reward_score_bps
+        );
+        Ok(())
+    }
+
     /// Initialize a new user profile
     pub fn initialize_profile(
         ctx: Context<InitializeProfile>,
         username: String,
+        referral_code: Option<[u8; 8]>,
     ) -> Result<()> {
+        let username = normalize_username(&username)?;
+        
+        // A freshly created username registry entry has no owning profile yet
+        let username_registry = &mut ctx.accounts.username_registry;
+        require!(
+            username_registry.profile == Pubkey::default(),
+            ErrorCode::UsernameTaken
+        );
+        username_registry.profile = ctx.accounts.user_profile.key();
+        username_registry.owner = ctx.accounts.authority.key();
+        username_registry.registered_at = Clock::get()?.unix_timestamp;
+        
         let profile = &mut ctx.accounts.user_profile;
         profile.authority = ctx.accounts.authority.key();
+        profile.profile_id = profile.authority;
         profile.username = username;
         profile.xp = 0;
         profile.level = 1;
         profile.streak = 0;
         profile.quizzes_completed = 0;
         profile.achievements_earned = 0;
+        profile.streak_frozen = false;
+        profile.last_streak_day = 0;
+        profile.bio = String::new();
+        profile.last_username_change = 0;
+        profile.bump = ctx.bumps.user_profile;
+        profile.last_quiz_at = 0;
+        profile.last_daily_claim = 0;
+        profile.weekly_xp = 0;
+        profile.week_index = 0;
+        profile.tokens_minted_today = 0;
+        profile.token_mint_day = 0;
+        profile.referrals_count = 0;
+        profile.pending_rewards = 0;
+        profile.avatar_uri = String::new();
+        profile.social_link = String::new();
         profile.created_at = Clock::get()?.unix_timestamp;
         profile.last_active = Clock::get()?.unix_timestamp;
         
+        ctx.accounts.global_stats.record_user()?;
+        
+        // Every profile gets a referral code; a code given here records the
+        // referrer, whose bonus is paid by `claim_referral_reward`
+        profile.referral_code = referral_code_for(&profile.profile_id);
+        let referral_registry = &mut ctx.accounts.referral_registry;
+        referral_registry.referrer = profile.profile_id;
+        referral_registry.code = profile.referral_code;
+        
+        profile.referred_by = match referral_code {
+            Some(referral_code) => {
+                let referrer = ctx
+                    .accounts
+                    .referrer_registry
+                    .as_ref()
+                    .ok_or(ErrorCode::InvalidReferrer)?
+                    .referrer_of(referral_code, &profile.profile_id)?;
+                msg!("Referred by: {}", referrer);
+                Some(referrer)
+            }
+            None => None,
+        };
+        profile.referral_claimed = false;
+        profile.schema_version = PROFILE_SCHEMA_VERSION;
+        profile.guild = None;
+        profile.delegate = None;
+        profile.submission_nonce = 0;
+        profile.open_challenges = 0;
+        profile.tournament_locked_until = 0;
+        profile.monthly_xp = 0;
+        profile.month_index = 0;
+        profile.milestone_awards = 0;
+        
+        emit_cpi!(ProfileInitialized {
+            user: profile.authority,
+            username: profile.username.clone(),
+            created_at: profile.created_at,
+        });
+        
         msg!("User profile initialized for: {}", profile.username);
         Ok(())
     }
 
-    /// Submit a quiz attempt and calculate rewards
+    /// Submit a quiz attempt and calculate rewards.
+    ///
+    /// Attempts are numbered from 0 per quiz. Retakes only earn the XP above
+    /// the best previous attempt. Reaching a count in `QUIZ_MILESTONES` emits
+    /// `AchievementEligible`; threshold achievements whose definition and PDA
+    /// are passed in `remaining_accounts` are awarded directly (see
+    /// `award_threshold_achievements`). Quizzes with answer hashes must go
+    /// through `start_quiz` and `reveal_quiz` instead.
+    ///
+    /// Every submission variant takes the profile's current
+    /// `submission_nonce`, which then increments by one.
+    ///
+    /// While the config has an attestor, the score must be attested: the
+    /// instruction right before this one must be an ed25519 program
+    /// instruction in which the attestor signs `attestation_message` with
+    /// `attestation`, no more than `ATTESTATION_MAX_AGE` seconds ago. The
+    /// message also covers the time taken and hints used, which feed into
+    /// XP. It binds the attempt and nonce, so an attestation is good for a
+    /// single submission.
+    #[allow(clippy::too_many_arguments)]
     pub fn submit_quiz(
-        ctx: Context<SubmitQuiz>,
+        mut ctx: Context<SubmitQuiz>,
         quiz_id: String,
+        attempt: u16,
         score: u8,
         total_questions: u8,
+        time_taken_seconds: u32,
+        hints_used: u8,
+        submission_nonce: u64,
+        attestation: [u8; 64],
+        attested_at: i64,
+    ) -> Result<()> {
+        require!(
+            !ctx.accounts.quiz_metadata.is_graded_on_chain(),
+            ErrorCode::QuizGradedOnChain
+        );
+        let graded = GradedScore::flat(score, total_questions);
+        let message = attestation_message(
+            &ctx.accounts.user_profile.authority,
+            &quiz_id,
+            attempt,
+            &graded,
+            time_taken_seconds,
+            hints_used,
+            submission_nonce,
+            attested_at,
+        );
+        ctx.accounts.protocol_config.ensure_attested(
+            ctx.accounts.instructions_sysvar.as_deref(),
+            &message,
+            &attestation,
+            attested_at,
+            Clock::get()?.unix_timestamp,
+        )?;
+        
+        record_quiz_submission(
+            &mut ctx,
+            quiz_id,
+            attempt,
+            graded,
+            time_taken_seconds,
+            hints_used,
+            submission_nonce,
+        )
+    }
+
+    /// Submit a quiz attempt with partial credit per question.
+    ///
+    /// `points` holds the points earned on each question and is checked
+    /// against the quiz's `question_points`. XP scales with points; the legacy
+    /// score counts the questions that earned full points. The graded points
+    /// are attested as in `submit_quiz`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn submit_quiz_weighted(
+        mut ctx: Context<SubmitQuiz>,
+        quiz_id: String,
+        attempt: u16,
+        points: Vec<u16>,
+        time_taken_seconds: u32,
+        hints_used: u8,
+        submission_nonce: u64,
+        attestation: [u8; 64],
+        attested_at: i64,
+    ) -> Result<()> {
+        let quiz_metadata = &ctx.accounts.quiz_metadata;
+        require!(!quiz_metadata.is_graded_on_chain(), ErrorCode::QuizGradedOnChain);
+        require!(!quiz_metadata.question_points.is_empty(), ErrorCode::InvalidQuizMetadata);
+        
+        let graded = GradedScore::weighted(
+            &points,
+            &quiz_metadata.question_points,
+            quiz_metadata.total_questions,
+        )?;
+        let message = attestation_message(
+            &ctx.accounts.user_profile.authority,
+            &quiz_id,
+            attempt,
+            &graded,
+            time_taken_seconds,
+            hints_used,
+            submission_nonce,
+            attested_at,
+        );
+        ctx.accounts.protocol_config.ensure_attested(
+            ctx.accounts.instructions_sysvar.as_deref(),
+            &message,
+            &attestation,
+            attested_at,
+            Clock::get()?.unix_timestamp,
+        )?;
+        
+        record_quiz_submission(
+            &mut ctx,
+            quiz_id,
+            attempt,
+            graded,
+            time_taken_seconds,
+            hints_used,
+            submission_nonce,
+        )
+    }
+
+    /// Commit to a set of answers before revealing them with `reveal_quiz`.
+    ///
+    /// `commitment` is `answer_commitment(answers, salt)`. The reveal must
+    /// follow within `QUIZ_REVEAL_WINDOW` seconds.
+    pub fn start_quiz(
+        ctx: Context<StartQuiz>,
+        quiz_id: String,
+        attempt: u16,
+        commitment: [u8; 32],
+    ) -> Result<()> {
+        ctx.accounts.protocol_config.ensure_not_paused()?;
+        require!(ctx.accounts.quiz_metadata.is_active, ErrorCode::QuizInactive);
+        
+        let quiz_attempt = &mut ctx.accounts.quiz_attempt;
+        quiz_attempt.user = ctx.accounts.user_profile.profile_id;
+        quiz_attempt.quiz_id = quiz_id;
+        quiz_attempt.attempt = attempt;
+        quiz_attempt.commitment = commitment;
+        quiz_attempt.started_at = Clock::get()?.unix_timestamp;
+        quiz_attempt.revealed = false;
+        
+        msg!("Quiz started: {} (attempt {})", quiz_attempt.quiz_id, attempt);
+        Ok(())
+    }
+
+    /// Reveal the answers committed in `start_quiz`, grade them against the
+    /// quiz's answer hashes and record the result as `submit_quiz` does.
+    #[allow(clippy::too_many_arguments)]
+    pub fn reveal_quiz(
+        mut ctx: Context<SubmitQuiz>,
+        quiz_id: String,
+        attempt: u16,
+        answers: Vec<u8>,
+        salt: [u8; 32],
+        time_taken_seconds: u32,
+        hints_used: u8,
+        submission_nonce: u64,
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let quiz_metadata = &ctx.accounts.quiz_metadata;
+        let quiz_attempt = ctx
+            .accounts
+            .quiz_attempt
+            .as_mut()
+            .ok_or(ErrorCode::QuizNotStarted)?;
+        
+        quiz_attempt.reveal(&answers, &salt, current_time)?;
+        require!(!quiz_metadata.answer_hashes.is_empty(), ErrorCode::InvalidQuizMetadata);
+        require!(
+            answers.len() == quiz_metadata.answer_hashes.len(),
+            ErrorCode::QuizMismatch
+        );
+        
+        let score = answers
+            .iter()
+            .zip(&quiz_metadata.answer_hashes)
+            .enumerate()
+            .filter(|(question, (answer, expected))| {
+                answer_hash(&quiz_id, *question as u8, **answer) == **expected
+            })
+            .count() as u8;
+        let total_questions = quiz_metadata.total_questions;
+        
+        record_quiz_submission(
+            &mut ctx,
+            quiz_id,
+            attempt,
+            GradedScore::flat(score, total_questions),
+            time_taken_seconds,
+            hints_used,
+            submission_nonce,
+        )
+    }
+
+    /// Grade a chunk of answers against the quiz's answer Merkle root.
+    ///
+    /// Each answer carries the correct answer hash for its question and a
+    /// proof that `(question_index, correct_answer_hash)` is in the tree. Large
+    /// quizzes are answered over several calls of at most
+    /// `MAX_PROVEN_ANSWERS_PER_CALL` answers into the attempt opened by
+    /// `start_quiz` (its commitment is unused here); the call that answers the
+    /// last question records the result as `submit_quiz` does.
+    pub fn submit_quiz_with_proof(
+        mut ctx: Context<SubmitQuiz>,
+        quiz_id: String,
+        attempt: u16,
+        answers: Vec<ProvenAnswer>,
+        time_taken_seconds: u32,
+        hints_used: u8,
+        submission_nonce: u64,
     ) -> Result<()> {
-        require!(score <= total_questions, ErrorCode::InvalidScore);
+        require!(answers.len() <= MAX_PROVEN_ANSWERS_PER_CALL, ErrorCode::TooManyAnswers);
+        
+        let quiz_metadata = &ctx.accounts.quiz_metadata;
+        let root = quiz_metadata.answers_merkle_root;
+        let total_questions = quiz_metadata.total_questions;
+        require!(root != [0u8; 32], ErrorCode::InvalidQuizMetadata);
+        
+        let quiz_attempt = ctx
+            .accounts
+            .quiz_attempt
+            .as_mut()
+            .ok_or(ErrorCode::QuizNotStarted)?;
+        require!(!quiz_attempt.revealed, ErrorCode::AnswersAlreadyRevealed);
+        
+        quiz_attempt.grade_proven(&quiz_id, &root, total_questions, &answers)?;
+        
+        if quiz_attempt.answered_count < total_questions {
+            msg!(
+                "Quiz {} answered {}/{}",
+                quiz_id,
+                quiz_attempt.answered_count,
+                total_questions
+            );
+            return Ok(());
+        }
+        quiz_attempt.revealed = true;
+        let score = quiz_attempt.correct_count;
+        
+        record_quiz_submission(
+            &mut ctx,
+            quiz_id,
+            attempt,
+            GradedScore::flat(score, total_questions),
+            time_taken_seconds,
+            hints_used,
+            submission_nonce,
+        )
+    }
+
+    /// Award a catalog achievement to a user (config or achievement authority only).
+    ///
+    /// Name, tier and bonus XP are copied from the achievement's definition,
+    /// which must be active and have supply left.
+    pub fn award_achievement(ctx: Context<AwardAchievement>, achievement_id: String) -> Result<()> {
+        ctx.accounts.protocol_config.ensure_not_paused()?;
         
         let profile = &mut ctx.accounts.user_profile;
-        let quiz_result = &mut ctx.accounts.quiz_result;
+        let achievement = &mut ctx.accounts.achievement;
+        let definition = &mut ctx.accounts.achievement_definition;
+        let old_level = profile.level;
         
-        // Calculate XP earned (base 10 XP per correct answer, bonus for perfect score)
-        let xp_earned = (score as u64) * 10 + if score == total_questions { 50 } else { 0 };
+        require!(achievement_id.len() <= MAX_ID_LEN, ErrorCode::IdTooLong);
+        definition.take_supply()?;
         
-        // Update profile
-        profile.xp += xp_earned;
-        profile.quizzes_completed += 1;
-        profile.last_active = Clock::get()?.unix_timestamp;
+        ensure_progress_completed(&ctx.accounts.achievement_progress)?;
         
-        // Calculate new level (every 100 XP = 1 level)
-        profile.level = (profile.xp / 100) + 1;
+        if let Some(required_id) = &definition.prerequisite_achievement_id {
+            let prerequisite = ctx
+                .accounts
+                .prerequisite_achievement
+                .as_ref()
+                .ok_or(ErrorCode::PrerequisiteNotMet)?;
+            require!(
+                prerequisite.user == profile.profile_id
+                    && prerequisite.achievement_id == *required_id
+                    && prerequisite.awarded_at != 0,
+                ErrorCode::PrerequisiteNotMet
+            );
+        }
         
-        // Update streak
-        let time_since_last_active = Clock::get()?.unix_timestamp - profile.last_active;
-        if time_since_last_active <= 86400 { // 24 hours
-            profile.streak += 1;
-        } else {
-            profile.streak = 1;
+        // Bonus XP for achievements
+        let bonus_xp = definition.award_bonus(&ctx.accounts.protocol_config);
+        achievement.award(
+            profile,
+            achievement_id,
+            definition.name.clone(),
+            definition.tier.clone(),
+            bonus_xp,
+            Clock::get()?.unix_timestamp,
+            ctx.bumps.achievement,
+        )?;
+        profile.level = level_for_xp(profile.xp, &ctx.accounts.protocol_config.level_curve);
+        profile.accrue_token_rewards(
+            bonus_xp,
+            &ctx.accounts.protocol_config,
+            achievement.awarded_at,
+        )?;
+        
+        ctx.accounts.global_stats.record_awards(1, bonus_xp)?;
+        
+        if let Some(leaderboard) = &ctx.accounts.leaderboard {
+            leaderboard.load_mut()?.record(profile, achievement.awarded_at)?;
+        }
+        if let Some(period_leaderboard) = &ctx.accounts.period_leaderboard {
+            period_leaderboard.load_mut()?.record(profile, achievement.awarded_at)?;
+        }
+        if let Some(weekly_leaderboard) = &ctx.accounts.weekly_leaderboard {
+            weekly_leaderboard.load_mut()?.record(profile, achievement.awarded_at)?;
         }
         
-        // Store quiz result
-        quiz_result.user = profile.authority;
-        quiz_result.quiz_id = quiz_id.clone();
-        quiz_result.score = score;
-        quiz_result.total_questions = total_questions;
-        quiz_result.xp_earned = xp_earned;
-        quiz_result.completed_at = Clock::get()?.unix_timestamp;
+        emit_cpi!(AchievementAwarded {
+            user: profile.authority,
+            username: profile.username.clone(),
+            achievement_id: achievement.achievement_id.clone(),
+            achievement_name: achievement.achievement_name.clone(),
+            tier: achievement.tier.clone(),
+            bonus_xp,
+            total_xp: profile.xp,
+            new_level: profile.level,
+            awarded_at: achievement.awarded_at,
+        });
+        if profile.level > old_level {
+            emit_cpi!(LevelUp {
+                user: profile.authority,
+                old_level,
+                new_level: profile.level,
+            });
+        }
         
-        msg!("Quiz completed! Score: {}/{}, XP earned: {}", score, total_questions, xp_earned);
+        msg!("Achievement unlocked: {} ({})", achievement.achievement_name, achievement.tier);
         Ok(())
     }
 
-    /// Award an achievement to a user
-    pub fn award_achievement(
-        ctx: Context<AwardAchievement>,
+    /// Add an achievement to the catalog, with the achievement that must be
+    /// held before it can be awarded (config authority only).
+    ///
+    /// `bonus_xp` overrides the configured tier bonus; with `None` the bonus
+    /// follows `set_tier_bonuses` at award time. A `max_supply` of
+    /// zero means the achievement can be awarded without limit.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_achievement_definition(
+        ctx: Context<CreateAchievementDefinition>,
         achievement_id: String,
-        achievement_name: String,
-        achievement_tier: AchievementTier,
+        name: String,
+        tier: AchievementTier,
+        bonus_xp: Option<u64>,
+        max_supply: u32,
+        prerequisite_achievement_id: Option<String>,
+        metadata_uri: String,
     ) -> Result<()> {
-        let profile = &mut ctx.accounts.user_profile;
-        let achievement = &mut ctx.accounts.achievement;
+        require!(achievement_id.len() <= MAX_ID_LEN, ErrorCode::IdTooLong);
+        require!(!name.is_empty(), ErrorCode::InvalidAchievementDefinition);
+        require!(name.len() <= 128, ErrorCode::TextTooLong);
+        require!(metadata_uri.len() <= MAX_METADATA_URI_LEN, ErrorCode::TextTooLong);
+        if let Some(prerequisite_id) = &prerequisite_achievement_id {
+            require!(prerequisite_id.len() <= MAX_ID_LEN, ErrorCode::IdTooLong);
+            require!(*prerequisite_id != achievement_id, ErrorCode::InvalidAchievementDefinition);
+        }
         
-        achievement.user = profile.authority;
-        achievement.achievement_id = achievement_id;
-        achievement.achievement_name = achievement_name;
-        achievement.tier = achievement_tier;
-        achievement.awarded_at = Clock::get()?.unix_timestamp;
+        let definition = &mut ctx.accounts.achievement_definition;
+        definition.achievement_id = achievement_id;
+        definition.prerequisite_achievement_id = prerequisite_achievement_id;
+        definition.metadata_uri = metadata_uri;
+        definition.created_at = Clock::get()?.unix_timestamp;
+        definition.bonus_xp = bonus_xp;
+        definition.name = name;
+        definition.tier = tier;
+        definition.max_supply = max_supply;
+        definition.remaining_supply = max_supply;
+        definition.is_active = true;
         
-        profile.achievements_earned += 1;
+        msg!("Achievement defined: {}", definition.achievement_id);
+        Ok(())
+    }
+
+    /// Enable or retire a catalog achievement (config authority only)
+    pub fn set_achievement_definition_active(
+        ctx: Context<UpdateAchievementDefinition>,
+        _achievement_id: String,
+        is_active: bool,
+    ) -> Result<()> {
+        let definition = &mut ctx.accounts.achievement_definition;
+        definition.is_active = is_active;
         
-        // Bonus XP for achievements
-        let bonus_xp = match achievement.tier {
-            AchievementTier::Bronze => 50,
-            AchievementTier::Silver => 100,
-            AchievementTier::Gold => 200,
-            AchievementTier::Platinum => 500,
-        };
-        profile.xp += bonus_xp;
+        msg!("Achievement {} active: {}", definition.achievement_id, is_active);
+        Ok(())
+    }
+
+    /// Record progress towards a multi-step achievement (config or
+    /// achievement authority only).
+    ///
+    /// `target` is only used when the tracker is first created; later calls
+    /// keep the original target.
+    pub fn increment_achievement_progress(
+        ctx: Context<IncrementAchievementProgress>,
+        achievement_id: String,
+        target: u64,
+        amount: u64,
+    ) -> Result<()> {
+        require!(achievement_id.len() <= MAX_ID_LEN, ErrorCode::IdTooLong);
+        
+        let progress = &mut ctx.accounts.achievement_progress;
+        if progress.target == 0 {
+            require!(target > 0, ErrorCode::InvalidProgressTarget);
+            progress.user = ctx.accounts.user_profile.profile_id;
+            progress.achievement_id = achievement_id;
+            progress.target = target;
+        }
+        
+        progress.current = progress.current.checked_add(amount).ok_or(ErrorCode::NumericOverflow)?;
+        progress.completed = progress.current >= progress.target;
         
-        msg!("Achievement unlocked: {} ({})", achievement.achievement_name, achievement_tier);
+        msg!(
+            "Achievement progress for {}: {}/{}",
+            progress.achievement_id,
+            progress.current,
+            progress.target
+        );
         Ok(())
     }
 
     /// Update user streak
     pub fn update_streak(ctx: Context<UpdateStreak>) -> Result<()> {
+        ctx.accounts.protocol_config.ensure_not_paused()?;
+        
+        let config = &ctx.accounts.protocol_config;
         let profile = &mut ctx.accounts.user_profile;
         let current_time = Clock::get()?.unix_timestamp;
-        let time_since_last_active = current_time - profile.last_active;
+        let old_streak = profile.streak;
+        let old_level = profile.level;
         
-        if time_since_last_active <= 86400 { // 24 hours
-            profile.streak += 1;
-        } else {
-            profile.streak = 1;
+        if let Some(streak_freeze) = ctx.accounts.streak_freeze.as_mut() {
+            if profile.use_banked_freeze(
+                streak_freeze,
+                current_time,
+                config.streak_window_secs,
+                config.streak_grace_secs,
+            ) {
+                msg!("Streak freeze used. Freezes remaining: {}", streak_freeze.count);
+            }
         }
+        profile.record_activity(current_time, config.streak_window_secs, config.streak_grace_secs)?;
         
-        profile.last_active = current_time;
+        emit_cpi!(StreakUpdated {
+            user: profile.authority,
+            username: profile.username.clone(),
+            streak: profile.streak,
+            last_active: profile.last_active,
+        });
+        
+        if profile.streak > old_streak {
+            if let Some(bonus_xp) = config.streak_milestone_bonus(profile.streak) {
+                profile.earn_xp(bonus_xp, current_time)?;
+                profile.level = level_for_xp(profile.xp, &config.level_curve);
+                
+                emit_cpi!(StreakMilestoneReached {
+                    user: profile.authority,
+                    streak: profile.streak,
+                    bonus_xp,
+                });
+                
+                // Streak milestones also earn their catalog achievement when
+                // its account and definition are supplied
+                let streak_achievement = match (
+                    ctx.accounts.milestone_achievement.as_ref(),
+                    ctx.accounts.milestone_definition.as_mut(),
+                ) {
+                    (Some(achievement), Some(definition)) => award_milestone_achievement(
+                        achievement,
+                        definition,
+                        profile,
+                        config,
+                        &ctx.accounts.authority.to_account_info(),
+                        &ctx.accounts.system_program.to_account_info(),
+                        current_time,
+                        ctx.bumps.milestone_achievement.unwrap_or_default(),
+                    )?,
+                    _ => None,
+                };
+                profile.level = level_for_xp(profile.xp, &config.level_curve);
+                if let Some(achievement) = streak_achievement {
+                    emit_cpi!(AchievementAwarded {
+                        user: profile.authority,
+                        username: profile.username.clone(),
+                        achievement_id: achievement.achievement_id,
+                        achievement_name: achievement.achievement_name,
+                        tier: achievement.tier,
+                        bonus_xp: achievement.bonus_xp,
+                        total_xp: profile.xp,
+                        new_level: profile.level,
+                        awarded_at: achievement.awarded_at,
+                    });
+                }
+                if profile.level > old_level {
+                    emit_cpi!(LevelUp {
+                        user: profile.authority,
+                        old_level,
+                        new_level: profile.level,
+                    });
+                }
+            }
+        }
         
         msg!("Streak updated: {}", profile.streak);
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-pub struct InitializeProfile<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + UserProfile::INIT_SPACE,
-        seeds = [b"user_profile", authority.key().as_ref()],
-        bump
-    )]
-    pub user_profile: Account<'info, UserProfile>,
-    
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
-}
+    /// Claim the daily check-in bonus, at most once every 24 hours.
+    ///
+    /// Counts as activity for the streak, so checking in on consecutive days
+    /// keeps the streak going without completing a quiz.
+    pub fn claim_daily_bonus(ctx: Context<DailyBonusClaim>) -> Result<()> {
+        ctx.accounts.protocol_config.ensure_not_paused()?;
+        
+        let config = &ctx.accounts.protocol_config;
+        let profile = &mut ctx.accounts.user_profile;
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(
+            profile.last_daily_claim == 0
+                || current_time.saturating_sub(profile.last_daily_claim) >= SECONDS_PER_DAY,
+            ErrorCode::DailyBonusAlreadyClaimed
+        );
+        let old_streak = profile.streak;
+        let old_level = profile.level;
+        
+        profile.record_activity(current_time, config.streak_window_secs, config.streak_grace_secs)?;
+        let streak_bonus_xp = if profile.streak > old_streak {
+            config.streak_milestone_bonus(profile.streak)
+        } else {
+            None
+        };
+        
+        let bonus_xp = config
+            .daily_bonus_xp
+            .checked_add(streak_bonus_xp.unwrap_or(0))
+            .ok_or(ErrorCode::NumericOverflow)?;
+        profile.earn_xp(bonus_xp, current_time)?;
+        profile.level = level_for_xp(profile.xp, &config.level_curve);
+        profile.last_daily_claim = current_time;
+        
+        emit_cpi!(DailyBonusClaimed {
+            user: profile.authority,
+            bonus_xp: config.daily_bonus_xp,
+            streak: profile.streak,
+            claimed_at: current_time,
+        });
+        if let Some(bonus_xp) = streak_bonus_xp {
+            emit_cpi!(StreakMilestoneReached {
+                user: profile.authority,
+                streak: profile.streak,
+                bonus_xp,
+            });
+        }
+        if profile.level > old_level {
+            emit_cpi!(LevelUp {
+                user: profile.authority,
+                old_level,
+                new_level: profile.level,
+            });
+        }
+        
+        msg!("Daily bonus claimed: {} XP, streak {}", config.daily_bonus_xp, profile.streak);
+        Ok(())
+    }
 
-#[derive(Accounts)]
-#[instruction(quiz_id: String)]
-pub struct SubmitQuiz<'info> {
-    #[account(
-        mut,
-        seeds = [b"user_profile", authority.key().as_ref()],
-        bump,
-        has_one = authority
-    )]
-    pub user_profile: Account<'info, UserProfile>,
-    
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + QuizResult::INIT_SPACE,
-        seeds = [b"quiz_result", authority.key().as_ref(), quiz_id.as_bytes()],
-        bump
-    )]
-    pub quiz_result: Account<'info, QuizResult>,
-    
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    
+    /// Earn a streak freeze for reaching a streak milestone
+    pub fn earn_streak_freeze(ctx: Context<EarnStreakFreeze>) -> Result<()> {
+        let profile = &ctx.accounts.user_profile;
+        let streak_freeze = &mut ctx.accounts.streak_freeze;
+        
+        // One freeze every STREAK_FREEZE_MILESTONE days, claimable once per milestone
+        let milestone = profile.streak / STREAK_FREEZE_MILESTONE * STREAK_FREEZE_MILESTONE;
+        require!(
+            milestone > 0 && milestone > streak_freeze.last_milestone,
+            ErrorCode::StreakMilestoneNotReached
+        );
+        
+        streak_freeze.bank(profile.profile_id)?;
+        streak_freeze.last_milestone = milestone;
+        
+        msg!("Streak freeze earned! Available freezes: {}", streak_freeze.count);
+        Ok(())
+    }
+
+    /// Buy a streak freeze with XP at the configured price
+    pub fn buy_streak_freeze(ctx: Context<BuyStreakFreeze>) -> Result<()> {
+        let config = &ctx.accounts.protocol_config;
+        config.ensure_not_paused()?;
+        require!(config.streak_freeze_cost_xp > 0, ErrorCode::StreakFreezeUnavailable);
+        
+        let profile = &mut ctx.accounts.user_profile;
+        let streak_freeze = &mut ctx.accounts.streak_freeze;
+        streak_freeze.bank(profile.profile_id)?;
+        
+        profile.xp = profile
+            .xp
+            .checked_sub(config.streak_freeze_cost_xp)
+            .ok_or(ErrorCode::InsufficientXp)?;
+        profile.level = level_for_xp(profile.xp, &config.level_curve);
+        
+        msg!("Streak freeze bought! Available freezes: {}", streak_freeze.count);
+        Ok(())
+    }
+
+    /// Spend a banked streak freeze to protect the current streak
+    pub fn freeze_streak(ctx: Context<FreezeStreak>) -> Result<()> {
+        let profile = &mut ctx.accounts.user_profile;
+        let streak_freeze = &mut ctx.accounts.streak_freeze;
+        
+        require!(!profile.streak_frozen, ErrorCode::StreakAlreadyFrozen);
+        require!(streak_freeze.count > 0, ErrorCode::NoStreakFreezes);
+        
+        streak_freeze.count -= 1;
+        profile.streak_frozen = true;
+        
+        msg!("Streak frozen at {}. Freezes remaining: {}", profile.streak, streak_freeze.count);
+        Ok(())
+    }
+
+    /// Grow a profile created under an older layout to the current size.
+    ///
+    /// New trailing fields are zero-initialized, then backfilled from existing
+    /// data where possible. Running it on an up-to-date profile is a no-op.
+    /// Profiles created before the bump was stored must be migrated before
+    /// `submit_quiz`, `award_achievement` or `update_streak` accept them.
+    pub fn migrate_profile(ctx: Context<MigrateProfile>) -> Result<()> {
+        let profile_info = ctx.accounts.user_profile.to_account_info();
+        let new_len = 8 + UserProfile::INIT_SPACE;
+        
+        if profile_info.data_len() < new_len {
+            let rent_exempt_lamports = Rent::get()?.minimum_balance(new_len);
+            let lamports_needed = rent_exempt_lamports.saturating_sub(profile_info.lamports());
+            if lamports_needed > 0 {
+                system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        system_program::Transfer {
+                            from: ctx.accounts.authority.to_account_info(),
+                            to: profile_info.clone(),
+                        },
+                    ),
+                    lamports_needed,
+                )?;
+            }
+            profile_info.realloc(new_len, true)?;
+        }
+        
+        let mut profile = UserProfile::try_deserialize(&mut &profile_info.data.borrow()[..])?;
+        require_keys_eq!(profile.authority, ctx.accounts.authority.key(), ErrorCode::Unauthorized);
+        profile.upgrade(ctx.bumps.user_profile);
+        
+        profile.try_serialize(&mut &mut profile_info.data.borrow_mut()[..])?;
+        
+        msg!("User profile migrated for: {}", profile.username);
+        Ok(())
+    }
+
+    /// Close a user profile and return its rent to the owner.
+    ///
+    /// This is irreversible: XP, level and streak are lost, and a profile
+    /// created again later starts from scratch.
+    ///
+    /// The username registry, referral registry and streak freeze bank are
+    /// closed with it, so the name and the referral code can't outlive it. Quiz
+    /// results, quiz progress and achievements are separate accounts and are
+    /// not closed here; quiz results can be reclaimed with `close_quiz_result`.
+    ///
+    /// A profile can't be closed while it is in a guild, has an unresolved
+    /// challenge holding its wager, or is entered in a running tournament.
+    pub fn close_profile(ctx: Context<CloseProfile>) -> Result<()> {
+        // A profile that gave its name away has no registry entry to release
+        require!(
+            ctx.accounts.user_profile.username.is_empty()
+                || ctx.accounts.username_registry.is_some(),
+            ErrorCode::UsernameRegistryRequired
+        );
+        // Profiles that predate referral codes may not have registered one
+        require!(
+            ctx.accounts.user_profile.referral_code == [0; 8]
+                || ctx.accounts.referral_registry.is_some(),
+            ErrorCode::ReferralRegistryRequired
+        );
+        require!(
+            ctx.accounts.stake_position.data_is_empty(),
+            ErrorCode::ProfileHasActiveStake
+        );
+        let current_time = Clock::get()?.unix_timestamp;
+        ctx.accounts.user_profile.ensure_closable(current_time)?;
+        
+        let global_stats = &mut ctx.accounts.global_stats;
+        global_stats.total_users = global_stats
+            .total_users
+            .checked_sub(1)
+            .ok_or(ErrorCode::NumericOverflow)?;
+        
+        emit_cpi!(ProfileClosed {
+            user: ctx.accounts.authority.key(),
+            username: ctx.accounts.user_profile.username.clone(),
+            xp: ctx.accounts.user_profile.xp,
+            closed_at: current_time,
+        });
+        
+        msg!("User profile closed for: {}", ctx.accounts.user_profile.username);
+        Ok(())
+    }
+
+    /// Close a quiz result and return its rent to the owner.
+    ///
+    /// Only results older than `MIN_RECORD_AGE` can be closed, so recent bad
+    /// scores can't be hidden. Profile counters are left untouched.
+    pub fn close_quiz_result(
+        ctx: Context<CloseQuizResult>,
+        quiz_id: String,
+        attempt: u16,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            is_record_closable(ctx.accounts.quiz_result.completed_at, now),
+            ErrorCode::RecordTooRecent
+        );
+        
+        msg!("Quiz result closed for quiz: {} (attempt {})", quiz_id, attempt);
+        Ok(())
+    }
+
+    /// Close an achievement record and return most of its rent to the owner.
+    ///
+    /// Subject to the same `MIN_RECORD_AGE` as quiz results. Bonus XP and the
+    /// profile's achievement count are kept, and a certificate NFT, if any,
+    /// stays in the owner's wallet. The account shrinks to a tombstone (see
+    /// `shrink_to_tombstone`) rather than being deleted, so the achievement
+    /// can't be awarded into it again. Blank records that were never awarded,
+    /// which older versions created for unreached streak milestones, are
+    /// deleted outright and can be closed at any time.
+    pub fn close_achievement(ctx: Context<CloseAchievement>, achievement_id: String) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let authority = ctx.accounts.authority.to_account_info();
+        let achievement = &mut ctx.accounts.achievement;
+        require!(is_record_closable(achievement.awarded_at, now), ErrorCode::RecordTooRecent);
+        
+        if achievement.awarded_at == 0 {
+            achievement.close(authority)?;
+        } else {
+            shrink_to_tombstone(achievement, &authority)?;
+        }
+        
+        msg!("Achievement closed: {}", achievement_id);
+        Ok(())
+    }
+
+    /// Allow a key to publish quizzes (config authority only)
+    pub fn grant_quiz_creator(ctx: Context<GrantQuizCreator>, creator: Pubkey) -> Result<()> {
+        let quiz_creator = &mut ctx.accounts.quiz_creator;
+        quiz_creator.creator = creator;
+        quiz_creator.granted_at = Clock::get()?.unix_timestamp;
+        
+        msg!("Quiz creator granted: {}", creator);
+        Ok(())
+    }
+
+    /// Stop a key from publishing new quizzes (config authority only).
+    /// Quizzes it already published are unaffected.
+    pub fn revoke_quiz_creator(ctx: Context<RevokeQuizCreator>, creator: Pubkey) -> Result<()> {
+        msg!("Quiz creator revoked: {}", creator);
+        Ok(())
+    }
+
+    /// Revoke a mistaken or fraudulent achievement (config authority only).
+    ///
+    /// The bonus XP it granted is taken back, saturating at zero, and the
+    /// level is recomputed. The award goes back into the definition's supply
+    /// and out of the global stats. A certificate NFT already minted is not
+    /// burned.
+    pub fn revoke_achievement(
+        ctx: Context<RevokeAchievement>,
+        achievement_id: String,
+    ) -> Result<()> {
+        let config = &ctx.accounts.protocol_config;
+        let profile = &mut ctx.accounts.user_profile;
+        let achievement = &ctx.accounts.achievement;
+        let old_level = profile.level;
+        
+        let xp_removed = profile.revoke_achievement(achievement.bonus_xp, &config.level_curve);
+        restore_definition_supply(&ctx.accounts.achievement_definition)?;
+        
+        let global_stats = &mut ctx.accounts.global_stats;
+        global_stats.total_achievements_awarded =
+            global_stats.total_achievements_awarded.saturating_sub(1);
+        global_stats.total_xp_minted = global_stats.total_xp_minted.saturating_sub(xp_removed);
+        
+        emit_cpi!(AchievementRevoked {
+            user: profile.authority,
+            achievement_id: achievement_id.clone(),
+            xp_removed,
+            old_level,
+            new_level: profile.level,
+        });
+        
+        msg!("Achievement revoked: {} ({} XP removed)", achievement_id, xp_removed);
+        Ok(())
+    }
+
+    /// Publish a quiz; the signer must hold the quiz creator role and becomes
+    /// the quiz's creator
+    pub fn create_quiz_metadata(
+        ctx: Context<CreateQuizMetadata>,
+        quiz_id: String,
+        title: String,
+        description: String,
+        category: QuizCategory,
+        difficulty: Difficulty,
+        total_questions: u8,
+    ) -> Result<()> {
+        require!(quiz_id.len() <= MAX_ID_LEN, ErrorCode::IdTooLong);
+        require!(is_valid_id(&quiz_id), ErrorCode::InvalidQuizMetadata);
+        require!(title.len() <= 128, ErrorCode::TextTooLong);
+        require!(description.len() <= 256, ErrorCode::TextTooLong);
+        require!(total_questions >= 1, ErrorCode::EmptyQuiz);
+        
+        let quiz_metadata = &mut ctx.accounts.quiz_metadata;
+        quiz_metadata.creator = ctx.accounts.creator.key();
+        quiz_metadata.quiz_id = quiz_id;
+        quiz_metadata.title = title;
+        quiz_metadata.description = description;
+        quiz_metadata.category = category;
+        quiz_metadata.difficulty = difficulty;
+        quiz_metadata.created_at = Clock::get()?.unix_timestamp;
+        quiz_metadata.is_active = true;
+        quiz_metadata.is_event = false;
+        quiz_metadata.total_questions = total_questions;
+        quiz_metadata.answer_hashes = Vec::new();
+        quiz_metadata.question_points = Vec::new();
+        
+        let quiz_stats = &mut ctx.accounts.quiz_stats;
+        quiz_stats.quiz = quiz_metadata.key();
+        
+        msg!("Quiz created: {} ({})", quiz_metadata.title, quiz_metadata.quiz_id);
+        Ok(())
+    }
+
+    /// Edit a quiz's details (creator only). Past results keep the multiplier
+    /// they were scored with.
+    pub fn update_quiz(
+        ctx: Context<UpdateQuiz>,
+        quiz_id: String,
+        title: String,
+        description: String,
+        difficulty: Difficulty,
+        total_questions: u8,
+    ) -> Result<()> {
+        require!(title.len() <= 128, ErrorCode::TextTooLong);
+        require!(description.len() <= 256, ErrorCode::TextTooLong);
+        require!(total_questions >= 1, ErrorCode::EmptyQuiz);
+        
+        let quiz_metadata = &mut ctx.accounts.quiz_metadata;
+        quiz_metadata.title = title;
+        quiz_metadata.description = description;
+        quiz_metadata.difficulty = difficulty;
+        if quiz_metadata.total_questions != total_questions {
+            // Answers no longer line up with the questions
+            quiz_metadata.answer_hashes.clear();
+            quiz_metadata.answers_merkle_root = [0u8; 32];
+            quiz_metadata.question_points.clear();
+        }
+        quiz_metadata.total_questions = total_questions;
+        
+        msg!("Quiz updated: {}", quiz_id);
+        Ok(())
+    }
+
+    /// Store the per-question answer hashes used to grade `reveal_quiz`
+    /// (creator only). Each entry is `answer_hash(quiz_id, question, answer)`.
+    pub fn set_quiz_answers(
+        ctx: Context<SetQuizAnswers>,
+        quiz_id: String,
+        answer_hashes: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let quiz_metadata = &mut ctx.accounts.quiz_metadata;
+        require!(answer_hashes.len() <= MAX_QUIZ_QUESTIONS, ErrorCode::TooManyQuestions);
+        require!(
+            answer_hashes.len() == quiz_metadata.total_questions as usize,
+            ErrorCode::QuizMismatch
+        );
+        quiz_metadata.answer_hashes = answer_hashes;
+        
+        msg!("Answers set for quiz: {}", quiz_id);
+        Ok(())
+    }
+
+    /// Store the Merkle root of the quiz's answers for
+    /// `submit_quiz_with_proof` (creator only). Leaves are
+    /// `answer_leaf(question, answer_hash(quiz_id, question, answer))`.
+    pub fn set_answers_merkle_root(
+        ctx: Context<SetQuizAnswers>,
+        quiz_id: String,
+        answers_merkle_root: [u8; 32],
+    ) -> Result<()> {
+        ctx.accounts.quiz_metadata.answers_merkle_root = answers_merkle_root;
+        
+        msg!("Answer root set for quiz: {}", quiz_id);
+        Ok(())
+    }
+
+    /// Set the maximum points for each question, enabling
+    /// `submit_quiz_weighted` (creator only)
+    pub fn set_question_points(
+        ctx: Context<SetQuizAnswers>,
+        quiz_id: String,
+        question_points: Vec<u16>,
+    ) -> Result<()> {
+        let quiz_metadata = &mut ctx.accounts.quiz_metadata;
+        require!(question_points.len() <= MAX_QUIZ_QUESTIONS, ErrorCode::TooManyQuestions);
+        require!(
+            question_points.len() == quiz_metadata.total_questions as usize,
+            ErrorCode::QuizMismatch
+        );
+        require!(question_points.iter().all(|&points| points > 0), ErrorCode::InvalidQuizMetadata);
+        quiz_metadata.question_points = question_points;
+        
+        msg!("Question points set for quiz: {}", quiz_id);
+        Ok(())
+    }
+
+    /// Set how long after `start_quiz` a submission still counts; 0 removes
+    /// the limit (creator only)
+    pub fn set_quiz_time_limit(
+        ctx: Context<UpdateQuiz>,
+        quiz_id: String,
+        time_limit_secs: i64,
+    ) -> Result<()> {
+        require!(time_limit_secs >= 0, ErrorCode::InvalidQuizMetadata);
+        ctx.accounts.quiz_metadata.time_limit_secs = time_limit_secs;
+        
+        msg!("Quiz {} time limit set to {}s", quiz_id, time_limit_secs);
+        Ok(())
+    }
+
+    /// Stop accepting submissions for a quiz (creator only). Its leaderboard
+    /// is frozen from then on.
+    pub fn deactivate_quiz(ctx: Context<DeactivateQuiz>, quiz_id: String) -> Result<()> {
+        ctx.accounts.quiz_metadata.is_active = false;
+        
+        msg!("Quiz deactivated: {}", quiz_id);
+        Ok(())
+    }
+
+    /// Mark a quiz as an event quiz, exempt from the submission cooldown
+    /// (config authority only)
+    pub fn set_event_quiz(
+        ctx: Context<SetEventQuiz>,
+        quiz_id: String,
+        is_event: bool,
+    ) -> Result<()> {
+        ctx.accounts.quiz_metadata.is_event = is_event;
+        
+        msg!("Quiz {} event status: {}", quiz_id, is_event);
+        Ok(())
+    }
+
+    /// Create the leaderboard of the given type for one ranking period.
+    ///
+    /// Weekly and monthly boards exist per period (see
+    /// `LeaderboardType::period_at`) and can be created for the current or a
+    /// later one. The all-time board has the single period 0.
+    pub fn initialize_leaderboard(
+        ctx: Context<InitializeLeaderboard>,
+        leaderboard_type: LeaderboardType,
+        period: u32,
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let current_period = leaderboard_type.period_at(current_time);
+        if leaderboard_type == LeaderboardType::AllTime {
+            require!(period == current_period, ErrorCode::InvalidPeriod);
+        } else {
+            require!(period >= current_period, ErrorCode::InvalidPeriod);
+        }
+        
+        let mut leaderboard = ctx.accounts.leaderboard.load_init()?;
+        leaderboard.count = 0;
+        leaderboard.reset_at = current_time;
+        leaderboard.leaderboard_type = leaderboard_type as u8;
+        leaderboard.finalized = 0;
+        leaderboard.period_index = period;
+        
+        msg!("{:?} leaderboard initialized for period {}", leaderboard_type, period);
+        Ok(())
+    }
+
+    /// Insert or refresh a user's position on a leaderboard for the current
+    /// period, ranked by the XP earned in that period.
+    ///
+    /// `submit_quiz` and `award_achievement` also refresh the boards passed
+    /// to them; clients call this for boards they didn't pass.
+    pub fn update_leaderboard(
+        ctx: Context<UpdateLeaderboard>,
+        _leaderboard_type: LeaderboardType,
+        _period: u32,
+    ) -> Result<()> {
+        let profile = &ctx.accounts.user_profile;
+        let mut leaderboard = ctx.accounts.leaderboard.load_mut()?;
+        
+        let ranked = leaderboard.record(profile, Clock::get()?.unix_timestamp)?;
+        
+        if ranked {
+            msg!("Leaderboard updated for: {}", profile.username);
+        } else {
+            msg!("{} does not place on the leaderboard", profile.username);
+        }
+        Ok(())
+    }
+
+    /// Freeze a past period's weekly or monthly leaderboard so rewards can be
+    /// distributed against it (config authority only)
+    pub fn finalize_leaderboard(
+        ctx: Context<FinalizeLeaderboard>,
+        leaderboard_type: LeaderboardType,
+        period: u32,
+    ) -> Result<()> {
+        require!(leaderboard_type != LeaderboardType::AllTime, ErrorCode::InvalidPeriod);
+        require!(
+            period < leaderboard_type.period_at(Clock::get()?.unix_timestamp),
+            ErrorCode::PeriodNotOver
+        );
+        
+        let mut leaderboard = ctx.accounts.leaderboard.load_mut()?;
+        require!(leaderboard.finalized == 0, ErrorCode::LeaderboardFinalized);
+        leaderboard.finalized = 1;
+        
+        msg!(
+            "{:?} leaderboard for period {} finalized with {} entries",
+            leaderboard_type,
+            period,
+            leaderboard.count
+        );
+        Ok(())
+    }
+
+    /// Create the weekly leaderboard for the current or a later week
+    pub fn init_weekly_leaderboard(ctx: Context<InitWeeklyLeaderboard>, week: u32) -> Result<()> {
+        require!(week >= week_index(Clock::get()?.unix_timestamp), ErrorCode::InvalidWeek);
+        
+        let mut weekly_leaderboard = ctx.accounts.weekly_leaderboard.load_init()?;
+        weekly_leaderboard.week_index = week;
+        weekly_leaderboard.count = 0;
+        weekly_leaderboard.finalized = 0;
+        
+        msg!("Weekly leaderboard initialized for week {}", week);
+        Ok(())
+    }
+
+    /// Insert or refresh a user's position on the current week's leaderboard
+    pub fn update_weekly_leaderboard(
+        ctx: Context<UpdateWeeklyLeaderboard>,
+        _week: u32,
+    ) -> Result<()> {
+        let profile = &ctx.accounts.user_profile;
+        let ranked = ctx
+            .accounts
+            .weekly_leaderboard
+            .load_mut()?
+            .record(profile, Clock::get()?.unix_timestamp)?;
+        
+        if ranked {
+            msg!("Weekly leaderboard updated for: {}", profile.username);
+        } else {
+            msg!("{} does not place on the weekly leaderboard", profile.username);
+        }
+        Ok(())
+    }
+
+    /// Freeze a past week's leaderboard so rewards can be distributed
+    /// against it (config authority only)
+    pub fn finalize_week(ctx: Context<FinalizeWeek>, week: u32) -> Result<()> {
+        let mut weekly_leaderboard = ctx.accounts.weekly_leaderboard.load_mut()?;
+        weekly_leaderboard.finalize(Clock::get()?.unix_timestamp)?;
+        
+        msg!("Week {} finalized with {} entries", week, weekly_leaderboard.count);
+        Ok(())
+    }
+
+    /// Recompute a profile's level from its XP under the current level curve.
+    ///
+    /// Profiles keep the level stored under the curve in force when they last
+    /// earned XP, so after the curve changes this brings them back in line.
+    /// Anyone may call it.
+    pub fn sync_level(ctx: Context<SyncLevel>) -> Result<()> {
+        let profile = &mut ctx.accounts.user_profile;
+        let old_level = profile.level;
+        profile.level = level_for_xp(profile.xp, &ctx.accounts.protocol_config.level_curve);
+        
+        if profile.level != old_level {
+            emit_cpi!(LevelUp {
+                user: profile.authority,
+                old_level,
+                new_level: profile.level,
+            });
+        }
+        
+        msg!("Level synced for {}: {} -> {}", profile.username, old_level, profile.level);
+        Ok(())
+    }
+
+    /// Clear every entry on a leaderboard that is not finalized (config
+    /// authority only)
+    pub fn admin_reset_leaderboard(
+        ctx: Context<AdminResetLeaderboard>,
+        leaderboard_type: LeaderboardType,
+        period: u32,
+    ) -> Result<()> {
+        let mut leaderboard = ctx.accounts.leaderboard.load_mut()?;
+        leaderboard.reset(Clock::get()?.unix_timestamp)?;
+        
+        msg!("{:?} leaderboard for period {} reset", leaderboard_type, period);
+        Ok(())
+    }
+
+    /// Mint a soulbound 1-of-1 NFT certificate for a Gold, Platinum or Diamond
+    /// achievement.
+    ///
+    /// Bronze and Silver achievements stay plain accounts. The certificate mint
+    /// is derived from the achievement, so there is at most one; calling this
+    /// again once it exists is a no-op. The holder's token account is frozen by
+    /// the mint authority PDA, so the certificate cannot be transferred. The
+    /// metadata URI of the achievement's definition takes precedence over
+    /// `metadata_uri` when the definition is supplied and has one.
+    pub fn mint_achievement_certificate(
+        ctx: Context<MintAchievementCertificate>,
+        metadata_uri: String,
+    ) -> Result<()> {
+        if let Some(nft_mint) = ctx.accounts.achievement.nft_mint {
+            msg!("Certificate already minted: {}", nft_mint);
+            return Ok(());
+        }
+        require!(!ctx.accounts.achievement.is_closed(), ErrorCode::AchievementClosed);
+        
+        let metadata_uri = match &ctx.accounts.achievement_definition {
+            Some(definition) if !definition.metadata_uri.is_empty() => {
+                definition.metadata_uri.clone()
+            }
+            _ => metadata_uri,
+        };
+        require!(metadata_uri.len() <= MAX_METADATA_URI_LEN, ErrorCode::TextTooLong);
+        require!(
+            ctx.accounts.achievement.tier.has_certificate(),
+            ErrorCode::CertificateTierTooLow
+        );
+        
+        let signer_seeds: &[&[&[u8]]] =
+            &[&[b"mint_authority".as_ref(), &[ctx.bumps.mint_authority]]];
+        
+        token::mint_to(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                MintTo {
+                    mint: ctx.accounts.certificate_mint.to_account_info(),
+                    to: ctx.accounts.user_token_account.to_account_info(),
+                    authority: ctx.accounts.mint_authority.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            1,
+        )?;
+        
+        // Freezing the holder's account makes the certificate soulbound. It
+        // must happen now: the master edition takes over the freeze authority.
+        token::freeze_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            FreezeAccount {
+                account: ctx.accounts.user_token_account.to_account_info(),
+                mint: ctx.accounts.certificate_mint.to_account_info(),
+                authority: ctx.accounts.mint_authority.to_account_info(),
+            },
+            signer_seeds,
+        ))?;
+        
+        let achievement = &ctx.accounts.achievement;
+        let name = achievement.certificate_name();
+        let royalty_bps = ctx.accounts.protocol_config.tier_royalty_bps(&achievement.tier);
+        
+        create_metadata_accounts_v3(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_metadata_program.to_account_info(),
+                CreateMetadataAccountsV3 {
+                    metadata: ctx.accounts.metadata.to_account_info(),
+                    mint: ctx.accounts.certificate_mint.to_account_info(),
+                    mint_authority: ctx.accounts.mint_authority.to_account_info(),
+                    payer: ctx.accounts.awarder.to_account_info(),
+                    update_authority: ctx.accounts.mint_authority.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                    rent: ctx.accounts.rent.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            DataV2 {
+                name,
+                symbol: "MENTOR".to_string(),
+                uri: metadata_uri,
+                seller_fee_basis_points: royalty_bps,
+                creators: None,
+                collection: None,
+                uses: None,
+            },
+            false,
+            true,
+            None,
+        )?;
+        
+        create_master_edition_v3(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_metadata_program.to_account_info(),
+                CreateMasterEditionV3 {
+                    edition: ctx.accounts.master_edition.to_account_info(),
+                    mint: ctx.accounts.certificate_mint.to_account_info(),
+                    update_authority: ctx.accounts.mint_authority.to_account_info(),
+                    mint_authority: ctx.accounts.mint_authority.to_account_info(),
+                    payer: ctx.accounts.awarder.to_account_info(),
+                    metadata: ctx.accounts.metadata.to_account_info(),
+                    token_program: ctx.accounts.token_program.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                    rent: ctx.accounts.rent.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            Some(0),
+        )?;
+        
+        let achievement = &mut ctx.accounts.achievement;
+        achievement.nft_mint = Some(ctx.accounts.certificate_mint.key());
+        
+        msg!("Certificate minted for: {} ({})", achievement.achievement_name, achievement.tier);
+        Ok(())
+    }
+
+    /// Hand a username over to another profile.
+    ///
+    /// Both owners sign. The registry entry moves to the new profile, the old
+    /// profile is left without a name, and any name the new profile held is
+    /// released, so no registry entry is ever orphaned.
+    pub fn transfer_username(ctx: Context<TransferUsername>) -> Result<()> {
+        let old_profile = &mut ctx.accounts.old_profile;
+        let new_profile = &mut ctx.accounts.new_profile;
+        require!(!old_profile.username.is_empty(), ErrorCode::InvalidUsername);
+        require!(
+            new_profile.username.is_empty() || ctx.accounts.new_profile_registry.is_some(),
+            ErrorCode::UsernameRegistryRequired
+        );
+        
+        let username_registry = &mut ctx.accounts.username_registry;
+        username_registry.profile = new_profile.key();
+        username_registry.owner = ctx.accounts.new_authority.key();
+        username_registry.registered_at = Clock::get()?.unix_timestamp;
+        
+        new_profile.username = std::mem::take(&mut old_profile.username);
+        
+        msg!("Username {} transferred to: {}", new_profile.username, username_registry.owner);
+        Ok(())
+    }
+
+    /// Update the profile's bio and, optionally, its username.
+    ///
+    /// The account is grown to the current layout if needed. Profiles too old
+    /// to deserialize must go through `migrate_profile` first. Renames are
+    /// subject to the same cooldown as `update_username`.
+    pub fn update_profile(
+        ctx: Context<UpdateProfile>,
+        new_username: Option<String>,
+        bio: String,
+    ) -> Result<()> {
+        ctx.accounts.protocol_config.ensure_not_paused()?;
+        require!(bio.len() <= MAX_BIO_LEN, ErrorCode::TextTooLong);
+        
+        if let Some(new_username) = new_username {
+            rename_profile(
+                &mut ctx.accounts.user_profile,
+                ctx.accounts.current_registry.as_ref(),
+                ctx.accounts.new_registry.as_mut(),
+                &ctx.accounts.authority.to_account_info(),
+                &new_username,
+                Clock::get()?.unix_timestamp,
+            )?;
+        }
+        let profile = &mut ctx.accounts.user_profile;
+        profile.bio = bio;
+        
+        msg!("Profile updated for: {}", profile.username);
+        Ok(())
+    }
+
+    /// Set the profile's bio, avatar URI and social link.
+    ///
+    /// The account is grown to the current layout if needed. Profiles too old
+    /// to deserialize must go through `migrate_profile` first.
+    pub fn update_metadata(
+        ctx: Context<UpdateMetadata>,
+        bio: String,
+        avatar_uri: String,
+        social_link: String,
+    ) -> Result<()> {
+        ctx.accounts.protocol_config.ensure_not_paused()?;
+        
+        let profile = &mut ctx.accounts.user_profile;
+        profile.set_metadata(bio, avatar_uri, social_link)?;
+        
+        msg!("Profile metadata updated for: {}", profile.username);
+        Ok(())
+    }
+
+    /// Move a profile to a new wallet.
+    ///
+    /// Profiles are seeded by their authority, so the data is copied into the
+    /// new wallet's profile PDA and the old one is closed. Quiz progress and
+    /// results, achievements, streak freezes and enrollments are seeded by the
+    /// profile id, which doesn't change, so they move with the profile and
+    /// can't be earned a second time from the new wallet. The referral code
+    /// and its registry entry stay with the profile too: the entry keeps the
+    /// old wallet from creating a profile with the same id, and referees
+    /// still claim against the moved profile. Both wallets must sign.
+    ///
+    /// A profile that is staking, in a guild, in an unresolved challenge or
+    /// in a running tournament cannot move, since those accounts are keyed by
+    /// the wallet.
+    pub fn transfer_profile_authority(ctx: Context<TransferAuthority>) -> Result<()> {
+        require!(
+            ctx.accounts.old_profile.username.is_empty()
+                || ctx.accounts.username_registry.is_some(),
+            ErrorCode::UsernameRegistryRequired
+        );
+        require!(
+            ctx.accounts.stake_position.data_is_empty(),
+            ErrorCode::ProfileHasActiveStake
+        );
+        ctx.accounts
+            .old_profile
+            .ensure_closable(Clock::get()?.unix_timestamp)?;
+        
+        let new_authority = ctx.accounts.new_authority.key();
+        let mut profile = (*ctx.accounts.old_profile).clone();
+        profile.authority = new_authority;
+        profile.bump = ctx.bumps.new_profile;
+        profile.delegate = None;
+        ctx.accounts.new_profile.set_inner(profile);
+        
+        if let Some(username_registry) = &mut ctx.accounts.username_registry {
+            username_registry.profile = ctx.accounts.new_profile.key();
+            username_registry.owner = new_authority;
+        }
+        
+        msg!(
+            "Profile {} moved from {} to {}",
+            ctx.accounts.new_profile.username,
+            ctx.accounts.old_authority.key(),
+            new_authority
+        );
+        Ok(())
+    }
+
+    /// Rename the profile, at most once every `USERNAME_CHANGE_COOLDOWN` seconds.
+    pub fn update_username(ctx: Context<UpdateUsername>, new_username: String) -> Result<()> {
+        ctx.accounts.protocol_config.ensure_not_paused()?;
+        
+        rename_profile(
+            &mut ctx.accounts.user_profile,
+            ctx.accounts.current_registry.as_ref(),
+            Some(&mut ctx.accounts.new_registry),
+            &ctx.accounts.authority.to_account_info(),
+            &new_username,
+            Clock::get()?.unix_timestamp,
+        )?;
+        
+        msg!("Username changed to: {}", ctx.accounts.user_profile.username);
+        Ok(())
+    }
+
+    /// Publish a course: an ordered sequence of lesson quizzes. The signer
+    /// must hold the quiz creator role.
+    ///
+    /// The completion reward is capped by the config, and a reward above the
+    /// config's approval threshold can't be collected until the config
+    /// authority approves the course.
+    pub fn create_course(
+        ctx: Context<CreateCourse>,
+        course_id: String,
+        title: String,
+        lesson_quiz_ids: Vec<String>,
+        required_pass_score: u8,
+        completion_reward_xp: u64,
+    ) -> Result<()> {
+        require!(course_id.len() <= MAX_ID_LEN, ErrorCode::IdTooLong);
+        require!(is_valid_id(&course_id), ErrorCode::InvalidCourse);
+        require!(title.len() <= 128, ErrorCode::TextTooLong);
+        require!(
+            !lesson_quiz_ids.is_empty() && lesson_quiz_ids.len() <= MAX_COURSE_LESSONS,
+            ErrorCode::InvalidCourse
+        );
+        require!(
+            lesson_quiz_ids
+                .iter()
+                .all(|quiz_id| quiz_id.len() <= MAX_ID_LEN && is_valid_id(quiz_id)),
+            ErrorCode::InvalidCourse
+        );
+        require!(required_pass_score > 0, ErrorCode::InvalidCourse);
+        let approved = ctx.accounts.protocol_config.course_reward_approved(completion_reward_xp)?;
+        
+        let course = &mut ctx.accounts.course;
+        course.creator = ctx.accounts.creator.key();
+        course.course_id = course_id;
+        course.title = title;
+        course.lesson_quiz_ids = lesson_quiz_ids;
+        course.required_pass_score = required_pass_score;
+        course.completion_reward_xp = completion_reward_xp;
+        course.created_at = Clock::get()?.unix_timestamp;
+        course.approved = approved;
+        
+        msg!("Course created: {} ({} lessons)", course.title, course.lesson_quiz_ids.len());
+        Ok(())
+    }
+
+    /// Approve a course whose completion reward is above the config's
+    /// approval threshold (config authority only)
+    pub fn approve_course(ctx: Context<ApproveCourse>, course_id: String) -> Result<()> {
+        let course = &mut ctx.accounts.course;
+        require!(
+            course.completion_reward_xp <= ctx.accounts.protocol_config.max_course_reward_xp,
+            ErrorCode::CourseRewardTooHigh
+        );
+        course.approved = true;
+        
+        msg!("Course approved: {} ({} XP reward)", course_id, course.completion_reward_xp);
+        Ok(())
+    }
+
+    /// Enroll in a course
+    pub fn enroll_in_course(ctx: Context<EnrollInCourse>, course_id: String) -> Result<()> {
+        let enrollment = &mut ctx.accounts.enrollment;
+        enrollment.user = ctx.accounts.user_profile.profile_id;
+        enrollment.course = ctx.accounts.course.key();
+        enrollment.lessons_completed = 0;
+        enrollment.enrolled_at = Clock::get()?.unix_timestamp;
+        enrollment.completed_at = 0;
+        
+        msg!("Enrolled in course: {}", course_id);
+        Ok(())
+    }
+
+    /// Complete a course and collect its XP reward.
+    ///
+    /// The user's `QuizProgress` account for every lesson, in course order,
+    /// is passed as remaining accounts; each must show a best score of at
+    /// least the course's pass score.
+    pub fn complete_course(ctx: Context<CompleteCourse>, course_id: String) -> Result<()> {
+        ctx.accounts.protocol_config.ensure_not_paused()?;
+        
+        let course = &ctx.accounts.course;
+        let enrollment = &mut ctx.accounts.enrollment;
+        let profile = &mut ctx.accounts.user_profile;
+        require!(course.approved, ErrorCode::CourseNotApproved);
+        require!(enrollment.completed_at == 0, ErrorCode::CourseAlreadyCompleted);
+        require!(
+            ctx.remaining_accounts.len() == course.lesson_quiz_ids.len(),
+            ErrorCode::CourseIncomplete
+        );
+        
+        for (quiz_id, progress_info) in course.lesson_quiz_ids.iter().zip(ctx.remaining_accounts) {
+            let (expected, _) = Pubkey::find_program_address(
+                &[b"quiz_progress", profile.profile_id.as_ref(), &id_seed(quiz_id)],
+                &crate::ID,
+            );
+            require_keys_eq!(progress_info.key(), expected, ErrorCode::CourseIncomplete);
+            require!(progress_info.owner == &crate::ID, ErrorCode::CourseIncomplete);
+            let progress = QuizProgress::try_deserialize(&mut &progress_info.data.borrow()[..])?;
+            require!(
+                progress.best_score >= course.required_pass_score,
+                ErrorCode::CourseIncomplete
+            );
+        }
+        
+        let current_time = Clock::get()?.unix_timestamp;
+        let old_level = profile.level;
+        profile.earn_xp(course.completion_reward_xp, current_time)?;
+        profile.level = level_for_xp(profile.xp, &ctx.accounts.protocol_config.level_curve);
+        
+        enrollment.lessons_completed = course.lesson_quiz_ids.len() as u64;
+        enrollment.completed_at = current_time;
+        
+        if profile.level > old_level {
+            emit_cpi!(LevelUp {
+                user: profile.authority,
+                old_level,
+                new_level: profile.level,
+            });
+        }
+        
+        msg!("Course completed: {} (+{} XP)", course_id, course.completion_reward_xp);
+        Ok(())
+    }
+
+    /// Publish a learning path: an ordered sequence of courses whose
+    /// completion earns a badge achievement. The signer must hold the quiz
+    /// creator role, and the badge must already be in the achievement catalog.
+    pub fn create_learning_path(
+        ctx: Context<CreateLearningPath>,
+        path_id: String,
+        title: String,
+        courses: Vec<String>,
+        completion_badge_achievement_id: String,
+    ) -> Result<()> {
+        require!(path_id.len() <= MAX_ID_LEN, ErrorCode::IdTooLong);
+        require!(is_valid_id(&path_id), ErrorCode::InvalidLearningPath);
+        require!(title.len() <= 128, ErrorCode::TextTooLong);
+        require!(
+            !courses.is_empty() && courses.len() <= MAX_LEARNING_PATH_COURSES,
+            ErrorCode::InvalidLearningPath
+        );
+        require!(
+            courses.iter().all(|course_id| course_id.len() <= MAX_ID_LEN && is_valid_id(course_id)),
+            ErrorCode::InvalidLearningPath
+        );
+        require!(completion_badge_achievement_id.len() <= MAX_ID_LEN, ErrorCode::IdTooLong);
+        require!(is_valid_id(&completion_badge_achievement_id), ErrorCode::InvalidLearningPath);
+        
+        let learning_path = &mut ctx.accounts.learning_path;
+        learning_path.creator = ctx.accounts.creator.key();
+        learning_path.path_id = path_id;
+        learning_path.title = title;
+        learning_path.courses = courses;
+        learning_path.completion_badge_achievement_id = completion_badge_achievement_id;
+        learning_path.created_at = Clock::get()?.unix_timestamp;
+        
+        msg!(
+            "Learning path created: {} ({} courses)",
+            learning_path.title,
+            learning_path.courses.len()
+        );
+        Ok(())
+    }
+
+    /// Enroll in a learning path
+    pub fn enroll_in_learning_path(
+        ctx: Context<EnrollInLearningPath>,
+        path_id: String,
+    ) -> Result<()> {
+        let enrollment = &mut ctx.accounts.path_enrollment;
+        enrollment.user = ctx.accounts.user_profile.profile_id;
+        enrollment.learning_path = ctx.accounts.learning_path.key();
+        enrollment.courses_completed = 0;
+        enrollment.enrolled_at = Clock::get()?.unix_timestamp;
+        enrollment.completed_at = 0;
+        
+        msg!("Enrolled in learning path: {}", path_id);
+        Ok(())
+    }
+
+    /// Complete a learning path and collect its badge achievement.
+    ///
+    /// The user's `CourseEnrollment` account for every course, in path
+    /// order, is passed as remaining accounts; each course must be completed.
+    /// The badge's name, tier and bonus come from its catalog definition.
+    pub fn complete_learning_path(
+        ctx: Context<CompleteLearningPath>,
+        path_id: String,
+    ) -> Result<()> {
+        ctx.accounts.protocol_config.ensure_not_paused()?;
+        
+        let learning_path = &ctx.accounts.learning_path;
+        let enrollment = &mut ctx.accounts.path_enrollment;
+        let profile = &mut ctx.accounts.user_profile;
+        let achievement = &mut ctx.accounts.badge_achievement;
+        let definition = &mut ctx.accounts.badge_definition;
+        let old_level = profile.level;
+        require!(enrollment.completed_at == 0, ErrorCode::LearningPathAlreadyCompleted);
+        require!(
+            ctx.remaining_accounts.len() == learning_path.courses.len(),
+            ErrorCode::LearningPathIncomplete
+        );
+        
+        let enrollments = learning_path.courses.iter().zip(ctx.remaining_accounts);
+        for (course_id, enrollment_info) in enrollments {
+            let (expected, _) = Pubkey::find_program_address(
+                &[b"enrollment", profile.profile_id.as_ref(), &id_seed(course_id)],
+                &crate::ID,
+            );
+            require_keys_eq!(enrollment_info.key(), expected, ErrorCode::LearningPathIncomplete);
+            require!(enrollment_info.owner == &crate::ID, ErrorCode::LearningPathIncomplete);
+            let course_enrollment =
+                CourseEnrollment::try_deserialize(&mut &enrollment_info.data.borrow()[..])?;
+            require!(course_enrollment.completed_at != 0, ErrorCode::LearningPathIncomplete);
+        }
+        
+        let current_time = Clock::get()?.unix_timestamp;
+        enrollment.courses_completed = learning_path.courses.len() as u8;
+        enrollment.completed_at = current_time;
+        
+        // Award the completion badge, as award_achievement would
+        definition.take_supply()?;
+        let bonus_xp = definition.award_bonus(&ctx.accounts.protocol_config);
+        achievement.award(
+            profile,
+            learning_path.completion_badge_achievement_id.clone(),
+            definition.name.clone(),
+            definition.tier.clone(),
+            bonus_xp,
+            current_time,
+            ctx.bumps.badge_achievement,
+        )?;
+        profile.level = level_for_xp(profile.xp, &ctx.accounts.protocol_config.level_curve);
+        
+        emit_cpi!(AchievementAwarded {
+            user: profile.authority,
+            username: profile.username.clone(),
+            achievement_id: achievement.achievement_id.clone(),
+            achievement_name: achievement.achievement_name.clone(),
+            tier: achievement.tier.clone(),
+            bonus_xp,
+            total_xp: profile.xp,
+            new_level: profile.level,
+            awarded_at: achievement.awarded_at,
+        });
+        if profile.level > old_level {
+            emit_cpi!(LevelUp {
+                user: profile.authority,
+                old_level,
+                new_level: profile.level,
+            });
+        }
+        
+        msg!("Learning path completed: {}", path_id);
+        Ok(())
+    }
+
+    /// Register the signer as a mentor; requires the configured minimum level
+    pub fn register_mentor(
+        ctx: Context<RegisterMentor>,
+        display_name: String,
+        bio: String,
+        expertise_tags: [u8; 16],
+        hourly_rate_lamports: u64,
+    ) -> Result<()> {
+        ctx.accounts.protocol_config.ensure_not_paused()?;
+        require!(
+            ctx.accounts.user_profile.level >= ctx.accounts.protocol_config.min_mentor_level,
+            ErrorCode::MentorLevelTooLow
+        );
+        require!(!display_name.is_empty(), ErrorCode::InvalidMentorProfile);
+        require!(display_name.len() <= 64, ErrorCode::TextTooLong);
+        require!(bio.len() <= 256, ErrorCode::TextTooLong);
+        
+        let mentor = &mut ctx.accounts.mentor_profile;
+        mentor.authority = ctx.accounts.authority.key();
+        mentor.display_name = display_name;
+        mentor.bio = bio;
+        mentor.expertise_tags = expertise_tags;
+        mentor.hourly_rate_lamports = hourly_rate_lamports;
+        mentor.total_sessions = 0;
+        mentor.average_rating_basis_points = 0;
+        mentor.is_available = true;
+        mentor.registered_at = Clock::get()?.unix_timestamp;
+        mentor.bump = ctx.bumps.mentor_profile;
+        mentor.total_reviews = 0;
+        
+        msg!("Mentor registered: {}", mentor.display_name);
+        Ok(())
+    }
+
+    /// Mark the mentor as open or closed to new sessions
+    pub fn update_mentor_availability(
+        ctx: Context<UpdateMentorProfile>,
+        is_available: bool,
+    ) -> Result<()> {
+        ctx.accounts.mentor_profile.is_available = is_available;
+        
+        msg!("Mentor availability: {}", is_available);
+        Ok(())
+    }
+
+    /// Change the mentor's hourly rate
+    pub fn update_mentor_rate(
+        ctx: Context<UpdateMentorProfile>,
+        hourly_rate_lamports: u64,
+    ) -> Result<()> {
+        ctx.accounts.mentor_profile.hourly_rate_lamports = hourly_rate_lamports;
+        
+        msg!("Mentor rate set to {} lamports/hour", hourly_rate_lamports);
+        Ok(())
+    }
+
+    /// Create the top-scores board for a quiz
+    pub fn init_quiz_leaderboard(ctx: Context<InitQuizLeaderboard>, quiz_id: String) -> Result<()> {
+        let mut quiz_leaderboard = ctx.accounts.quiz_leaderboard.load_init()?;
+        quiz_leaderboard.quiz = ctx.accounts.quiz_metadata.key();
+        quiz_leaderboard.count = 0;
+        
+        msg!("Quiz leaderboard initialized: {}", quiz_id);
+        Ok(())
+    }
+
+    /// Enter an existing quiz result on the quiz's leaderboard (callable by
+    /// anyone), for results submitted without the leaderboard account.
+    ///
+    /// The leaderboard is frozen once the quiz is deactivated.
+    pub fn update_quiz_leaderboard(
+        ctx: Context<UpdateQuizLeaderboard>,
+        quiz_id: String,
+    ) -> Result<()> {
+        require!(ctx.accounts.quiz_metadata.is_active, ErrorCode::QuizInactive);
+        let quiz_result = &ctx.accounts.quiz_result;
+        require!(
+            quiz_result.quiz_id == quiz_id && quiz_result.completed_at != 0,
+            ErrorCode::QuizMismatch
+        );
+        
+        let placed = ctx
+            .accounts
+            .quiz_leaderboard
+            .load_mut()?
+            .upsert(QuizLeaderboardEntry::from_result(quiz_result));
+        
+        msg!("Quiz leaderboard {} updated for {}: placed {}", quiz_id, quiz_result.user, placed);
+        Ok(())
+    }
+
+    /// Create the protocol-wide usage counters (config authority only)
+    pub fn initialize_global_stats(ctx: Context<InitializeGlobalStats>) -> Result<()> {
+        let global_stats = &mut ctx.accounts.global_stats;
+        global_stats.total_users = 0;
+        global_stats.total_quizzes_completed = 0;
+        global_stats.total_xp_minted = 0;
+        global_stats.total_achievements_awarded = 0;
+        global_stats.created_at = Clock::get()?.unix_timestamp;
+        
+        msg!("Global stats initialized");
+        Ok(())
+    }
+
+    /// Book a session with an available mentor, escrowing the mentor's
+    /// hourly rate for the session length in the session account
+    pub fn book_session(
+        ctx: Context<BookSession>,
+        session_nonce: u64,
+        scheduled_at: i64,
+        duration_minutes: u16,
+    ) -> Result<()> {
+        ctx.accounts.protocol_config.ensure_not_paused()?;
+        let mentor = &ctx.accounts.mentor_profile;
+        require!(mentor.is_available, ErrorCode::MentorUnavailable);
+        require_keys_neq!(mentor.authority, ctx.accounts.student.key(), ErrorCode::InvalidSession);
+        require!(duration_minutes > 0, ErrorCode::InvalidSession);
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(scheduled_at > current_time, ErrorCode::InvalidSession);
+        
+        let escrow_lamports = u64::try_from(
+            mentor.hourly_rate_lamports as u128 * duration_minutes as u128 / 60,
+        )
+        .map_err(|_| ErrorCode::NumericOverflow)?;
+        
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.student.to_account_info(),
+                    to: ctx.accounts.session.to_account_info(),
+                },
+            ),
+            escrow_lamports,
+        )?;
+        
+        let session = &mut ctx.accounts.session;
+        session.student = ctx.accounts.student.key();
+        session.mentor = mentor.authority;
+        session.session_nonce = session_nonce;
+        session.scheduled_at = scheduled_at;
+        session.duration_minutes = duration_minutes;
+        session.escrow_lamports = escrow_lamports;
+        session.status = SessionStatus::Booked;
+        session.session_notes_cid = String::new();
+        session.booked_at = current_time;
+        session.bump = ctx.bumps.session;
+        
+        msg!(
+            "Session booked with {} at {} ({} lamports escrowed)",
+            mentor.display_name,
+            scheduled_at,
+            escrow_lamports
+        );
+        Ok(())
+    }
+
+    /// Mark a session as held and release the escrow to the mentor, minus
+    /// the protocol fee (mentor only)
+    pub fn complete_session(
+        ctx: Context<CompleteSession>,
+        session_notes_cid: String,
+    ) -> Result<()> {
+        require!(session_notes_cid.len() <= MAX_CID_LEN, ErrorCode::TextTooLong);
+        let session = &mut ctx.accounts.session;
+        require!(session.status == SessionStatus::Booked, ErrorCode::InvalidSessionStatus);
+        require!(
+            Clock::get()?.unix_timestamp >= session.scheduled_at,
+            ErrorCode::SessionNotStarted
+        );
+        
+        let fee = u64::try_from(
+            session.escrow_lamports as u128 * ctx.accounts.protocol_config.session_fee_bps as u128
+                / BASE_MULTIPLIER_BPS as u128,
+        )
+        .map_err(|_| ErrorCode::NumericOverflow)?;
+        let session_info = session.to_account_info();
+        move_lamports(&session_info, &ctx.accounts.fee_recipient.to_account_info(), fee)?;
+        move_lamports(
+            &session_info,
+            &ctx.accounts.mentor.to_account_info(),
+            session.escrow_lamports - fee,
+        )?;
+        
+        session.status = SessionStatus::Completed;
+        session.session_notes_cid = session_notes_cid;
+        
+        let mentor_profile = &mut ctx.accounts.mentor_profile;
+        mentor_profile.total_sessions = mentor_profile
+            .total_sessions
+            .checked_add(1)
+            .ok_or(ErrorCode::NumericOverflow)?;
+        
+        msg!(
+            "Session completed: {} lamports to mentor, {} fee",
+            session.escrow_lamports - fee,
+            fee
+        );
+        Ok(())
+    }
+
+    /// Cancel a booked session before it starts and refund the student
+    /// (student only)
+    pub fn cancel_session(ctx: Context<CancelSession>) -> Result<()> {
+        let session = &mut ctx.accounts.session;
+        require!(session.status == SessionStatus::Booked, ErrorCode::InvalidSessionStatus);
+        require!(
+            Clock::get()?.unix_timestamp < session.scheduled_at,
+            ErrorCode::SessionAlreadyStarted
+        );
+        
+        let session_info = session.to_account_info();
+        let student_info = ctx.accounts.student.to_account_info();
+        move_lamports(&session_info, &student_info, session.escrow_lamports)?;
+        session.status = SessionStatus::Refunded;
+        
+        msg!("Session cancelled: {} lamports refunded", session.escrow_lamports);
+        Ok(())
+    }
+
+    /// Correct a user's XP by a signed amount, e.g. to compensate for
+    /// downtime (config authority only). Deductions stop at zero.
+    pub fn adjust_xp(ctx: Context<AdjustXp>, delta: i64, reason_code: u16) -> Result<()> {
+        let config = &ctx.accounts.protocol_config;
+        let profile = &mut ctx.accounts.user_profile;
+        profile.adjust_xp(delta, &config.level_curve)?;
+        
+        emit_cpi!(XpAdjusted {
+            user: profile.authority,
+            delta,
+            reason_code,
+            admin: ctx.accounts.authority.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("XP adjusted by {} (reason {}): now {} XP", delta, reason_code, profile.xp);
+        Ok(())
+    }
+
+    /// Rate the mentor of a completed session (student only)
+    pub fn submit_session_review(
+        ctx: Context<SubmitSessionReview>,
+        rating: u8,
+        comment: String,
+    ) -> Result<()> {
+        require!((1..=5).contains(&rating), ErrorCode::InvalidRating);
+        require!(comment.len() <= 256, ErrorCode::TextTooLong);
+        let session = &ctx.accounts.session;
+        require!(session.status == SessionStatus::Completed, ErrorCode::InvalidSessionStatus);
+        
+        let review = &mut ctx.accounts.session_review;
+        review.session = session.key();
+        review.reviewer = ctx.accounts.reviewer.key();
+        review.mentor = session.mentor;
+        review.rating = rating;
+        review.comment = comment;
+        review.reviewed_at = Clock::get()?.unix_timestamp;
+        
+        // Running average over reviews, in basis points (5 stars = 10000)
+        let mentor_profile = &mut ctx.accounts.mentor_profile;
+        let total = mentor_profile
+            .total_reviews
+            .checked_add(1)
+            .ok_or(ErrorCode::NumericOverflow)?;
+        let sum = mentor_profile.average_rating_basis_points as u128 * (total - 1) as u128
+            + rating as u128 * 2000;
+        mentor_profile.average_rating_basis_points = (sum / total as u128) as u16;
+        mentor_profile.total_reviews = total;
+        
+        msg!(
+            "Session reviewed: {} stars, mentor average {} bps",
+            rating,
+            mentor_profile.average_rating_basis_points
+        );
+        Ok(())
+    }
+
+    /// Mint the user's pending token rewards to their associated token
+    /// account, creating it if missing. A claim with nothing pending mints
+    /// nothing.
+    pub fn claim_rewards(ctx: Context<ClaimRewards>) -> Result<()> {
+        ctx.accounts.protocol_config.ensure_not_paused()?;
+        
+        let profile = &mut ctx.accounts.user_profile;
+        let amount = profile.take_pending_rewards(ctx.accounts.protocol_config.min_reward_claim)?;
+        if amount == 0 {
+            msg!("No rewards to claim");
+            return Ok(());
+        }
+        
+        let signer_seeds: &[&[&[u8]]] =
+            &[&[b"mint_authority".as_ref(), &[ctx.accounts.protocol_config.mint_authority_bump]]];
+        token::mint_to(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                MintTo {
+                    mint: ctx.accounts.reward_mint.to_account_info(),
+                    to: ctx.accounts.user_token_account.to_account_info(),
+                    authority: ctx.accounts.mint_authority.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+        )?;
+        
+        emit_cpi!(RewardsClaimed {
+            user: profile.authority,
+            amount,
+            claimed_at: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Rewards claimed: {} tokens", amount);
+        Ok(())
+    }
+
+    /// Give a profile created before referral codes existed its code
+    pub fn register_referral_code(ctx: Context<RegisterReferralCode>) -> Result<()> {
+        let profile = &mut ctx.accounts.user_profile;
+        profile.referral_code = referral_code_for(&profile.profile_id);
+        
+        let referral_registry = &mut ctx.accounts.referral_registry;
+        referral_registry.referrer = profile.profile_id;
+        referral_registry.code = profile.referral_code;
+        
+        msg!("Referral code registered for: {}", profile.username);
+        Ok(())
+    }
+
+    /// Pay the referral bonus to both the referred user and their referrer,
+    /// once the referred user has completed a quiz. Can only be claimed once.
+    pub fn claim_referral_reward(ctx: Context<ClaimReferralReward>) -> Result<()> {
+        ctx.accounts.protocol_config.ensure_not_paused()?;
+        
+        let config = &ctx.accounts.protocol_config;
+        let profile = &mut ctx.accounts.user_profile;
+        let referrer_profile = &mut ctx.accounts.referrer_profile;
+        let bonus_xp = config.referral_bonus_xp;
+        profile.claim_referral_reward(
+            referrer_profile,
+            bonus_xp,
+            &config.level_curve,
+            Clock::get()?.unix_timestamp,
+        )?;
+        
+        ctx.accounts
+            .global_stats
+            .record_awards(0, bonus_xp.checked_mul(2).ok_or(ErrorCode::NumericOverflow)?)?;
+        
+        msg!(
+            "Referral reward claimed: {} and {} +{} XP each",
+            profile.username,
+            referrer_profile.username,
+            bonus_xp
+        );
+        Ok(())
+    }
+
+    /// Stake reward tokens in the program vault for a temporary XP boost.
+    ///
+    /// Staking more adds to the position, recomputes the boost and restarts
+    /// both the boost period and the lock.
+    pub fn stake_tokens(ctx: Context<StakeTokens>, amount: u64) -> Result<()> {
+        ctx.accounts.protocol_config.ensure_not_paused()?;
+        require!(amount > 0, ErrorCode::InvalidStakeAmount);
+        
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.user_token_account.to_account_info(),
+                    to: ctx.accounts.stake_vault.to_account_info(),
+                    authority: ctx.accounts.authority.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+        
+        let config = &ctx.accounts.protocol_config;
+        let current_time = Clock::get()?.unix_timestamp;
+        let position = &mut ctx.accounts.stake_position;
+        position.user = ctx.accounts.authority.key();
+        position.add_stake(amount, ctx.accounts.reward_mint.decimals, config, current_time)?;
+        position.bump = ctx.bumps.stake_position;
+        
+        msg!(
+            "Staked {} tokens ({} total, +{} bps XP until {})",
+            amount,
+            position.staked_amount,
+            position.boost_bps,
+            position.boost_expires_at
+        );
+        Ok(())
+    }
+
+    /// Withdraw the whole stake once the lock period has passed
+    pub fn unstake_tokens(ctx: Context<UnstakeTokens>) -> Result<()> {
+        let position = &ctx.accounts.stake_position;
+        position.ensure_unlocked(
+            ctx.accounts.protocol_config.stake_min_lock_secs,
+            Clock::get()?.unix_timestamp,
+        )?;
+        
+        let signer_seeds: &[&[&[u8]]] = &[&[b"stake_vault".as_ref(), &[ctx.bumps.stake_vault]]];
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.stake_vault.to_account_info(),
+                    to: ctx.accounts.user_token_account.to_account_info(),
+                    authority: ctx.accounts.stake_vault.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            position.staked_amount,
+        )?;
+        
+        msg!("Unstaked {} tokens", position.staked_amount);
+        Ok(())
+    }
+
+    /// Found a guild (study group); members join it with `join_guild`
+    pub fn create_guild(ctx: Context<CreateGuild>, guild_id: String, name: String) -> Result<()> {
+        ctx.accounts.protocol_config.ensure_not_paused()?;
+        require!(guild_id.len() <= MAX_ID_LEN, ErrorCode::IdTooLong);
+        require!(is_valid_id(&guild_id), ErrorCode::InvalidGuild);
+        require!(!name.is_empty(), ErrorCode::InvalidGuild);
+        require!(name.len() <= 64, ErrorCode::TextTooLong);
+        
+        let guild = &mut ctx.accounts.guild;
+        guild.guild_id = guild_id;
+        guild.name = name;
+        guild.founder = ctx.accounts.founder.key();
+        guild.member_count = 0;
+        guild.total_guild_xp = 0;
+        guild.created_at = Clock::get()?.unix_timestamp;
+        
+        msg!("Guild created: {}", guild.name);
+        Ok(())
+    }
+
+    /// Join a guild; a user belongs to at most one guild at a time
+    pub fn join_guild(ctx: Context<JoinGuild>, guild_id: String) -> Result<()> {
+        ctx.accounts.protocol_config.ensure_not_paused()?;
+        let profile = &mut ctx.accounts.user_profile;
+        require!(profile.guild.is_none(), ErrorCode::AlreadyInGuild);
+        
+        let guild = &mut ctx.accounts.guild;
+        guild.member_count = guild.member_count.checked_add(1).ok_or(ErrorCode::NumericOverflow)?;
+        profile.guild = Some(guild.key());
+        
+        // Only XP earned after joining counts towards the guild
+        let membership = &mut ctx.accounts.guild_membership;
+        membership.user = profile.authority;
+        membership.guild = guild.key();
+        membership.joined_at = Clock::get()?.unix_timestamp;
+        membership.synced_xp = profile.xp;
+        membership.contributed_xp = 0;
+        
+        msg!("{} joined guild: {}", profile.username, guild_id);
+        Ok(())
+    }
+
+    /// Add the XP a member earned since the last sync to the guild's total.
+    ///
+    /// Clients call this after `submit_quiz`. XP lost since the last sync is
+    /// not taken from the guild.
+    pub fn update_guild_xp(ctx: Context<UpdateGuildXp>, _guild_id: String) -> Result<()> {
+        let profile = &ctx.accounts.user_profile;
+        let membership = &mut ctx.accounts.guild_membership;
+        let guild = &mut ctx.accounts.guild;
+        
+        let gained = profile.xp.saturating_sub(membership.synced_xp);
+        membership.synced_xp = profile.xp;
+        membership.contributed_xp = membership
+            .contributed_xp
+            .checked_add(gained)
+            .ok_or(ErrorCode::NumericOverflow)?;
+        guild.total_guild_xp = guild
+            .total_guild_xp
+            .checked_add(gained)
+            .ok_or(ErrorCode::NumericOverflow)?;
+        
+        msg!("Guild {} +{} XP ({} total)", guild.name, gained, guild.total_guild_xp);
+        Ok(())
+    }
+
+    /// Leave a guild, taking the XP contributed to it along
+    pub fn leave_guild(ctx: Context<LeaveGuild>, guild_id: String) -> Result<()> {
+        let membership = &ctx.accounts.guild_membership;
+        let guild = &mut ctx.accounts.guild;
+        guild.member_count = guild.member_count.saturating_sub(1);
+        guild.total_guild_xp = guild.total_guild_xp.saturating_sub(membership.contributed_xp);
+        ctx.accounts.user_profile.guild = None;
+        
+        msg!("Left guild: {}", guild_id);
+        Ok(())
+    }
+
+    /// Challenge another user to a quiz, locking `wager_xp` of the
+    /// challenger's XP until the challenge is resolved
+    pub fn send_challenge(
+        ctx: Context<SendChallenge>,
+        challenged: Pubkey,
+        quiz_id: String,
+        wager_xp: u64,
+        duration_secs: i64,
+    ) -> Result<()> {
+        ctx.accounts.protocol_config.ensure_not_paused()?;
+        require!(ctx.accounts.quiz_metadata.is_active, ErrorCode::QuizInactive);
+        require!(challenged != ctx.accounts.challenger.key(), ErrorCode::InvalidChallenge);
+        require!(
+            duration_secs > 0 && duration_secs <= MAX_CHALLENGE_DURATION,
+            ErrorCode::InvalidChallenge
+        );
+        
+        let profile = &mut ctx.accounts.challenger_profile;
+        profile.xp = profile.xp.checked_sub(wager_xp).ok_or(ErrorCode::InsufficientXp)?;
+        profile.level = level_for_xp(profile.xp, &ctx.accounts.protocol_config.level_curve);
+        profile.open_challenges = profile
+            .open_challenges
+            .checked_add(1)
+            .ok_or(ErrorCode::NumericOverflow)?;
+        
+        let now = Clock::get()?.unix_timestamp;
+        let challenge = &mut ctx.accounts.challenge;
+        challenge.challenger = ctx.accounts.challenger.key();
+        challenge.challenged = challenged;
+        challenge.quiz_id = quiz_id;
+        challenge.wager_xp = wager_xp;
+        challenge.challenger_score = None;
+        challenge.challenged_score = None;
+        challenge.challenger_attempt = next_quiz_attempt(&ctx.accounts.challenger_progress)?;
+        challenge.challenged_attempt = 0;
+        challenge.status = ChallengeStatus::Pending;
+        challenge.created_at = now;
+        challenge.accepted_at = 0;
+        challenge.expires_at = now.checked_add(duration_secs).ok_or(ErrorCode::NumericOverflow)?;
+        challenge.winner = None;
+        challenge.bump = ctx.bumps.challenge;
+        
+        msg!("Challenge sent to {} on {} for {} XP", challenged, challenge.quiz_id, wager_xp);
+        Ok(())
+    }
+
+    /// Accept a pending challenge, locking the same wager from the challenged user
+    pub fn accept_challenge(ctx: Context<AcceptChallenge>) -> Result<()> {
+        ctx.accounts.protocol_config.ensure_not_paused()?;
+        let now = Clock::get()?.unix_timestamp;
+        let challenge = &mut ctx.accounts.challenge;
+        require!(challenge.status == ChallengeStatus::Pending, ErrorCode::InvalidChallengeStatus);
+        require!(now < challenge.expires_at, ErrorCode::ChallengeExpired);
+        
+        let profile = &mut ctx.accounts.challenged_profile;
+        profile.xp = profile
+            .xp
+            .checked_sub(challenge.wager_xp)
+            .ok_or(ErrorCode::InsufficientXp)?;
+        profile.level = level_for_xp(profile.xp, &ctx.accounts.protocol_config.level_curve);
+        profile.open_challenges = profile
+            .open_challenges
+            .checked_add(1)
+            .ok_or(ErrorCode::NumericOverflow)?;
+        
+        challenge.status = ChallengeStatus::Active;
+        challenge.accepted_at = now;
+        challenge.challenged_attempt = next_quiz_attempt(&ctx.accounts.challenged_progress)?;
+        
+        msg!("Challenge accepted on {}", challenge.quiz_id);
+        Ok(())
+    }
+
+    /// Record a participant's score from their first result for the
+    /// challenge quiz after entering: the challenger's after sending the
+    /// challenge, the challenged user's after accepting it
+    pub fn submit_challenge_score(ctx: Context<SubmitChallengeScore>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let participant = ctx.accounts.participant.key();
+        let quiz_result = &ctx.accounts.quiz_result;
+        let challenge = &mut ctx.accounts.challenge;
+        require!(challenge.status == ChallengeStatus::Active, ErrorCode::InvalidChallengeStatus);
+        require!(now < challenge.expires_at, ErrorCode::ChallengeExpired);
+        require!(
+            quiz_result.user == ctx.accounts.participant_profile.profile_id
+                && quiz_result.quiz_id == challenge.quiz_id,
+            ErrorCode::InvalidChallenge
+        );
+        challenge.record_score(participant, quiz_result.attempt, quiz_result.score)?;
+        
+        msg!("Challenge score {} recorded for {}", quiz_result.score, participant);
+        Ok(())
+    }
+
+    /// Settle a challenge once both scores are in, or once it has expired.
+    ///
+    /// The higher score takes both wagers; a tie refunds each side. After
+    /// expiry a lone submitted score wins, and a challenge nobody played (or
+    /// nobody accepted) refunds the wagers and is marked expired.
+    pub fn resolve_challenge(ctx: Context<ResolveChallenge>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let challenge = &mut ctx.accounts.challenge;
+        let expired = now >= challenge.expires_at;
+        let accepted = challenge.status == ChallengeStatus::Active;
+        
+        let (challenger_payout, challenged_payout) = match challenge.status {
+            ChallengeStatus::Pending if expired => {
+                challenge.status = ChallengeStatus::Expired;
+                (challenge.wager_xp, 0)
+            }
+            ChallengeStatus::Active => {
+                let pot = challenge.wager_xp.checked_mul(2).ok_or(ErrorCode::NumericOverflow)?;
+                match (challenge.challenger_score, challenge.challenged_score) {
+                    (Some(a), Some(b)) if a > b => {
+                        challenge.winner = Some(challenge.challenger);
+                        (pot, 0)
+                    }
+                    (Some(a), Some(b)) if a < b => {
+                        challenge.winner = Some(challenge.challenged);
+                        (0, pot)
+                    }
+                    (Some(_), Some(_)) => (challenge.wager_xp, challenge.wager_xp),
+                    (Some(_), None) if expired => {
+                        challenge.winner = Some(challenge.challenger);
+                        (pot, 0)
+                    }
+                    (None, Some(_)) if expired => {
+                        challenge.winner = Some(challenge.challenged);
+                        (0, pot)
+                    }
+                    (None, None) if expired => {
+                        challenge.status = ChallengeStatus::Expired;
+                        (challenge.wager_xp, challenge.wager_xp)
+                    }
+                    _ => return err!(ErrorCode::ChallengeNotResolvable),
+                }
+            }
+            _ => return err!(ErrorCode::ChallengeNotResolvable),
+        };
+        if challenge.status == ChallengeStatus::Active {
+            challenge.status = ChallengeStatus::Completed;
+        }
+        
+        let level_curve = &ctx.accounts.protocol_config.level_curve;
+        for (profile, payout, participated) in [
+            (&mut ctx.accounts.challenger_profile, challenger_payout, true),
+            (&mut ctx.accounts.challenged_profile, challenged_payout, accepted),
+        ] {
+            profile.xp = profile.xp.checked_add(payout).ok_or(ErrorCode::NumericOverflow)?;
+            profile.level = level_for_xp(profile.xp, level_curve);
+            if participated {
+                // Challenges opened before the count existed may find it at zero
+                profile.open_challenges = profile.open_challenges.saturating_sub(1);
+            }
+        }
+        
+        emit_cpi!(ChallengeResolved {
+            challenger: challenge.challenger,
+            challenged: challenge.challenged,
+            quiz_id: challenge.quiz_id.clone(),
+            wager_xp: challenge.wager_xp,
+            status: challenge.status,
+            winner: challenge.winner,
+            resolved_at: now,
+        });
+        
+        msg!("Challenge on {} resolved: {:?}", challenge.quiz_id, challenge.winner);
+        Ok(())
+    }
+
+    /// Let a session key submit quizzes and update the streak on the
+    /// profile's behalf, replacing any previous delegate
+    pub fn set_delegate(ctx: Context<SetDelegate>, delegate: Pubkey) -> Result<()> {
+        ctx.accounts.protocol_config.ensure_not_paused()?;
+        let profile = &mut ctx.accounts.user_profile;
+        require!(delegate != profile.authority, ErrorCode::InvalidDelegate);
+        profile.delegate = Some(delegate);
+        
+        msg!("Delegate set for {}: {}", profile.username, delegate);
+        Ok(())
+    }
+
+    /// Remove the profile's delegate
+    pub fn revoke_delegate(ctx: Context<SetDelegate>) -> Result<()> {
+        let profile = &mut ctx.accounts.user_profile;
+        profile.delegate = None;
+        
+        msg!("Delegate revoked for {}", profile.username);
+        Ok(())
+    }
+
+    /// Open a tournament on a quiz. Players register (paying the entry fee
+    /// into the prize pool) until `starts_at`, and results for the quiz
+    /// completed between `starts_at` and `ends_at` count.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_tournament(
+        ctx: Context<CreateTournament>,
+        tournament_id: String,
+        quiz_id: String,
+        max_participants: u8,
+        entry_fee_lamports: u64,
+        starts_at: i64,
+        ends_at: i64,
+    ) -> Result<()> {
+        ctx.accounts.protocol_config.ensure_not_paused()?;
+        require!(tournament_id.len() <= MAX_ID_LEN, ErrorCode::IdTooLong);
+        require!(is_valid_id(&tournament_id), ErrorCode::InvalidTournament);
+        require!(ctx.accounts.quiz_metadata.is_active, ErrorCode::QuizInactive);
+        require!(
+            max_participants >= 2
+                && max_participants <= MAX_TOURNAMENT_PARTICIPANTS
+                && max_participants.is_power_of_two(),
+            ErrorCode::InvalidTournament
+        );
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(starts_at > current_time && ends_at > starts_at, ErrorCode::InvalidTournament);
+        
+        let tournament = &mut ctx.accounts.tournament;
+        tournament.tournament_id = tournament_id;
+        tournament.organizer = ctx.accounts.organizer.key();
+        tournament.quiz_id = quiz_id;
+        tournament.max_participants = max_participants;
+        tournament.entry_fee_lamports = entry_fee_lamports;
+        tournament.prize_pool_lamports = 0;
+        tournament.starts_at = starts_at;
+        tournament.ends_at = ends_at;
+        tournament.status = TournamentStatus::Registration;
+        tournament.participants = Vec::new();
+        tournament.scores = Vec::new();
+        tournament.attempts = Vec::new();
+        tournament.bump = ctx.bumps.tournament;
+        
+        msg!("Tournament created: {} on {}", tournament.tournament_id, tournament.quiz_id);
+        Ok(())
+    }
+
+    /// Register for a tournament before it starts, paying the entry fee.
+    ///
+    /// Only the player's next attempt at the quiz counts, so it should be
+    /// taken once the tournament has started.
+    pub fn register_for_tournament(ctx: Context<RegisterForTournament>) -> Result<()> {
+        ctx.accounts.protocol_config.ensure_not_paused()?;
+        let player = ctx.accounts.player.key();
+        let tournament = &ctx.accounts.tournament;
+        require!(
+            tournament.status == TournamentStatus::Registration,
+            ErrorCode::InvalidTournamentStatus
+        );
+        require!(
+            Clock::get()?.unix_timestamp < tournament.starts_at,
+            ErrorCode::InvalidTournamentStatus
+        );
+        require!(
+            tournament.participants.len() < tournament.max_participants as usize,
+            ErrorCode::TournamentFull
+        );
+        require!(!tournament.participants.contains(&player), ErrorCode::AlreadyRegistered);
+        
+        let entry_fee_lamports = tournament.entry_fee_lamports;
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.player.to_account_info(),
+                    to: ctx.accounts.tournament.to_account_info(),
+                },
+            ),
+            entry_fee_lamports,
+        )?;
+        
+        let tournament = &mut ctx.accounts.tournament;
+        tournament.prize_pool_lamports = tournament
+            .prize_pool_lamports
+            .checked_add(entry_fee_lamports)
+            .ok_or(ErrorCode::NumericOverflow)?;
+        tournament.participants.push(player);
+        tournament.scores.push(None);
+        tournament.attempts.push(next_quiz_attempt(&ctx.accounts.quiz_progress)?);
+        
+        let profile = &mut ctx.accounts.user_profile;
+        profile.tournament_locked_until = profile.tournament_locked_until.max(tournament.ends_at);
+        
+        msg!(
+            "{} registered for {} ({}/{})",
+            player,
+            tournament.tournament_id,
+            tournament.participants.len(),
+            tournament.max_participants
+        );
+        Ok(())
+    }
+
+    /// Record a participant's score from their first result for the
+    /// tournament quiz after registering, which must have been completed
+    /// while the tournament was running
+    pub fn submit_tournament_score(ctx: Context<SubmitTournamentScore>) -> Result<()> {
+        let participant = ctx.accounts.participant.key();
+        let quiz_result = &ctx.accounts.quiz_result;
+        let tournament = &mut ctx.accounts.tournament;
+        require!(
+            tournament.status == TournamentStatus::Registration,
+            ErrorCode::InvalidTournamentStatus
+        );
+        require!(
+            quiz_result.user == ctx.accounts.participant_profile.profile_id
+                && quiz_result.quiz_id == tournament.quiz_id
+                && (tournament.starts_at..=tournament.ends_at).contains(&quiz_result.completed_at),
+            ErrorCode::InvalidTournament
+        );
+        
+        tournament.record_score(participant, quiz_result.attempt, quiz_result.score)?;
+        
+        msg!("Tournament score {} recorded for {}", quiz_result.score, participant);
+        Ok(())
+    }
+
+    /// Rank a finished tournament and pay out the prize pool (callable by anyone).
+    ///
+    /// Players are ranked by score; ties go to the earlier registration.
+    /// `remaining_accounts` must hold the wallets of the top (up to three)
+    /// scorers in rank order, who receive 60%, 30% and 10% of the pool; the
+    /// shares of places nobody filled go to first place. If nobody submitted
+    /// a score, `remaining_accounts` must hold every participant in
+    /// registration order and entry fees are refunded.
+    pub fn finalize_tournament(ctx: Context<FinalizeTournament>) -> Result<()> {
+        let tournament = &ctx.accounts.tournament;
+        require!(
+            tournament.status == TournamentStatus::Registration,
+            ErrorCode::InvalidTournamentStatus
+        );
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(current_time > tournament.ends_at, ErrorCode::TournamentNotEnded);
+        
+        let mut ranking: Vec<(Pubkey, u8)> = tournament
+            .participants
+            .iter()
+            .zip(tournament.scores.iter())
+            .filter_map(|(player, score)| score.map(|score| (*player, score)))
+            .collect();
+        // Stable sort keeps registration order among equal scores
+        ranking.sort_by(|a, b| b.1.cmp(&a.1));
+        ranking.truncate(TOURNAMENT_PRIZE_SHARES_BPS.len());
+        
+        let pool = tournament.prize_pool_lamports;
+        let payouts: Vec<(Pubkey, u64)> = if ranking.is_empty() {
+            tournament
+                .participants
+                .iter()
+                .map(|player| (*player, tournament.entry_fee_lamports))
+                .collect()
+        } else {
+            let mut payouts: Vec<(Pubkey, u64)> = ranking
+                .iter()
+                .zip(TOURNAMENT_PRIZE_SHARES_BPS.iter())
+                .map(|((player, _), share_bps)| {
+                    let amount = pool as u128 * *share_bps as u128 / BASE_MULTIPLIER_BPS as u128;
+                    (*player, amount as u64)
+                })
+                .collect();
+            let paid: u64 = payouts.iter().map(|(_, amount)| amount).sum();
+            payouts[0].1 = payouts[0].1.checked_add(pool - paid).ok_or(ErrorCode::NumericOverflow)?;
+            payouts
+        };
+        require!(
+            ctx.remaining_accounts.len() == payouts.len(),
+            ErrorCode::InvalidPrizeRecipient
+        );
+        
+        let tournament_info = ctx.accounts.tournament.to_account_info();
+        for ((player, amount), recipient) in payouts.iter().zip(ctx.remaining_accounts) {
+            require_keys_eq!(*recipient.key, *player, ErrorCode::InvalidPrizeRecipient);
+            move_lamports(&tournament_info, recipient, *amount)?;
+        }
+        
+        let tournament = &mut ctx.accounts.tournament;
+        tournament.prize_pool_lamports = 0;
+        tournament.status = if ranking.is_empty() {
+            TournamentStatus::Cancelled
+        } else {
+            TournamentStatus::Completed
+        };
+        
+        emit_cpi!(TournamentFinalized {
+            tournament_id: tournament.tournament_id.clone(),
+            quiz_id: tournament.quiz_id.clone(),
+            winners: ranking.iter().map(|(player, _)| *player).collect(),
+            prize_pool_lamports: pool,
+            finalized_at: current_time,
+        });
+        
+        msg!("Tournament {} finalized: {:?}", tournament.tournament_id, tournament.status);
+        Ok(())
+    }
+}
+
+/// Length of a UTC day, the default streak window
+pub const SECONDS_PER_DAY: i64 = 86400;
+
+/// Length of a week; weeks are counted from the Unix epoch
+pub const SECONDS_PER_WEEK: i64 = 604_800;
+
+/// Index of the week containing `timestamp`
+pub fn week_index(timestamp: i64) -> u32 {
+    (timestamp / SECONDS_PER_WEEK) as u32
+}
+
+/// Index of the UTC calendar month containing `timestamp`, counted from
+/// January 1970
+pub fn month_index(timestamp: i64) -> u32 {
+    // Days to civil date, from Howard Hinnant's `civil_from_days`
+    let days = timestamp.div_euclid(SECONDS_PER_DAY) + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    // Months counted from March, so the leap day ends the year
+    let march_month = (5 * day_of_year + 2) / 153;
+    let month = if march_month < 10 { march_month + 3 } else { march_month - 9 };
+    let year = era * 400 + year_of_era + if month <= 2 { 1 } else { 0 };
+    ((year - 1970) * 12 + month - 1) as u32
+}
+
+/// XP multiplier of 1.0x, in basis points
+pub const BASE_MULTIPLIER_BPS: u16 = 10_000;
+
+/// Maximum length of a quiz or achievement id
+pub const MAX_ID_LEN: usize = 64;
+
+/// PDA seed for a quiz or achievement id.
+///
+/// Seeds are capped at 32 bytes, so ids are hashed rather than used raw.
+pub fn id_seed(id: &str) -> [u8; 32] {
+    hash(id.as_bytes()).to_bytes()
+}
+
+/// Minimum length of a username
+pub const MIN_USERNAME_LEN: usize = 3;
+
+/// Maximum length of a username
+pub const MAX_USERNAME_LEN: usize = 32;
+
+/// PDA seed for a username registry entry.
+///
+/// Usernames are unique case-insensitively and stored trimmed, so the
+/// trimmed, lowercased name is hashed.
+pub fn username_seed(username: &str) -> [u8; 32] {
+    hash(username.trim().to_lowercase().as_bytes()).to_bytes()
+}
+
+/// Trim surrounding whitespace and validate a username
+pub fn normalize_username(username: &str) -> Result<String> {
+    let username = username.trim();
+    require!(
+        (MIN_USERNAME_LEN..=MAX_USERNAME_LEN).contains(&username.len()) && is_valid_id(username),
+        ErrorCode::InvalidUsername
+    );
+    Ok(username.to_string())
+}
+
+/// Ids must be non-empty and limited to `[a-zA-Z0-9_-]`
+pub fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+}
+
+/// Number of entries kept on the global leaderboard
+pub const LEADERBOARD_CAPACITY: usize = 100;
+
+/// Number of entries kept on each quiz leaderboard
+pub const QUIZ_LEADERBOARD_CAPACITY: usize = 50;
+
+/// Maximum length of an NFT metadata URI
+pub const MAX_METADATA_URI_LEN: usize = 200;
+
+/// Maximum length of a profile bio
+pub const MAX_BIO_LEN: usize = 256;
+
+/// Layout version of `UserProfile`; bump whenever fields are added, so
+/// `migrate_profile` marks upgraded accounts
+pub const PROFILE_SCHEMA_VERSION: u8 = 3;
+
+/// Layout version of `ProtocolConfig`; bump whenever fields are added, so
+/// `migrate_config` marks upgraded accounts
+pub const CONFIG_VERSION: u8 = 2;
+
+/// Maximum length of a profile avatar URI
+pub const MAX_AVATAR_URI_LEN: usize = 200;
+
+/// Maximum length of a profile social link
+pub const MAX_SOCIAL_LINK_LEN: usize = 100;
+
+/// Longest accepted time for a quiz attempt
+pub const MAX_TIME_TAKEN_SECONDS: u32 = u32::MAX / 2;
+
+/// Level a user must reach to register as a mentor on a new config
+pub const DEFAULT_MIN_MENTOR_LEVEL: u64 = 5;
+
+/// XP granted to both sides of a referral on a new config
+pub const DEFAULT_REFERRAL_BONUS_XP: u64 = 50;
+
+/// Largest course completion reward on a new config
+pub const DEFAULT_MAX_COURSE_REWARD_XP: u64 = 5_000;
+
+/// Course completion rewards above this need the config authority's
+/// approval on a new config
+pub const DEFAULT_COURSE_APPROVAL_XP: u64 = 1_000;
+
+/// Protocol fee on completed mentor sessions on a new config, in basis points
+pub const DEFAULT_SESSION_FEE_BPS: u16 = 500;
+
+/// Longest accepted IPFS CID
+pub const MAX_CID_LEN: usize = 64;
+
+/// Most lesson quizzes in a course
+pub const MAX_COURSE_LESSONS: usize = 20;
+
+/// Most courses in a learning path
+pub const MAX_LEARNING_PATH_COURSES: usize = 10;
+
+/// Most questions a quiz graded on-chain can have
+pub const MAX_QUIZ_QUESTIONS: usize = 50;
+
+/// Most proven answers graded by one `submit_quiz_with_proof` call
+pub const MAX_PROVEN_ANSWERS_PER_CALL: usize = 10;
+
+/// Time allowed between `start_quiz` and `reveal_quiz`
+pub const QUIZ_REVEAL_WINDOW: i64 = 60 * 60;
+
+/// Minimum age of a quiz result or achievement before it can be closed
+pub const MIN_RECORD_AGE: i64 = 30 * SECONDS_PER_DAY;
+
+/// Whether a record created at `recorded_at` is old enough to close at `now`
+pub fn is_record_closable(recorded_at: i64, now: i64) -> bool {
+    now.saturating_sub(recorded_at) >= MIN_RECORD_AGE
+}
+
+/// Minimum time between two username changes
+pub const USERNAME_CHANGE_COOLDOWN: i64 = 7 * SECONDS_PER_DAY;
+
+/// Oldest quiz attestation a submission accepts, in seconds
+pub const ATTESTATION_MAX_AGE: i64 = 5 * 60;
+
+/// Longest time a challenge can stay open
+pub const MAX_CHALLENGE_DURATION: i64 = 7 * SECONDS_PER_DAY;
+
+/// Most players in a tournament
+pub const MAX_TOURNAMENT_PARTICIPANTS: u8 = 64;
+
+/// Share of a tournament's prize pool for first, second and third place, in basis points
+pub const TOURNAMENT_PRIZE_SHARES_BPS: [u16; 3] = [6000, 3000, 1000];
+
+/// Give `profile` a new username and move its registry entry.
+///
+/// `new_registry` must be the registry PDA for the new name. Renames that only
+/// change casing keep the existing entry; otherwise the current entry is
+/// closed and its rent returned to `authority`.
+pub fn rename_profile<'info>(
+    profile: &mut Account<'info, UserProfile>,
+    current_registry: Option<&Account<'info, UsernameRegistry>>,
+    new_registry: Option<&mut Account<'info, UsernameRegistry>>,
+    authority: &AccountInfo<'info>,
+    new_username: &str,
+    now: i64,
+) -> Result<()> {
+    let new_username = normalize_username(new_username)?;
+    require!(
+        profile.last_username_change == 0
+            || now.saturating_sub(profile.last_username_change) >= USERNAME_CHANGE_COOLDOWN,
+        ErrorCode::UsernameChangeTooSoon
+    );
+    
+    let new_registry = new_registry.ok_or(ErrorCode::UsernameRegistryRequired)?;
+    if username_seed(&new_username) == username_seed(&profile.username) {
+        // Only the casing changes, so the registry entry stays put
+        require!(new_registry.profile == profile.key(), ErrorCode::UsernameTaken);
+    } else {
+        require!(new_registry.profile == Pubkey::default(), ErrorCode::UsernameTaken);
+        if !profile.username.is_empty() {
+            let current_registry = current_registry.ok_or(ErrorCode::UsernameRegistryRequired)?;
+            close_account(&current_registry.to_account_info(), authority)?;
+        }
+        new_registry.profile = profile.key();
+        new_registry.owner = profile.authority;
+        new_registry.registered_at = now;
+    }
+    profile.username = new_username;
+    profile.last_username_change = now;
+    Ok(())
+}
+
+/// Record a graded quiz attempt: XP, progress, streak, rewards and events.
+///
+/// Shared by `submit_quiz`, `submit_quiz_weighted`, `reveal_quiz` and
+/// `submit_quiz_with_proof`, which differ only in where the score comes from.
+fn record_quiz_submission(
+    ctx: &mut Context<SubmitQuiz>,
+    quiz_id: String,
+    attempt: u16,
+    graded: GradedScore,
+    time_taken_seconds: u32,
+    hints_used: u8,
+    submission_nonce: u64,
+) -> Result<()> {
+    let GradedScore { score, total_questions, points_earned, points_possible } = graded;
+    ctx.accounts.protocol_config.ensure_not_paused()?;
+    // Each submission must carry the profile's next nonce, so a signed
+    // transaction cannot be replayed
+    ctx.accounts.user_profile.use_submission_nonce(submission_nonce)?;
+    // Implausibly fast attempts are rejected outright
+    ctx.accounts.protocol_config.ensure_plausible_time(time_taken_seconds)?;
+    require!(quiz_id.len() <= MAX_ID_LEN, ErrorCode::IdTooLong);
+    require!(is_valid_id(&quiz_id), ErrorCode::InvalidQuizMetadata);
+    require!(total_questions >= 1, ErrorCode::EmptyQuiz);
+    require!(score <= total_questions, ErrorCode::InvalidScore);
+    require!(points_earned <= points_possible, ErrorCode::InvalidScore);
+    require!(hints_used <= score, ErrorCode::InvalidHintCount);
+    let quiz_metadata = &ctx.accounts.quiz_metadata;
+    quiz_metadata.ensure_accepts(total_questions)?;
+    let difficulty = quiz_metadata.difficulty.clone();
+    let is_event_quiz = quiz_metadata.is_event;
+    let current_time = Clock::get()?.unix_timestamp;
+    
+    // Timed quizzes are measured from `start_quiz`
+    let started_at = ctx.accounts.quiz_attempt.as_ref().map(|quiz_attempt| quiz_attempt.started_at);
+    let duration_secs = quiz_metadata.measure_duration(started_at, current_time)?;
+    
+    let config = &ctx.accounts.protocol_config;
+    let profile = &mut ctx.accounts.user_profile;
+    let quiz_result = &mut ctx.accounts.quiz_result;
+    let quiz_progress = &mut ctx.accounts.quiz_progress;
+    let old_level = profile.level;
+    let old_xp = profile.xp;
+    let difficulty_multiplier_bps = config.difficulty_multiplier_bps(&difficulty);
+    
+    // Event quizzes are exempt from the cooldown between submissions
+    profile.mark_quiz_submitted(config, is_event_quiz, current_time)?;
+    
+    // A freshly created quiz result has never been completed
+    require!(quiz_result.completed_at == 0, ErrorCode::QuizAlreadyCompleted);
+    
+    let attempt_xp = config.attempt_xp(
+        &graded,
+        difficulty_multiplier_bps,
+        attempt,
+        time_taken_seconds,
+        hints_used,
+    )?;
+    let improvement = quiz_progress.record_attempt(attempt, score, attempt_xp, current_time)?;
+    
+    // Only the improvement over the best previous attempt is awarded,
+    // boosted while an XP event is running and by an active stake
+    let stake_boost_bps = ctx
+        .accounts
+        .stake_position
+        .as_ref()
+        .map_or(0, |position| position.boost_bps_at(current_time));
+    let xp_earned = config.boosted_xp(improvement, stake_boost_bps, current_time)?;
+    
+    quiz_progress.user = profile.profile_id;
+    quiz_progress.total_xp_from_quiz = quiz_progress
+        .total_xp_from_quiz
+        .checked_add(xp_earned)
+        .ok_or(ErrorCode::NumericOverflow)?;
+    
+    // Update profile
+    profile.earn_xp(xp_earned, current_time)?;
+    profile.quizzes_completed = profile
+        .quizzes_completed
+        .checked_add(1)
+        .ok_or(ErrorCode::NumericOverflow)?;
+    
+    // Update streak, crediting any streak milestone bonus
+    let old_streak = profile.streak;
+    if let Some(streak_freeze) = ctx.accounts.streak_freeze.as_mut() {
+        if profile.use_banked_freeze(
+            streak_freeze,
+            current_time,
+            config.streak_window_secs,
+            config.streak_grace_secs,
+        ) {
+            msg!("Streak freeze used. Freezes remaining: {}", streak_freeze.count);
+        }
+    }
+    profile.record_activity(
+        current_time,
+        config.streak_window_secs,
+        config.streak_grace_secs,
+    )?;
+    let streak_bonus_xp = if profile.streak > old_streak {
+        config.streak_milestone_bonus(profile.streak)
+    } else {
+        None
+    };
+    if let Some(bonus_xp) = streak_bonus_xp {
+        profile.earn_xp(bonus_xp, current_time)?;
+    }
+    // Streak milestones also earn their catalog achievement when its account
+    // and definition are supplied
+    let streak_achievement = match (
+        streak_bonus_xp,
+        ctx.accounts.milestone_achievement.as_ref(),
+        ctx.accounts.milestone_definition.as_mut(),
+    ) {
+        (Some(_), Some(achievement), Some(definition)) => award_milestone_achievement(
+            achievement,
+            definition,
+            profile,
+            config,
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            current_time,
+            ctx.bumps.milestone_achievement.unwrap_or_default(),
+        )?,
+        _ => None,
+    };
+    
+    // Threshold achievements supplied in remaining_accounts are awarded in place
+    let threshold_achievements = award_threshold_achievements(
+        ctx.remaining_accounts,
+        &ctx.accounts.authority.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        profile,
+        config,
+        points_earned == points_possible,
+        current_time,
+    )?;
+    
+    // Calculate new level from the configured level curve
+    profile.level = level_for_xp(profile.xp, &config.level_curve);
+    
+    // Store quiz result
+    quiz_result.user = profile.profile_id;
+    quiz_result.quiz_id = quiz_id.clone();
+    quiz_result.attempt = attempt;
+    quiz_result.score = score;
+    quiz_result.total_questions = total_questions;
+    quiz_result.points_earned = points_earned;
+    quiz_result.points_possible = points_possible;
+    quiz_result.xp_earned = xp_earned;
+    quiz_result.difficulty = difficulty.clone();
+    quiz_result.difficulty_multiplier_used = difficulty_multiplier_bps;
+    quiz_result.completed_at = current_time;
+    quiz_result.time_taken_seconds = time_taken_seconds;
+    quiz_result.hints_used = hints_used;
+    quiz_result.duration_secs = duration_secs;
+    quiz_result.bump = ctx.bumps.quiz_result;
+    
+    // Update the quiz's aggregate statistics
+    ctx.accounts.quiz_stats.record(&graded, profile.authority)?;
+    
+    // Accrue claimable token rewards when the attempt reaches the reward
+    // score threshold
+    let meets_reward_threshold = points_earned as u64 * BASE_MULTIPLIER_BPS as u64
+        >= points_possible as u64 * config.min_reward_score_bps as u64;
+    if meets_reward_threshold {
+        let accrued = profile.accrue_token_rewards(xp_earned, config, current_time)?;
+        if accrued > 0 {
+            msg!("Rewards accrued: {} tokens ({} pending)", accrued, profile.pending_rewards);
+        }
+    }
+    
+    ctx.accounts.global_stats.record_quiz(
+        threshold_achievements.len() as u64 + streak_achievement.is_some() as u64,
+        profile.xp.saturating_sub(old_xp),
+    )?;
+    
+    if let Some(leaderboard) = &ctx.accounts.leaderboard {
+        leaderboard.load_mut()?.record(profile, current_time)?;
+    }
+    if let Some(period_leaderboard) = &ctx.accounts.period_leaderboard {
+        period_leaderboard.load_mut()?.record(profile, current_time)?;
+    }
+    if let Some(weekly_leaderboard) = &ctx.accounts.weekly_leaderboard {
+        weekly_leaderboard.load_mut()?.record(profile, current_time)?;
+    }
+    if let Some(quiz_leaderboard) = &ctx.accounts.quiz_leaderboard {
+        quiz_leaderboard.load_mut()?.upsert(QuizLeaderboardEntry::from_result(quiz_result));
+    }
+    
+    emit_cpi!(QuizSubmitted {
+        user: profile.authority,
+        username: profile.username.clone(),
+        quiz_id,
+        attempt,
+        score,
+        total_questions,
+        difficulty,
+        xp_earned,
+        total_xp: profile.xp,
+        new_level: profile.level,
+        streak: profile.streak,
+        timestamp: current_time,
+    });
+    if let Some(bonus_xp) = streak_bonus_xp {
+        emit_cpi!(StreakMilestoneReached {
+            user: profile.authority,
+            streak: profile.streak,
+            bonus_xp,
+        });
+    }
+    if let Some(achievement) = streak_achievement {
+        emit_cpi!(AchievementAwarded {
+            user: profile.authority,
+            username: profile.username.clone(),
+            achievement_id: achievement.achievement_id,
+            achievement_name: achievement.achievement_name,
+            tier: achievement.tier,
+            bonus_xp: achievement.bonus_xp,
+            total_xp: profile.xp,
+            new_level: profile.level,
+            awarded_at: achievement.awarded_at,
+        });
+    }
+    for achievement in threshold_achievements {
+        emit_cpi!(AchievementAwarded {
+            user: profile.authority,
+            username: profile.username.clone(),
+            achievement_id: achievement.achievement_id,
+            achievement_name: achievement.achievement_name,
+            tier: achievement.tier,
+            bonus_xp: achievement.bonus_xp,
+            total_xp: profile.xp,
+            new_level: profile.level,
+            awarded_at: achievement.awarded_at,
+        });
+    }
+    if profile.level > old_level {
+        emit_cpi!(LevelUp {
+            user: profile.authority,
+            old_level,
+            new_level: profile.level,
+        });
+    }
+    if QUIZ_MILESTONES.contains(&profile.quizzes_completed) {
+        emit_cpi!(AchievementEligible {
+            user: profile.authority,
+            achievement_id: format!("quizzes_completed_{}", profile.quizzes_completed),
+        });
+    }
+    
+    msg!("Quiz completed! Score: {}/{}, XP earned: {}", score, total_questions, xp_earned);
+    Ok(())
+}
+
+/// Outcome of grading a quiz attempt
+#[derive(Clone, Copy, Debug)]
+pub struct GradedScore {
+    /// Questions answered with full credit
+    pub score: u8,
+    pub total_questions: u8,
+    pub points_earned: u32,
+    pub points_possible: u32,
+}
+
+impl GradedScore {
+    /// Every question worth one point
+    pub fn flat(score: u8, total_questions: u8) -> Self {
+        Self {
+            score,
+            total_questions,
+            points_earned: score as u32,
+            points_possible: total_questions as u32,
+        }
+    }
+
+    /// Grade per-question `points` against each question's maximum. A
+    /// question counts towards the score only when it earned full points.
+    pub fn weighted(points: &[u16], question_points: &[u16], total_questions: u8) -> Result<Self> {
+        require!(points.len() == question_points.len(), ErrorCode::QuizMismatch);
+        let mut graded = Self {
+            score: 0,
+            total_questions,
+            points_earned: 0,
+            points_possible: 0,
+        };
+        for (&earned, &possible) in points.iter().zip(question_points) {
+            require!(earned <= possible, ErrorCode::InvalidScore);
+            graded.points_earned = graded
+                .points_earned
+                .checked_add(earned as u32)
+                .ok_or(ErrorCode::NumericOverflow)?;
+            graded.points_possible = graded
+                .points_possible
+                .checked_add(possible as u32)
+                .ok_or(ErrorCode::NumericOverflow)?;
+            if earned == possible {
+                graded.score = graded.score.checked_add(1).ok_or(ErrorCode::NumericOverflow)?;
+            }
+        }
+        Ok(graded)
+    }
+}
+
+/// Commitment to a set of answers, as passed to `start_quiz`
+pub fn answer_commitment(answers: &[u8], salt: &[u8; 32]) -> [u8; 32] {
+    hashv(&[answers, salt]).to_bytes()
+}
+
+/// Hash of the answer to question `question` of a quiz, as stored in the
+/// quiz's `answer_hashes`.
+///
+/// Answers are small option indices, so these hashes only stop casual
+/// tampering; anyone can recover them by trying every option.
+pub fn answer_hash(quiz_id: &str, question: u8, answer: u8) -> [u8; 32] {
+    hashv(&[&id_seed(quiz_id), &[question, answer]]).to_bytes()
+}
+
+/// Merkle leaf for a question's correct answer hash
+pub fn answer_leaf(question: u8, correct_answer_hash: &[u8; 32]) -> [u8; 32] {
+    hashv(&[&[question], correct_answer_hash]).to_bytes()
+}
+
+/// Message a quiz attestation signs: the user, the hashed quiz id, the
+/// attempt, the graded score and points, the time taken and hints used, the
+/// submission nonce and the attestation time
+#[allow(clippy::too_many_arguments)]
+pub fn attestation_message(
+    user: &Pubkey,
+    quiz_id: &str,
+    attempt: u16,
+    graded: &GradedScore,
+    time_taken_seconds: u32,
+    hints_used: u8,
+    submission_nonce: u64,
+    timestamp: i64,
+) -> Vec<u8> {
+    let mut message = Vec::with_capacity(32 + 32 + 2 + 2 + 4 + 4 + 4 + 1 + 8 + 8);
+    message.extend_from_slice(user.as_ref());
+    message.extend_from_slice(&id_seed(quiz_id));
+    message.extend_from_slice(&attempt.to_le_bytes());
+    message.extend_from_slice(&[graded.score, graded.total_questions]);
+    message.extend_from_slice(&graded.points_earned.to_le_bytes());
+    message.extend_from_slice(&graded.points_possible.to_le_bytes());
+    message.extend_from_slice(&time_taken_seconds.to_le_bytes());
+    message.push(hints_used);
+    message.extend_from_slice(&submission_nonce.to_le_bytes());
+    message.extend_from_slice(&timestamp.to_le_bytes());
+    message
+}
+
+/// Check that the instruction before the current one is an ed25519 program
+/// instruction verifying `signature` by `signer` over `message`.
+///
+/// The ed25519 program has already checked the signature when this runs;
+/// what remains is making sure it covered the expected key, signature and
+/// message, all stored in that instruction's own data.
+pub fn verify_ed25519_attestation(
+    instructions: &AccountInfo,
+    signer: &Pubkey,
+    signature: &[u8; 64],
+    message: &[u8],
+) -> Result<()> {
+    let current_index = load_current_index_checked(instructions)?;
+    require!(current_index > 0, ErrorCode::InvalidAttestation);
+    let ed25519_ix = load_instruction_at_checked(current_index as usize - 1, instructions)?;
+    require_keys_eq!(ed25519_ix.program_id, ed25519_program::ID, ErrorCode::InvalidAttestation);
+    
+    // Header: signature count and padding, then one 14-byte offsets entry
+    let data = &ed25519_ix.data;
+    require!(data.len() >= 16 && data[0] == 1, ErrorCode::InvalidAttestation);
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let own_data = u16::MAX;
+    require!(
+        read_u16(4) == own_data && read_u16(8) == own_data && read_u16(14) == own_data,
+        ErrorCode::InvalidAttestation
+    );
+    let field = |offset: u16, len: usize| data.get(offset as usize..offset as usize + len);
+    require!(
+        field(read_u16(2), 64) == Some(&signature[..])
+            && field(read_u16(6), 32) == Some(signer.as_ref())
+            && field(read_u16(10), read_u16(12) as usize) == Some(message),
+        ErrorCode::InvalidAttestation
+    );
+    Ok(())
+}
+
+/// Check a Merkle proof for `leaf` against `root`. Pairs are hashed in
+/// sorted order, so proofs carry no left/right flags.
+pub fn verify_merkle_proof(proof: &[[u8; 32]], root: &[u8; 32], leaf: [u8; 32]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| {
+        if node <= *sibling {
+            hashv(&[&node, sibling]).to_bytes()
+        } else {
+            hashv(&[sibling, &node]).to_bytes()
+        }
+    });
+    computed == *root
+}
+
+/// Achievement id for reaching a streak of `streak` days
+pub fn streak_achievement_id(streak: u64) -> String {
+    format!("streak_{}", streak)
+}
+
+/// Bit recording a milestone achievement in `UserProfile::milestone_awards`.
+///
+/// The thresholds of `threshold_achievements` take bits 0 to 8, and a streak
+/// milestone takes bit 16 plus its slot in the config's `streak_milestones`.
+/// Other achievements have none.
+pub fn milestone_award_bit(achievement_id: &str, config: &ProtocolConfig) -> Option<u32> {
+    let thresholds = QUIZ_MILESTONES
+        .iter()
+        .map(|milestone| format!("quizzes_completed_{}", milestone))
+        .chain(std::iter::once("first_perfect_score".to_string()))
+        .chain(XP_MILESTONES.iter().map(|milestone| format!("xp_{}", milestone)));
+    if let Some(bit) = thresholds.position(|id| id == achievement_id) {
+        return Some(bit as u32);
+    }
+    config
+        .streak_milestones
+        .iter()
+        .position(|&milestone| {
+            milestone != 0 && streak_achievement_id(milestone) == achievement_id
+        })
+        .map(|slot| 16 + slot as u32)
+}
+
+/// Award a milestone achievement (a streak or quiz threshold) from its
+/// catalog definition, which sets its name, tier and bonus XP.
+///
+/// Nothing is awarded if the achievement already was, or if the definition
+/// is inactive, out of supply or needs a prerequisite (those are left to
+/// `award_achievement`), so a missed milestone never fails the caller.
+/// Awards are also recorded in the profile's `milestone_awards`, so a
+/// milestone whose record was closed or revoked is not awarded again.
+/// Returns whether the achievement was newly awarded.
+pub fn grant_milestone_achievement(
+    achievement: &mut Achievement,
+    definition: &mut AchievementDefinition,
+    profile: &mut UserProfile,
+    config: &ProtocolConfig,
+    now: i64,
+    bump: u8,
+) -> Result<bool> {
+    let award_bit = milestone_award_bit(&definition.achievement_id, config)
+        .map_or(0, |bit| 1u32 << bit);
+    if achievement.awarded_at != 0
+        || profile.milestone_awards & award_bit != 0
+        || definition.prerequisite_achievement_id.is_some()
+        || definition.take_supply().is_err()
+    {
+        return Ok(false);
+    }
+    
+    profile.milestone_awards |= award_bit;
+    let bonus_xp = definition.award_bonus(config);
+    achievement.award(
+        profile,
+        definition.achievement_id.clone(),
+        definition.name.clone(),
+        definition.tier.clone(),
+        bonus_xp,
+        now,
+        bump,
+    )?;
+    profile.accrue_token_rewards(bonus_xp, config, now)?;
+    Ok(true)
+}
+
+/// Award a milestone achievement into its not yet created PDA `account`
+/// through `grant_milestone_achievement`, creating the account only when the
+/// achievement is granted. An account that already exists is left alone.
+/// Returns the new achievement.
+#[allow(clippy::too_many_arguments)]
+pub fn award_milestone_achievement<'info>(
+    account: &AccountInfo<'info>,
+    definition: &mut AchievementDefinition,
+    profile: &mut UserProfile,
+    config: &ProtocolConfig,
+    payer: &AccountInfo<'info>,
+    system_program_info: &AccountInfo<'info>,
+    now: i64,
+    bump: u8,
+) -> Result<Option<Achievement>> {
+    if !account.data_is_empty() {
+        return Ok(None);
+    }
+    let mut achievement = Achievement {
+        user: Pubkey::default(),
+        achievement_id: String::new(),
+        achievement_name: String::new(),
+        tier: AchievementTier::Bronze,
+        awarded_at: 0,
+        nft_mint: None,
+        bump: 0,
+        bonus_xp: 0,
+    };
+    if !grant_milestone_achievement(&mut achievement, definition, profile, config, now, bump)? {
+        return Ok(None);
+    }
+    
+    create_achievement_account(
+        account,
+        payer,
+        system_program_info,
+        &profile.profile_id,
+        &achievement.achievement_id,
+        bump,
+    )?;
+    achievement.try_serialize(&mut &mut account.try_borrow_mut_data()?[..])?;
+    Ok(Some(achievement))
+}
+
+/// Create the achievement PDA `account` for `achievement_id`, paid by `payer`.
+///
+/// As with Anchor's `init`, an address that already holds lamports is topped
+/// up to rent exemption, allocated and assigned instead of failing.
+pub fn create_achievement_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program_info: &AccountInfo<'info>,
+    profile_id: &Pubkey,
+    achievement_id: &str,
+    bump: u8,
+) -> Result<()> {
+    let space = 8 + Achievement::INIT_SPACE;
+    let rent_exempt_lamports = Rent::get()?.minimum_balance(space);
+    let id_hash = id_seed(achievement_id);
+    let signer_seeds: &[&[&[u8]]] =
+        &[&[b"achievement".as_ref(), profile_id.as_ref(), &id_hash, &[bump]]];
+    
+    if account.lamports() == 0 {
+        return system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program_info.clone(),
+                system_program::CreateAccount {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+                signer_seeds,
+            ),
+            rent_exempt_lamports,
+            space as u64,
+            &crate::ID,
+        );
+    }
+    
+    let lamports_needed = rent_exempt_lamports.saturating_sub(account.lamports());
+    if lamports_needed > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program_info.clone(),
+                system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            lamports_needed,
+        )?;
+    }
+    system_program::allocate(
+        CpiContext::new_with_signer(
+            system_program_info.clone(),
+            system_program::Allocate {
+                account_to_allocate: account.clone(),
+            },
+            signer_seeds,
+        ),
+        space as u64,
+    )?;
+    system_program::assign(
+        CpiContext::new_with_signer(
+            system_program_info.clone(),
+            system_program::Assign {
+                account_to_assign: account.clone(),
+            },
+            signer_seeds,
+        ),
+        &crate::ID,
+    )
+}
+
+/// Id of every threshold achievement the profile has reached: quiz counts
+/// in `QUIZ_MILESTONES`, a perfect score on the current attempt, and XP
+/// totals in `XP_MILESTONES`
+pub fn threshold_achievements(profile: &UserProfile, perfect_score: bool) -> Vec<String> {
+    let mut reached = Vec::new();
+    for milestone in QUIZ_MILESTONES {
+        if profile.quizzes_completed >= milestone {
+            reached.push(format!("quizzes_completed_{}", milestone));
+        }
+    }
+    if perfect_score {
+        reached.push("first_perfect_score".to_string());
+    }
+    for milestone in XP_MILESTONES {
+        if profile.xp >= milestone {
+            reached.push(format!("xp_{}", milestone));
+        }
+    }
+    reached
+}
+
+/// Create and fill in the supplied achievement PDAs for thresholds the
+/// profile has reached, through `grant_milestone_achievement`.
+///
+/// `remaining_accounts` holds `[definition, achievement]` pairs. Pairs whose
+/// achievement is already initialized, is not the PDA of a reached
+/// threshold, or doesn't come with that threshold's definition are skipped,
+/// as are definitions that can't be awarded, so passing nothing (or too
+/// much) never fails. Thresholds already awarded are skipped too, even if
+/// their record was closed since. XP from these bonuses can only reach
+/// further thresholds on a later submission. Returns the new achievements.
+pub fn award_threshold_achievements<'info>(
+    remaining_accounts: &[AccountInfo<'info>],
+    payer: &AccountInfo<'info>,
+    system_program_info: &AccountInfo<'info>,
+    profile: &mut UserProfile,
+    config: &ProtocolConfig,
+    perfect_score: bool,
+    now: i64,
+) -> Result<Vec<Achievement>> {
+    if remaining_accounts.is_empty() {
+        return Ok(Vec::new());
+    }
+    let reached: Vec<_> = threshold_achievements(profile, perfect_score)
+        .into_iter()
+        .map(|achievement_id| {
+            let (address, bump) = Pubkey::find_program_address(
+                &[b"achievement", profile.profile_id.as_ref(), &id_seed(&achievement_id)],
+                &crate::ID,
+            );
+            let (definition_address, _) = Pubkey::find_program_address(
+                &[b"achievement_def", &id_seed(&achievement_id)],
+                &crate::ID,
+            );
+            (address, bump, definition_address)
+        })
+        .collect();
+    
+    let mut awarded = Vec::new();
+    for pair in remaining_accounts.chunks_exact(2) {
+        let (definition_info, account) = (&pair[0], &pair[1]);
+        if !account.data_is_empty() || definition_info.owner != &crate::ID {
+            continue;
+        }
+        let Some((_, bump, _)) = reached.iter().find(|(address, _, definition)| {
+            address == account.key && definition == definition_info.key
+        }) else {
+            continue;
+        };
+        
+        let mut definition =
+            AchievementDefinition::try_deserialize(&mut &definition_info.data.borrow()[..])?;
+        let Some(achievement) = award_milestone_achievement(
+            account,
+            &mut definition,
+            profile,
+            config,
+            payer,
+            system_program_info,
+            now,
+            *bump,
+        )?
+        else {
+            continue;
+        };
+        
+        definition.try_serialize(&mut &mut definition_info.try_borrow_mut_data()?[..])?;
+        awarded.push(achievement);
+    }
+    Ok(awarded)
+}
+
+/// Shrink an awarded achievement's account to a tombstone: the record without
+/// its name. Award paths still find it awarded, so it is never awarded again.
+/// Rent the smaller account no longer needs goes to `destination`.
+pub fn shrink_to_tombstone<'info>(
+    achievement: &mut Account<'info, Achievement>,
+    destination: &AccountInfo<'info>,
+) -> Result<()> {
+    achievement.achievement_name.clear();
+    let mut tombstone = Vec::new();
+    achievement.try_serialize(&mut tombstone)?;
+    
+    let achievement_info = achievement.to_account_info();
+    achievement_info.realloc(tombstone.len(), false)?;
+    let excess = achievement_info
+        .lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(tombstone.len()));
+    move_lamports(&achievement_info, destination, excess)
+}
+
+/// Move lamports out of a program-owned account, such as a session escrow.
+pub fn move_lamports<'info>(
+    from: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    let from_lamports = from.lamports().checked_sub(amount).ok_or(ErrorCode::NumericOverflow)?;
+    let to_lamports = to.lamports().checked_add(amount).ok_or(ErrorCode::NumericOverflow)?;
+    **from.try_borrow_mut_lamports()? = from_lamports;
+    **to.try_borrow_mut_lamports()? = to_lamports;
+    Ok(())
+}
+
+/// Referral code of a profile owner, derived from their key
+pub fn referral_code_for(authority: &Pubkey) -> [u8; 8] {
+    let mut code = [0u8; 8];
+    code.copy_from_slice(&hashv(&[b"referral", authority.as_ref()]).to_bytes()[..8]);
+    code
+}
+
+/// Require the achievement progress tracker at `progress_info`, if one was
+/// ever created, to be completed. The account is the derived PDA, so a
+/// tracker can't be left out to skip the check.
+pub fn ensure_progress_completed(progress_info: &AccountInfo) -> Result<()> {
+    if progress_info.data_is_empty() {
+        return Ok(());
+    }
+    require!(progress_info.owner == &crate::ID, ErrorCode::AchievementProgressIncomplete);
+    let progress = AchievementProgress::try_deserialize(&mut &progress_info.data.borrow()[..])?;
+    require!(progress.completed, ErrorCode::AchievementProgressIncomplete);
+    Ok(())
+}
+
+/// Number of the next attempt the quiz progress tracker at `progress_info`
+/// will record: its attempt count, or 0 if the user never finished the quiz
+pub fn next_quiz_attempt(progress_info: &AccountInfo) -> Result<u16> {
+    if progress_info.data_is_empty() {
+        return Ok(0);
+    }
+    require!(progress_info.owner == &crate::ID, ErrorCode::InvalidAttempt);
+    let progress = QuizProgress::try_deserialize(&mut &progress_info.data.borrow()[..])?;
+    Ok(progress.attempts)
+}
+
+/// Return a revoked award to the supply of the achievement definition at
+/// `definition_info`, if the achievement has one
+pub fn restore_definition_supply(definition_info: &AccountInfo) -> Result<()> {
+    if definition_info.data_is_empty() {
+        return Ok(());
+    }
+    require!(definition_info.owner == &crate::ID, ErrorCode::InvalidAchievementDefinition);
+    let mut definition =
+        AchievementDefinition::try_deserialize(&mut &definition_info.data.borrow()[..])?;
+    definition.restore_supply();
+    definition.try_serialize(&mut &mut definition_info.try_borrow_mut_data()?[..])?;
+    Ok(())
+}
+
+/// Close a program-owned account by hand, sending its rent to `destination`.
+///
+/// Used where an account only needs closing on some paths, which the `close`
+/// constraint cannot express.
+pub fn close_account<'info>(
+    account: &AccountInfo<'info>,
+    destination: &AccountInfo<'info>,
+) -> Result<()> {
+    let destination_lamports = destination
+        .lamports()
+        .checked_add(account.lamports())
+        .ok_or(ErrorCode::NumericOverflow)?;
+    **destination.try_borrow_mut_lamports()? = destination_lamports;
+    **account.try_borrow_mut_lamports()? = 0;
+    account.assign(&system_program::ID);
+    account.realloc(0, false)?;
+    Ok(())
+}
+
+/// Highest level reachable on an exponential level curve
+pub const MAX_LEVEL: u64 = 1_000;
+
+/// Compute the level for a given amount of XP.
+///
+/// Reaching level `n + 1` from level `n` costs `base_xp * growth_factor^(n - 1)`
+/// XP, where the growth factor is fixed-point in basis points. A factor of
+/// 10000 (1.0x) gives the flat curve of one level per `base_xp` XP; steeper
+/// curves are capped at `MAX_LEVEL`.
+pub fn level_for_xp(xp: u64, curve: &LevelCurve) -> u64 {
+    if curve.growth_factor_bps == BASE_MULTIPLIER_BPS {
+        return (xp / curve.base_xp).saturating_add(1);
+    }
+    
+    let growth = curve.growth_factor_bps as u128;
+    let mut remaining = xp as u128;
+    let mut required = curve.base_xp as u128;
+    let mut level = 1;
+    while remaining >= required && level < MAX_LEVEL {
+        remaining -= required;
+        required = required.saturating_mul(growth) / BASE_MULTIPLIER_BPS as u128;
+        level += 1;
+    }
+    level
+}
+
+/// Streak lengths (in days) that earn bonus XP on a new config
+pub const DEFAULT_STREAK_MILESTONES: [u64; 8] = [7, 30, 100, 365, 0, 0, 0, 0];
+
+/// Bonus XP for each of `DEFAULT_STREAK_MILESTONES`
+pub const DEFAULT_STREAK_BONUS_XP: [u64; 8] = [50, 200, 750, 2_500, 0, 0, 0, 0];
+
+/// XP kept per retake on a new config, in basis points
+pub const DEFAULT_RETAKE_DECAY_BPS: u16 = 5_000;
+
+/// Lowest retake XP multiplier on a new config, in basis points
+pub const DEFAULT_MIN_RETAKE_MULTIPLIER_BPS: u16 = 2_500;
+
+/// XP multipliers per difficulty on a new config, in basis points
+pub const DEFAULT_DIFFICULTY_MULTIPLIERS_BPS: [u16; 4] = [10_000, 15_000, 20_000, 30_000];
+
+/// XP deducted per hint on a new config
+pub const DEFAULT_HINT_PENALTY_XP: u64 = 5;
+
+/// Completed-quiz counts that make a user eligible for an achievement
+pub const QUIZ_MILESTONES: [u64; 4] = [1, 10, 50, 100];
+
+/// XP totals that make a user eligible for an achievement
+pub const XP_MILESTONES: [u64; 4] = [1_000, 5_000, 10_000, 50_000];
+
+/// Streak length (in days) at which a new streak freeze can be earned
+pub const STREAK_FREEZE_MILESTONE: u64 = 7;
+
+/// Most streak freezes a user can bank at once
+pub const MAX_STREAK_FREEZES: u8 = 3;
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ProtocolConfig::INIT_SPACE,
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateConfig<'info> {
+    /// CHECK: may predate the current layout, so it is only deserialized after
+    /// reallocation; seeds and program ownership are checked here.
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump,
+        owner = crate::ID
+    )]
+    pub protocol_config: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(username: String)]
+pub struct InitializeProfile<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + UserProfile::INIT_SPACE,
+        seeds = [b"user_profile", authority.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + UsernameRegistry::INIT_SPACE,
+        seeds = [b"username_registry", &username_seed(&username)],
+        bump
+    )]
+    pub username_registry: Account<'info, UsernameRegistry>,
+    
+    #[account(
+        mut,
+        seeds = [b"global_stats"],
+        bump
+    )]
+    pub global_stats: Account<'info, GlobalStats>,
+    
+    /// The new user's referral code entry
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ReferralRegistry::INIT_SPACE,
+        seeds = [b"referral_registry", &referral_code_for(&authority.key())],
+        bump
+    )]
+    pub referral_registry: Account<'info, ReferralRegistry>,
+    
+    /// Referral code entry of the referrer; required when a referral code is given
+    #[account(
+        seeds = [b"referral_registry", &referrer_registry.code],
+        bump
+    )]
+    pub referrer_registry: Option<Account<'info, ReferralRegistry>>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(quiz_id: String, attempt: u16)]
+pub struct SubmitQuiz<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", user_profile.authority.as_ref()],
+        bump = user_profile.bump,
+        constraint = user_profile.is_authorized_signer(&authority.key()) @ ErrorCode::Unauthorized
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + QuizResult::INIT_SPACE,
+        seeds = [
+            b"quiz_result",
+            user_profile.profile_id.as_ref(),
+            &id_seed(&quiz_id),
+            &attempt.to_le_bytes()
+        ],
+        bump
+    )]
+    pub quiz_result: Account<'info, QuizResult>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + QuizProgress::INIT_SPACE,
+        seeds = [b"quiz_progress", user_profile.profile_id.as_ref(), &id_seed(&quiz_id)],
+        bump
+    )]
+    pub quiz_progress: Account<'info, QuizProgress>,
+    
+    /// The quiz being submitted; it must be active
+    #[account(
+        seeds = [b"quiz_meta", &id_seed(&quiz_id)],
+        bump
+    )]
+    pub quiz_metadata: Box<Account<'info, QuizMetadata>>,
+    
+    /// Aggregate statistics of the quiz being submitted
+    #[account(
+        mut,
+        constraint = quiz_stats.quiz == quiz_metadata.key() @ ErrorCode::QuizStatsMismatch
+    )]
+    pub quiz_stats: Box<Account<'info, QuizStats>>,
+    
+    #[account(
+        mut,
+        seeds = [b"global_stats"],
+        bump
+    )]
+    pub global_stats: Box<Account<'info, GlobalStats>>,
+    
+    /// Answer commitment from `start_quiz`; required by `reveal_quiz`
+    #[account(
+        mut,
+        seeds = [
+            b"quiz_attempt",
+            user_profile.profile_id.as_ref(),
+            &id_seed(&quiz_id),
+            &attempt.to_le_bytes()
+        ],
+        bump
+    )]
+    pub quiz_attempt: Option<Box<Account<'info, QuizAttempt>>>,
+    
+    /// Banked streak freezes; one is used automatically to cover a single missed day
+    #[account(
+        mut,
+        seeds = [b"streak_freeze", user_profile.profile_id.as_ref()],
+        bump
+    )]
+    pub streak_freeze: Option<Box<Account<'info, StreakFreeze>>>,
+    
+    /// CHECK: achievement PDA for the streak milestone this call may reach;
+    /// created by `award_milestone_achievement` only once it is awarded
+    #[account(
+        mut,
+        seeds = [
+            b"achievement",
+            user_profile.profile_id.as_ref(),
+            &id_seed(&streak_achievement_id(user_profile.streak.saturating_add(1)))
+        ],
+        bump
+    )]
+    pub milestone_achievement: Option<UncheckedAccount<'info>>,
+    
+    /// Catalog definition of the streak milestone achievement; without it
+    /// the achievement is not awarded
+    #[account(
+        mut,
+        seeds = [
+            b"achievement_def",
+            &id_seed(&streak_achievement_id(user_profile.streak.saturating_add(1)))
+        ],
+        bump
+    )]
+    pub milestone_definition: Option<Box<Account<'info, AchievementDefinition>>>,
+    
+    /// All-time leaderboard; refreshed with the user's new XP when supplied
+    #[account(
+        mut,
+        seeds = [b"leaderboard", &[LeaderboardType::AllTime as u8], &0u32.to_le_bytes()],
+        bump
+    )]
+    pub leaderboard: Option<AccountLoader<'info, Leaderboard>>,
+    
+    /// Top scores for this quiz; updated with the result when supplied
+    #[account(
+        mut,
+        seeds = [b"quiz_leaderboard", &id_seed(&quiz_id)],
+        bump
+    )]
+    pub quiz_leaderboard: Option<AccountLoader<'info, QuizLeaderboard>>,
+    
+    /// The current period's weekly or monthly leaderboard; refreshed with
+    /// the user's XP for the period when supplied
+    #[account(mut)]
+    pub period_leaderboard: Option<AccountLoader<'info, Leaderboard>>,
+    
+    /// The current week's leaderboard; refreshed with the user's weekly XP
+    /// when supplied
+    #[account(mut)]
+    pub weekly_leaderboard: Option<AccountLoader<'info, WeeklyLeaderboard>>,
+    
+    /// The user's stake; boosts the XP earned while its boost is active
+    #[account(
+        seeds = [b"stake_position", user_profile.authority.as_ref()],
+        bump = stake_position.bump
+    )]
+    pub stake_position: Option<Box<Account<'info, StakePosition>>>,
+    
+    /// CHECK: the instructions sysvar, read to find the score attestation;
+    /// required by `submit_quiz` and `submit_quiz_weighted` while an attestor
+    /// is configured
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+    
+    /// The profile authority or its delegate; pays for any accounts created
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(achievement_id: String)]
+pub struct AwardAchievement<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", user_profile.authority.as_ref()],
+        bump = user_profile.bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        init_if_needed,
+        payer = awarder,
+        space = 8 + Achievement::INIT_SPACE,
+        seeds = [b"achievement", user_profile.profile_id.as_ref(), &id_seed(&achievement_id)],
+        bump
+    )]
+    pub achievement: Account<'info, Achievement>,
+    
+    #[account(
+        mut,
+        seeds = [b"global_stats"],
+        bump
+    )]
+    pub global_stats: Account<'info, GlobalStats>,
+    
+    /// Catalog entry the achievement is awarded from
+    #[account(
+        mut,
+        seeds = [b"achievement_def", &id_seed(&achievement_id)],
+        bump
+    )]
+    pub achievement_definition: Account<'info, AchievementDefinition>,
+    
+    /// The user's copy of the prerequisite achievement, if the definition has one
+    pub prerequisite_achievement: Option<Account<'info, Achievement>>,
+    
+    /// CHECK: the user's progress tracker PDA for the achievement; checked
+    /// by `ensure_progress_completed`
+    #[account(
+        seeds = [b"ach_progress", user_profile.profile_id.as_ref(), &id_seed(&achievement_id)],
+        bump
+    )]
+    pub achievement_progress: UncheckedAccount<'info>,
+    
+    /// All-time leaderboard; refreshed with the user's new XP when supplied
+    #[account(
+        mut,
+        seeds = [b"leaderboard", &[LeaderboardType::AllTime as u8], &0u32.to_le_bytes()],
+        bump
+    )]
+    pub leaderboard: Option<AccountLoader<'info, Leaderboard>>,
+    
+    /// The current period's weekly or monthly leaderboard; refreshed with
+    /// the user's XP for the period when supplied
+    #[account(mut)]
+    pub period_leaderboard: Option<AccountLoader<'info, Leaderboard>>,
+    
+    /// The current week's leaderboard; refreshed with the user's weekly XP
+    /// when supplied
+    #[account(mut)]
+    pub weekly_leaderboard: Option<AccountLoader<'info, WeeklyLeaderboard>>,
+    
+    #[account(
+        mut,
+        constraint = protocol_config.is_awarder(&awarder.key()) @ ErrorCode::UnauthorizedAwarder
+    )]
+    pub awarder: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(achievement_id: String)]
+pub struct IncrementAchievementProgress<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        seeds = [b"user_profile", user_profile.authority.as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        init_if_needed,
+        payer = awarder,
+        space = 8 + AchievementProgress::INIT_SPACE,
+        seeds = [b"ach_progress", user_profile.profile_id.as_ref(), &id_seed(&achievement_id)],
+        bump
+    )]
+    pub achievement_progress: Account<'info, AchievementProgress>,
+    
+    #[account(
+        mut,
+        constraint = protocol_config.is_awarder(&awarder.key()) @ ErrorCode::UnauthorizedAwarder
+    )]
+    pub awarder: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(achievement_id: String)]
+pub struct CreateAchievementDefinition<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + AchievementDefinition::INIT_SPACE,
+        seeds = [b"achievement_def", &id_seed(&achievement_id)],
+        bump
+    )]
+    pub achievement_definition: Account<'info, AchievementDefinition>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(achievement_id: String)]
+pub struct UpdateAchievementDefinition<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"achievement_def", &id_seed(&achievement_id)],
+        bump
+    )]
+    pub achievement_definition: Account<'info, AchievementDefinition>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UpdateStreak<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", user_profile.authority.as_ref()],
+        bump = user_profile.bump,
+        constraint = user_profile.is_authorized_signer(&authority.key()) @ ErrorCode::Unauthorized
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    /// Banked streak freezes; one is used automatically to cover a single missed day
+    #[account(
+        mut,
+        seeds = [b"streak_freeze", user_profile.profile_id.as_ref()],
+        bump
+    )]
+    pub streak_freeze: Option<Account<'info, StreakFreeze>>,
+    
+    /// CHECK: achievement PDA for the streak milestone this call may reach;
+    /// created by `award_milestone_achievement` only once it is awarded
+    #[account(
+        mut,
+        seeds = [
+            b"achievement",
+            user_profile.profile_id.as_ref(),
+            &id_seed(&streak_achievement_id(user_profile.streak.saturating_add(1)))
+        ],
+        bump
+    )]
+    pub milestone_achievement: Option<UncheckedAccount<'info>>,
+    
+    /// Catalog definition of the streak milestone achievement; without it
+    /// the achievement is not awarded
+    #[account(
+        mut,
+        seeds = [
+            b"achievement_def",
+            &id_seed(&streak_achievement_id(user_profile.streak.saturating_add(1)))
+        ],
+        bump
+    )]
+    pub milestone_definition: Option<Box<Account<'info, AchievementDefinition>>>,
+    
+    /// The profile authority or its delegate; pays for any accounts created
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct DailyBonusClaim<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", authority.key().as_ref()],
+        bump = user_profile.bump,
+        has_one = authority
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct EarnStreakFreeze<'info> {
+    #[account(
+        seeds = [b"user_profile", authority.key().as_ref()],
+        bump,
+        has_one = authority
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + StreakFreeze::INIT_SPACE,
+        seeds = [b"streak_freeze", user_profile.profile_id.as_ref()],
+        bump
+    )]
+    pub streak_freeze: Account<'info, StreakFreeze>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BuyStreakFreeze<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", authority.key().as_ref()],
+        bump = user_profile.bump,
+        has_one = authority
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + StreakFreeze::INIT_SPACE,
+        seeds = [b"streak_freeze", user_profile.profile_id.as_ref()],
+        bump
+    )]
+    pub streak_freeze: Account<'info, StreakFreeze>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FreezeStreak<'info> {
+    #[account(
+        mut,
+        seeds = [b"user_profile", authority.key().as_ref()],
+        bump,
+        has_one = authority
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        mut,
+        seeds = [b"streak_freeze", user_profile.profile_id.as_ref()],
+        bump
+    )]
+    pub streak_freeze: Account<'info, StreakFreeze>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateProfile<'info> {
+    /// CHECK: may predate the current layout, so it is only deserialized after
+    /// reallocation; seeds and program ownership are checked here.
+    #[account(
+        mut,
+        seeds = [b"user_profile", authority.key().as_ref()],
+        bump,
+        owner = crate::ID
+    )]
+    pub user_profile: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CloseProfile<'info> {
+    #[account(
+        mut,
+        seeds = [b"user_profile", authority.key().as_ref()],
+        bump,
+        has_one = authority,
+        close = authority
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    /// Released along with the profile so the name can be claimed again
+    #[account(
+        mut,
+        seeds = [b"username_registry", &username_seed(&user_profile.username)],
+        bump,
+        constraint = username_registry.profile == user_profile.key(),
+        close = authority
+    )]
+    pub username_registry: Option<Account<'info, UsernameRegistry>>,
+    
+    /// Released along with the profile so referrals can't name a closed profile
+    #[account(
+        mut,
+        seeds = [b"referral_registry", &referral_code_for(&user_profile.profile_id)],
+        bump,
+        constraint = referral_registry.referrer == user_profile.profile_id
+            @ ErrorCode::InvalidReferrer,
+        close = authority
+    )]
+    pub referral_registry: Option<Account<'info, ReferralRegistry>>,
+    
+    #[account(
+        mut,
+        seeds = [b"streak_freeze", user_profile.profile_id.as_ref()],
+        bump,
+        close = authority
+    )]
+    pub streak_freeze: Option<Account<'info, StreakFreeze>>,
+    
+    /// CHECK: the user's stake position PDA; must not exist
+    #[account(
+        seeds = [b"stake_position", authority.key().as_ref()],
+        bump
+    )]
+    pub stake_position: UncheckedAccount<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"global_stats"],
+        bump
+    )]
+    pub global_stats: Account<'info, GlobalStats>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(quiz_id: String, attempt: u16)]
+pub struct CloseQuizResult<'info> {
+    #[account(
+        seeds = [b"user_profile", authority.key().as_ref()],
+        bump = user_profile.bump,
+        has_one = authority
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        mut,
+        seeds = [
+            b"quiz_result",
+            user_profile.profile_id.as_ref(),
+            &id_seed(&quiz_id),
+            &attempt.to_le_bytes()
+        ],
+        bump,
+        constraint = quiz_result.user == user_profile.profile_id @ ErrorCode::Unauthorized,
+        close = authority
+    )]
+    pub quiz_result: Account<'info, QuizResult>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(achievement_id: String)]
+pub struct CloseAchievement<'info> {
+    #[account(
+        seeds = [b"user_profile", authority.key().as_ref()],
+        bump = user_profile.bump,
+        has_one = authority
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        mut,
+        seeds = [b"achievement", user_profile.profile_id.as_ref(), &id_seed(&achievement_id)],
+        bump,
+        constraint = achievement.user == user_profile.profile_id
+            || achievement.awarded_at == 0 @ ErrorCode::Unauthorized,
+        constraint = !achievement.is_closed() @ ErrorCode::AchievementClosed
+    )]
+    pub achievement: Account<'info, Achievement>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(achievement_id: String)]
+pub struct RevokeAchievement<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", user_profile.authority.as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        mut,
+        seeds = [b"achievement", user_profile.profile_id.as_ref(), &id_seed(&achievement_id)],
+        bump,
+        constraint = achievement.user == user_profile.profile_id @ ErrorCode::Unauthorized,
+        close = authority
+    )]
+    pub achievement: Account<'info, Achievement>,
+    
+    /// CHECK: the achievement's catalog entry PDA; its supply is restored by
+    /// `restore_definition_supply` if it exists
+    #[account(
+        mut,
+        seeds = [b"achievement_def", &id_seed(&achievement_id)],
+        bump
+    )]
+    pub achievement_definition: UncheckedAccount<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"global_stats"],
+        bump
+    )]
+    pub global_stats: Account<'info, GlobalStats>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(quiz_id: String)]
+pub struct CreateQuizMetadata<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + QuizMetadata::INIT_SPACE,
+        seeds = [b"quiz_meta", &id_seed(&quiz_id)],
+        bump
+    )]
+    pub quiz_metadata: Account<'info, QuizMetadata>,
+    
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + QuizStats::INIT_SPACE,
+        seeds = [b"quiz_stats", &id_seed(&quiz_id)],
+        bump
+    )]
+    pub quiz_stats: Account<'info, QuizStats>,
+    
+    #[account(
+        seeds = [b"quiz_creator", creator.key().as_ref()],
+        bump
+    )]
+    pub quiz_creator: Account<'info, QuizCreator>,
+    
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(quiz_id: String, attempt: u16)]
+pub struct StartQuiz<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        seeds = [b"quiz_meta", &id_seed(&quiz_id)],
+        bump
+    )]
+    pub quiz_metadata: Box<Account<'info, QuizMetadata>>,
+    
+    #[account(
+        seeds = [b"user_profile", authority.key().as_ref()],
+        bump = user_profile.bump,
+        has_one = authority
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + QuizAttempt::INIT_SPACE,
+        seeds = [
+            b"quiz_attempt",
+            user_profile.profile_id.as_ref(),
+            &id_seed(&quiz_id),
+            &attempt.to_le_bytes()
+        ],
+        bump
+    )]
+    pub quiz_attempt: Account<'info, QuizAttempt>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(quiz_id: String)]
+pub struct SetQuizAnswers<'info> {
+    #[account(
+        mut,
+        seeds = [b"quiz_meta", &id_seed(&quiz_id)],
+        bump,
+        has_one = creator,
+        realloc = 8 + QuizMetadata::INIT_SPACE,
+        realloc::payer = creator,
+        realloc::zero = false
+    )]
+    pub quiz_metadata: Account<'info, QuizMetadata>,
+    
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(quiz_id: String)]
+pub struct UpdateQuiz<'info> {
+    #[account(
+        mut,
+        seeds = [b"quiz_meta", &id_seed(&quiz_id)],
+        bump,
+        has_one = creator
+    )]
+    pub quiz_metadata: Account<'info, QuizMetadata>,
+    
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(creator: Pubkey)]
+pub struct GrantQuizCreator<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + QuizCreator::INIT_SPACE,
+        seeds = [b"quiz_creator", creator.as_ref()],
+        bump
+    )]
+    pub quiz_creator: Account<'info, QuizCreator>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(creator: Pubkey)]
+pub struct RevokeQuizCreator<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"quiz_creator", creator.as_ref()],
+        bump,
+        close = authority
+    )]
+    pub quiz_creator: Account<'info, QuizCreator>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(quiz_id: String)]
+pub struct DeactivateQuiz<'info> {
+    #[account(
+        mut,
+        seeds = [b"quiz_meta", &id_seed(&quiz_id)],
+        bump,
+        has_one = creator
+    )]
+    pub quiz_metadata: Account<'info, QuizMetadata>,
+    
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(quiz_id: String)]
+pub struct SetEventQuiz<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"quiz_meta", &id_seed(&quiz_id)],
+        bump
+    )]
+    pub quiz_metadata: Account<'info, QuizMetadata>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(leaderboard_type: LeaderboardType, period: u32)]
+pub struct InitializeLeaderboard<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + std::mem::size_of::<Leaderboard>(),
+        seeds = [b"leaderboard", &[leaderboard_type as u8], &period.to_le_bytes()],
+        bump
+    )]
+    pub leaderboard: AccountLoader<'info, Leaderboard>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(leaderboard_type: LeaderboardType, period: u32)]
+pub struct UpdateLeaderboard<'info> {
+    #[account(
+        seeds = [b"user_profile", user_profile.authority.as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        mut,
+        seeds = [b"leaderboard", &[leaderboard_type as u8], &period.to_le_bytes()],
+        bump
+    )]
+    pub leaderboard: AccountLoader<'info, Leaderboard>,
+}
+
+#[derive(Accounts)]
+#[instruction(leaderboard_type: LeaderboardType, period: u32)]
+pub struct AdminResetLeaderboard<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"leaderboard", &[leaderboard_type as u8], &period.to_le_bytes()],
+        bump
+    )]
+    pub leaderboard: AccountLoader<'info, Leaderboard>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(leaderboard_type: LeaderboardType, period: u32)]
+pub struct FinalizeLeaderboard<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"leaderboard", &[leaderboard_type as u8], &period.to_le_bytes()],
+        bump
+    )]
+    pub leaderboard: AccountLoader<'info, Leaderboard>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(week: u32)]
+pub struct InitWeeklyLeaderboard<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + std::mem::size_of::<WeeklyLeaderboard>(),
+        seeds = [b"weekly_leaderboard", &week.to_le_bytes()],
+        bump
+    )]
+    pub weekly_leaderboard: AccountLoader<'info, WeeklyLeaderboard>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(week: u32)]
+pub struct UpdateWeeklyLeaderboard<'info> {
+    #[account(
+        seeds = [b"user_profile", user_profile.authority.as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        mut,
+        seeds = [b"weekly_leaderboard", &week.to_le_bytes()],
+        bump
+    )]
+    pub weekly_leaderboard: AccountLoader<'info, WeeklyLeaderboard>,
+}
+
+#[derive(Accounts)]
+#[instruction(week: u32)]
+pub struct FinalizeWeek<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"weekly_leaderboard", &week.to_le_bytes()],
+        bump
+    )]
+    pub weekly_leaderboard: AccountLoader<'info, WeeklyLeaderboard>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRewardMint<'info> {
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    pub reward_mint: Account<'info, Mint>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SyncLevel<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", user_profile.authority.as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+}
+
+#[derive(Accounts)]
+pub struct MintAchievementCertificate<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+    
+    #[account(
+        mut,
+        seeds = [b"achievement", achievement.user.as_ref(), &id_seed(&achievement.achievement_id)],
+        bump
+    )]
+    pub achievement: Box<Account<'info, Achievement>>,
+    
+    /// Definition of the achievement; supplies the metadata URI when present
+    #[account(
+        seeds = [b"achievement_def", &id_seed(&achievement.achievement_id)],
+        bump
+    )]
+    pub achievement_definition: Option<Box<Account<'info, AchievementDefinition>>>,
+    
+    #[account(
+        init_if_needed,
+        payer = awarder,
+        mint::decimals = 0,
+        mint::authority = mint_authority,
+        mint::freeze_authority = mint_authority,
+        seeds = [b"certificate", achievement.key().as_ref()],
+        bump
+    )]
+    pub certificate_mint: Box<Account<'info, Mint>>,
+    
+    /// CHECK: the achievement holder's current wallet; checked through `user_profile`
+    pub user: UncheckedAccount<'info>,
+    
+    /// The holder's profile, which may have moved wallets since the award
+    #[account(
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump = user_profile.bump,
+        constraint = user_profile.profile_id == achievement.user @ ErrorCode::Unauthorized
+    )]
+    pub user_profile: Box<Account<'info, UserProfile>>,
+    
+    #[account(
+        init_if_needed,
+        payer = awarder,
+        associated_token::mint = certificate_mint,
+        associated_token::authority = user
+    )]
+    pub user_token_account: Box<Account<'info, TokenAccount>>,
+    
+    /// CHECK: PDA that signs as mint and update authority; validated by seeds
+    #[account(
+        seeds = [b"mint_authority"],
+        bump
+    )]
+    pub mint_authority: UncheckedAccount<'info>,
+    
+    /// CHECK: created and validated by the token metadata program
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+    
+    /// CHECK: created and validated by the token metadata program
+    #[account(mut)]
+    pub master_edition: UncheckedAccount<'info>,
+    
+    #[account(
+        mut,
+        constraint = protocol_config.is_awarder(&awarder.key()) @ ErrorCode::UnauthorizedAwarder
+    )]
+    pub awarder: Signer<'info>,
+    
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct TransferUsername<'info> {
+    #[account(
+        mut,
+        seeds = [b"username_registry", &username_seed(&old_profile.username)],
+        bump,
+        constraint = username_registry.profile == old_profile.key()
+    )]
+    pub username_registry: Account<'info, UsernameRegistry>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", old_authority.key().as_ref()],
+        bump
+    )]
+    pub old_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", new_authority.key().as_ref()],
+        bump,
+        constraint = new_profile.key() != old_profile.key() @ ErrorCode::Unauthorized
+    )]
+    pub new_profile: Account<'info, UserProfile>,
+    
+    /// The new profile's current name, released as part of the transfer
+    #[account(
+        mut,
+        seeds = [b"username_registry", &username_seed(&new_profile.username)],
+        bump,
+        constraint = new_profile_registry.profile == new_profile.key(),
+        close = new_authority
+    )]
+    pub new_profile_registry: Option<Account<'info, UsernameRegistry>>,
+    
+    pub old_authority: Signer<'info>,
+    
+    #[account(mut)]
+    pub new_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(new_username: Option<String>)]
+pub struct UpdateProfile<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", authority.key().as_ref()],
+        bump,
+        has_one = authority,
+        realloc = 8 + UserProfile::INIT_SPACE,
+        realloc::payer = authority,
+        realloc::zero = false
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    /// Registry entry for the current username; required when renaming
+    #[account(
+        mut,
+        seeds = [b"username_registry", &username_seed(&user_profile.username)],
+        bump,
+        constraint = current_registry.profile == user_profile.key()
+    )]
+    pub current_registry: Option<Account<'info, UsernameRegistry>>,
+    
+    /// Registry entry for the new username; required when renaming
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + UsernameRegistry::INIT_SPACE,
+        seeds = [
+            b"username_registry",
+            &username_seed(new_username.as_deref().unwrap_or_default())
+        ],
+        bump
+    )]
+    pub new_registry: Option<Account<'info, UsernameRegistry>>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetDelegate<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", authority.key().as_ref()],
+        bump = user_profile.bump,
+        has_one = authority
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct TransferAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"user_profile", old_authority.key().as_ref()],
+        bump,
+        constraint = old_profile.authority == old_authority.key() @ ErrorCode::Unauthorized,
+        close = old_authority
+    )]
+    pub old_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        init,
+        payer = new_authority,
+        space = 8 + UserProfile::INIT_SPACE,
+        seeds = [b"user_profile", new_authority.key().as_ref()],
+        bump
+    )]
+    pub new_profile: Account<'info, UserProfile>,
+    
+    /// Registry entry for the profile's username, re-pointed at the new profile
+    #[account(
+        mut,
+        seeds = [b"username_registry", &username_seed(&old_profile.username)],
+        bump,
+        constraint = username_registry.profile == old_profile.key()
+    )]
+    pub username_registry: Option<Account<'info, UsernameRegistry>>,
+    
+    /// The profile's referral code entry; must exist, so the old wallet can't
+    /// open a new profile under the same profile id
+    #[account(
+        seeds = [b"referral_registry", &referral_code_for(&old_profile.profile_id)],
+        bump,
+        constraint = referral_registry.referrer == old_profile.profile_id
+            @ ErrorCode::InvalidReferrer
+    )]
+    pub referral_registry: Account<'info, ReferralRegistry>,
+    
+    /// CHECK: the old wallet's stake position PDA; must not exist
+    #[account(
+        seeds = [b"stake_position", old_authority.key().as_ref()],
+        bump
+    )]
+    pub stake_position: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub old_authority: Signer<'info>,
+    
+    #[account(mut)]
+    pub new_authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(new_username: String)]
+pub struct UpdateUsername<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", authority.key().as_ref()],
+        bump,
+        has_one = authority,
+        realloc = 8 + UserProfile::INIT_SPACE,
+        realloc::payer = authority,
+        realloc::zero = false
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    /// Registry entry for the current username; required unless the profile has none
+    #[account(
+        mut,
+        seeds = [b"username_registry", &username_seed(&user_profile.username)],
+        bump,
+        constraint = current_registry.profile == user_profile.key()
+    )]
+    pub current_registry: Option<Account<'info, UsernameRegistry>>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + UsernameRegistry::INIT_SPACE,
+        seeds = [b"username_registry", &username_seed(&new_username)],
+        bump
+    )]
+    pub new_registry: Account<'info, UsernameRegistry>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(course_id: String)]
+pub struct CreateCourse<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + Course::INIT_SPACE,
+        seeds = [b"course", &id_seed(&course_id)],
+        bump
+    )]
+    pub course: Box<Account<'info, Course>>,
+    
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        seeds = [b"quiz_creator", creator.key().as_ref()],
+        bump
+    )]
+    pub quiz_creator: Account<'info, QuizCreator>,
+    
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(course_id: String)]
+pub struct ApproveCourse<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"course", &id_seed(&course_id)],
+        bump
+    )]
+    pub course: Box<Account<'info, Course>>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(course_id: String)]
+pub struct EnrollInCourse<'info> {
+    #[account(
+        seeds = [b"course", &id_seed(&course_id)],
+        bump
+    )]
+    pub course: Box<Account<'info, Course>>,
+    
+    #[account(
+        seeds = [b"user_profile", authority.key().as_ref()],
+        bump = user_profile.bump,
+        has_one = authority
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + CourseEnrollment::INIT_SPACE,
+        seeds = [b"enrollment", user_profile.profile_id.as_ref(), &id_seed(&course_id)],
+        bump
+    )]
+    pub enrollment: Account<'info, CourseEnrollment>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(course_id: String)]
+pub struct CompleteCourse<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", authority.key().as_ref()],
+        bump = user_profile.bump,
+        has_one = authority
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        seeds = [b"course", &id_seed(&course_id)],
+        bump
+    )]
+    pub course: Box<Account<'info, Course>>,
+    
+    #[account(
+        mut,
+        seeds = [b"enrollment", user_profile.profile_id.as_ref(), &id_seed(&course_id)],
+        bump
+    )]
+    pub enrollment: Account<'info, CourseEnrollment>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(
+    path_id: String,
+    title: String,
+    courses: Vec<String>,
+    completion_badge_achievement_id: String
+)]
+pub struct CreateLearningPath<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + LearningPath::INIT_SPACE,
+        seeds = [b"learning_path", &id_seed(&path_id)],
+        bump
+    )]
+    pub learning_path: Box<Account<'info, LearningPath>>,
+    
+    /// Catalog entry for the completion badge; it must exist
+    #[account(
+        seeds = [b"achievement_def", &id_seed(&completion_badge_achievement_id)],
+        bump
+    )]
+    pub badge_definition: Box<Account<'info, AchievementDefinition>>,
+    
+    #[account(
+        seeds = [b"quiz_creator", creator.key().as_ref()],
+        bump
+    )]
+    pub quiz_creator: Account<'info, QuizCreator>,
+    
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(path_id: String)]
+pub struct EnrollInLearningPath<'info> {
+    #[account(
+        seeds = [b"learning_path", &id_seed(&path_id)],
+        bump
+    )]
+    pub learning_path: Box<Account<'info, LearningPath>>,
+    
+    #[account(
+        seeds = [b"user_profile", authority.key().as_ref()],
+        bump = user_profile.bump,
+        has_one = authority
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + LearningPathEnrollment::INIT_SPACE,
+        seeds = [b"path_enrollment", user_profile.profile_id.as_ref(), &id_seed(&path_id)],
+        bump
+    )]
+    pub path_enrollment: Account<'info, LearningPathEnrollment>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(path_id: String)]
+pub struct CompleteLearningPath<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", authority.key().as_ref()],
+        bump = user_profile.bump,
+        has_one = authority
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        seeds = [b"learning_path", &id_seed(&path_id)],
+        bump
+    )]
+    pub learning_path: Box<Account<'info, LearningPath>>,
+    
+    #[account(
+        mut,
+        seeds = [b"path_enrollment", user_profile.profile_id.as_ref(), &id_seed(&path_id)],
+        bump
+    )]
+    pub path_enrollment: Account<'info, LearningPathEnrollment>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + Achievement::INIT_SPACE,
+        seeds = [
+            b"achievement",
+            user_profile.profile_id.as_ref(),
+            &id_seed(&learning_path.completion_badge_achievement_id)
+        ],
+        bump
+    )]
+    pub badge_achievement: Box<Account<'info, Achievement>>,
+    
+    #[account(
+        mut,
+        seeds = [b"achievement_def", &id_seed(&learning_path.completion_badge_achievement_id)],
+        bump
+    )]
+    pub badge_definition: Box<Account<'info, AchievementDefinition>>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterMentor<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        seeds = [b"user_profile", authority.key().as_ref()],
+        bump = user_profile.bump,
+        has_one = authority
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + MentorProfile::INIT_SPACE,
+        seeds = [b"mentor_profile", authority.key().as_ref()],
+        bump
+    )]
+    pub mentor_profile: Account<'info, MentorProfile>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMentorProfile<'info> {
+    #[account(
+        mut,
+        seeds = [b"mentor_profile", authority.key().as_ref()],
+        bump = mentor_profile.bump,
+        has_one = authority
+    )]
+    pub mentor_profile: Account<'info, MentorProfile>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(quiz_id: String)]
+pub struct InitQuizLeaderboard<'info> {
+    #[account(
+        seeds = [b"quiz_meta", &id_seed(&quiz_id)],
+        bump
+    )]
+    pub quiz_metadata: Account<'info, QuizMetadata>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + std::mem::size_of::<QuizLeaderboard>(),
+        seeds = [b"quiz_leaderboard", &id_seed(&quiz_id)],
+        bump
+    )]
+    pub quiz_leaderboard: AccountLoader<'info, QuizLeaderboard>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(quiz_id: String)]
+pub struct UpdateQuizLeaderboard<'info> {
+    #[account(
+        seeds = [b"quiz_meta", &id_seed(&quiz_id)],
+        bump
+    )]
+    pub quiz_metadata: Box<Account<'info, QuizMetadata>>,
+    
+    #[account(
+        mut,
+        seeds = [b"quiz_leaderboard", &id_seed(&quiz_id)],
+        bump
+    )]
+    pub quiz_leaderboard: AccountLoader<'info, QuizLeaderboard>,
+    
+    #[account(
+        seeds = [
+            b"quiz_result",
+            quiz_result.user.as_ref(),
+            &id_seed(&quiz_id),
+            &quiz_result.attempt.to_le_bytes()
+        ],
+        bump = quiz_result.bump
+    )]
+    pub quiz_result: Box<Account<'info, QuizResult>>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeGlobalStats<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + GlobalStats::INIT_SPACE,
+        seeds = [b"global_stats"],
+        bump
+    )]
+    pub global_stats: Account<'info, GlobalStats>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(session_nonce: u64)]
+pub struct BookSession<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        seeds = [b"mentor_profile", mentor_profile.authority.as_ref()],
+        bump = mentor_profile.bump
+    )]
+    pub mentor_profile: Account<'info, MentorProfile>,
+    
+    #[account(
+        init,
+        payer = student,
+        space = 8 + Session::INIT_SPACE,
+        seeds = [
+            b"session",
+            student.key().as_ref(),
+            mentor_profile.authority.as_ref(),
+            &session_nonce.to_le_bytes()
+        ],
+        bump
+    )]
+    pub session: Account<'info, Session>,
+    
+    #[account(mut)]
+    pub student: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CompleteSession<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"mentor_profile", mentor.key().as_ref()],
+        bump = mentor_profile.bump
+    )]
+    pub mentor_profile: Account<'info, MentorProfile>,
+    
+    #[account(
+        mut,
+        seeds = [
+            b"session",
+            session.student.as_ref(),
+            mentor.key().as_ref(),
+            &session.session_nonce.to_le_bytes()
+        ],
+        bump = session.bump,
+        has_one = mentor @ ErrorCode::Unauthorized
+    )]
+    pub session: Account<'info, Session>,
+    
+    /// Receives the protocol fee
+    #[account(mut, address = protocol_config.authority @ ErrorCode::Unauthorized)]
+    pub fee_recipient: SystemAccount<'info>,
+    
+    #[account(mut)]
+    pub mentor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelSession<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"session",
+            student.key().as_ref(),
+            session.mentor.as_ref(),
+            &session.session_nonce.to_le_bytes()
+        ],
+        bump = session.bump,
+        has_one = student @ ErrorCode::Unauthorized
+    )]
+    pub session: Account<'info, Session>,
+    
+    #[account(mut)]
+    pub student: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AdjustXp<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", user_profile.authority.as_ref()],
+        bump = user_profile.bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitSessionReview<'info> {
+    #[account(
+        seeds = [
+            b"session",
+            session.student.as_ref(),
+            session.mentor.as_ref(),
+            &session.session_nonce.to_le_bytes()
+        ],
+        bump = session.bump,
+        constraint = session.student == reviewer.key() @ ErrorCode::Unauthorized
+    )]
+    pub session: Account<'info, Session>,
+    
+    #[account(
+        mut,
+        seeds = [b"mentor_profile", session.mentor.as_ref()],
+        bump = mentor_profile.bump
+    )]
+    pub mentor_profile: Account<'info, MentorProfile>,
+    
+    #[account(
+        init,
+        payer = reviewer,
+        space = 8 + SessionReview::INIT_SPACE,
+        seeds = [b"session_review", session.key().as_ref()],
+        bump
+    )]
+    pub session_review: Account<'info, SessionReview>,
+    
+    #[account(mut)]
+    pub reviewer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClaimRewards<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", authority.key().as_ref()],
+        bump = user_profile.bump,
+        has_one = authority
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        mut,
+        address = protocol_config.reward_mint @ ErrorCode::InvalidRewardMint
+    )]
+    pub reward_mint: Account<'info, Mint>,
+    
+    /// The user's associated token account for the reward mint; created if missing
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = reward_mint,
+        associated_token::authority = authority
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    
+    /// CHECK: PDA that signs as the reward mint authority; validated by seeds
+    #[account(
+        seeds = [b"mint_authority"],
+        bump = protocol_config.mint_authority_bump
+    )]
+    pub mint_authority: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMetadata<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", authority.key().as_ref()],
+        bump,
+        has_one = authority,
+        realloc = 8 + UserProfile::INIT_SPACE,
+        realloc::payer = authority,
+        realloc::zero = false
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterReferralCode<'info> {
+    #[account(
+        mut,
+        seeds = [b"user_profile", authority.key().as_ref()],
+        bump = user_profile.bump,
+        has_one = authority
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ReferralRegistry::INIT_SPACE,
+        seeds = [b"referral_registry", &referral_code_for(&user_profile.profile_id)],
+        bump
+    )]
+    pub referral_registry: Account<'info, ReferralRegistry>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimReferralReward<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", authority.key().as_ref()],
+        bump = user_profile.bump,
+        has_one = authority
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", referrer_profile.authority.as_ref()],
+        bump = referrer_profile.bump,
+        constraint = user_profile.referred_by == Some(referrer_profile.profile_id)
+            @ ErrorCode::InvalidReferrer
+    )]
+    pub referrer_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        mut,
+        seeds = [b"global_stats"],
+        bump
+    )]
+    pub global_stats: Account<'info, GlobalStats>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct StakeTokens<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + StakePosition::INIT_SPACE,
+        seeds = [b"stake_position", authority.key().as_ref()],
+        bump
+    )]
+    pub stake_position: Account<'info, StakePosition>,
+    
+    #[account(address = protocol_config.reward_mint @ ErrorCode::InvalidRewardMint)]
+    pub reward_mint: Box<Account<'info, Mint>>,
+    
+    /// Program-owned vault holding every stake; it is its own authority
+    #[account(
+        init_if_needed,
+        payer = authority,
+        token::mint = reward_mint,
+        token::authority = stake_vault,
+        seeds = [b"stake_vault"],
+        bump
+    )]
+    pub stake_vault: Box<Account<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        token::mint = reward_mint,
+        token::authority = authority
+    )]
+    pub user_token_account: Box<Account<'info, TokenAccount>>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UnstakeTokens<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+    
+    #[account(
+        mut,
+        seeds = [b"stake_position", authority.key().as_ref()],
+        bump = stake_position.bump,
+        constraint = stake_position.user == authority.key() @ ErrorCode::Unauthorized,
+        close = authority
+    )]
+    pub stake_position: Account<'info, StakePosition>,
+    
+    #[account(
+        mut,
+        seeds = [b"stake_vault"],
+        bump
+    )]
+    pub stake_vault: Box<Account<'info, TokenAccount>>,
+    
+    #[account(
+        mut,
+        token::mint = stake_vault.mint,
+        token::authority = authority
+    )]
+    pub user_token_account: Box<Account<'info, TokenAccount>>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(guild_id: String)]
+pub struct CreateGuild<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        init,
+        payer = founder,
+        space = 8 + Guild::INIT_SPACE,
+        seeds = [b"guild", &id_seed(&guild_id)],
+        bump
+    )]
+    pub guild: Account<'info, Guild>,
+    
+    #[account(mut)]
+    pub founder: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(guild_id: String)]
+pub struct JoinGuild<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", authority.key().as_ref()],
+        bump = user_profile.bump,
+        has_one = authority
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        mut,
+        seeds = [b"guild", &id_seed(&guild_id)],
+        bump
+    )]
+    pub guild: Account<'info, Guild>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + GuildMembership::INIT_SPACE,
+        seeds = [b"guild_member", authority.key().as_ref(), &id_seed(&guild_id)],
+        bump
+    )]
+    pub guild_membership: Account<'info, GuildMembership>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(guild_id: String)]
+pub struct UpdateGuildXp<'info> {
+    #[account(
+        seeds = [b"user_profile", user_profile.authority.as_ref()],
+        bump = user_profile.bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        mut,
+        seeds = [b"guild", &id_seed(&guild_id)],
+        bump
+    )]
+    pub guild: Account<'info, Guild>,
+    
+    #[account(
+        mut,
+        seeds = [b"guild_member", user_profile.authority.as_ref(), &id_seed(&guild_id)],
+        bump
+    )]
+    pub guild_membership: Account<'info, GuildMembership>,
+}
+
+#[derive(Accounts)]
+#[instruction(guild_id: String)]
+pub struct LeaveGuild<'info> {
+    #[account(
+        mut,
+        seeds = [b"user_profile", authority.key().as_ref()],
+        bump = user_profile.bump,
+        has_one = authority
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        mut,
+        seeds = [b"guild", &id_seed(&guild_id)],
+        bump
+    )]
+    pub guild: Account<'info, Guild>,
+    
+    #[account(
+        mut,
+        seeds = [b"guild_member", authority.key().as_ref(), &id_seed(&guild_id)],
+        bump,
+        close = authority
+    )]
+    pub guild_membership: Account<'info, GuildMembership>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(challenged: Pubkey, quiz_id: String)]
+pub struct SendChallenge<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", challenger.key().as_ref()],
+        bump = challenger_profile.bump
+    )]
+    pub challenger_profile: Box<Account<'info, UserProfile>>,
+    
+    /// The challenged user must have a profile
+    #[account(
+        seeds = [b"user_profile", challenged.as_ref()],
+        bump = challenged_profile.bump
+    )]
+    pub challenged_profile: Box<Account<'info, UserProfile>>,
+    
+    #[account(
+        seeds = [b"quiz_meta", &id_seed(&quiz_id)],
+        bump
+    )]
+    pub quiz_metadata: Box<Account<'info, QuizMetadata>>,
+    
+    /// CHECK: the challenger's progress tracker PDA for the quiz; read by
+    /// `next_quiz_attempt`
+    #[account(
+        seeds = [b"quiz_progress", challenger_profile.profile_id.as_ref(), &id_seed(&quiz_id)],
+        bump
+    )]
+    pub challenger_progress: UncheckedAccount<'info>,
+    
+    #[account(
+        init,
+        payer = challenger,
+        space = 8 + Challenge::INIT_SPACE,
+        seeds = [b"challenge", challenger.key().as_ref(), challenged.as_ref(), &id_seed(&quiz_id)],
+        bump
+    )]
+    pub challenge: Account<'info, Challenge>,
+    
+    #[account(mut)]
+    pub challenger: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptChallenge<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+    
+    #[account(
+        mut,
+        seeds = [
+            b"challenge",
+            challenge.challenger.as_ref(),
+            challenged.key().as_ref(),
+            &id_seed(&challenge.quiz_id)
+        ],
+        bump = challenge.bump
+    )]
+    pub challenge: Account<'info, Challenge>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", challenged.key().as_ref()],
+        bump = challenged_profile.bump
+    )]
+    pub challenged_profile: Box<Account<'info, UserProfile>>,
+    
+    /// CHECK: the challenged user's progress tracker PDA for the quiz; read
+    /// by `next_quiz_attempt`
+    #[account(
+        seeds = [
+            b"quiz_progress",
+            challenged_profile.profile_id.as_ref(),
+            &id_seed(&challenge.quiz_id)
+        ],
+        bump
+    )]
+    pub challenged_progress: UncheckedAccount<'info>,
+    
+    pub challenged: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitChallengeScore<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"challenge",
+            challenge.challenger.as_ref(),
+            challenge.challenged.as_ref(),
+            &id_seed(&challenge.quiz_id)
+        ],
+        bump = challenge.bump
+    )]
+    pub challenge: Account<'info, Challenge>,
+    
+    #[account(
+        seeds = [b"user_profile", participant.key().as_ref()],
+        bump = participant_profile.bump
+    )]
+    pub participant_profile: Box<Account<'info, UserProfile>>,
+    
+    /// The participant's result for the challenge quiz
+    #[account(
+        seeds = [
+            b"quiz_result",
+            participant_profile.profile_id.as_ref(),
+            &id_seed(&quiz_result.quiz_id),
+            &quiz_result.attempt.to_le_bytes()
+        ],
+        bump = quiz_result.bump
+    )]
+    pub quiz_result: Account<'info, QuizResult>,
+    
+    pub participant: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ResolveChallenge<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+    
+    #[account(
+        mut,
+        seeds = [
+            b"challenge",
+            challenge.challenger.as_ref(),
+            challenge.challenged.as_ref(),
+            &id_seed(&challenge.quiz_id)
+        ],
+        bump = challenge.bump
+    )]
+    pub challenge: Account<'info, Challenge>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", challenge.challenger.as_ref()],
+        bump = challenger_profile.bump
+    )]
+    pub challenger_profile: Box<Account<'info, UserProfile>>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", challenge.challenged.as_ref()],
+        bump = challenged_profile.bump
+    )]
+    pub challenged_profile: Box<Account<'info, UserProfile>>,
+}
+
+#[derive(Accounts)]
+#[instruction(tournament_id: String, quiz_id: String)]
+pub struct CreateTournament<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+    
+    #[account(
+        seeds = [b"quiz_meta", &id_seed(&quiz_id)],
+        bump
+    )]
+    pub quiz_metadata: Box<Account<'info, QuizMetadata>>,
+    
+    #[account(
+        init,
+        payer = organizer,
+        space = 8 + Tournament::INIT_SPACE,
+        seeds = [b"tournament", &id_seed(&tournament_id)],
+        bump
+    )]
+    pub tournament: Box<Account<'info, Tournament>>,
+    
+    #[account(mut)]
+    pub organizer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterForTournament<'info> {
+    #[account(
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+    
+    #[account(
+        mut,
+        seeds = [b"tournament", &id_seed(&tournament.tournament_id)],
+        bump = tournament.bump
+    )]
+    pub tournament: Box<Account<'info, Tournament>>,
+    
+    /// Only players with a profile can register; it stays open until the
+    /// tournament ends
+    #[account(
+        mut,
+        seeds = [b"user_profile", player.key().as_ref()],
+        bump = user_profile.bump
+    )]
+    pub user_profile: Box<Account<'info, UserProfile>>,
+    
+    /// CHECK: the player's progress tracker PDA for the tournament quiz;
+    /// read by `next_quiz_attempt`
+    #[account(
+        seeds = [
+            b"quiz_progress",
+            user_profile.profile_id.as_ref(),
+            &id_seed(&tournament.quiz_id)
+        ],
+        bump
+    )]
+    pub quiz_progress: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub player: Signer<'info>,
+    
     pub system_program: Program<'info, System>,
 }
 
-#[derive(Accounts)]
-#[instruction(achievement_id: String)]
-pub struct AwardAchievement<'info> {
-    #[account(
-        mut,
-        seeds = [b"user_profile", authority.key().as_ref()],
-        bump,
-        has_one = authority
-    )]
-    pub user_profile: Account<'info, UserProfile>,
-    
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + Achievement::INIT_SPACE,
-        seeds = [b"achievement", authority.key().as_ref(), achievement_id.as_bytes()],
-        bump
-    )]
-    pub achievement: Account<'info, Achievement>,
+#[derive(Accounts)]
+pub struct SubmitTournamentScore<'info> {
+    #[account(
+        mut,
+        seeds = [b"tournament", &id_seed(&tournament.tournament_id)],
+        bump = tournament.bump
+    )]
+    pub tournament: Box<Account<'info, Tournament>>,
+    
+    #[account(
+        seeds = [b"user_profile", participant.key().as_ref()],
+        bump = participant_profile.bump
+    )]
+    pub participant_profile: Box<Account<'info, UserProfile>>,
+    
+    /// The participant's result for the tournament quiz
+    #[account(
+        seeds = [
+            b"quiz_result",
+            participant_profile.profile_id.as_ref(),
+            &id_seed(&quiz_result.quiz_id),
+            &quiz_result.attempt.to_le_bytes()
+        ],
+        bump = quiz_result.bump
+    )]
+    pub quiz_result: Account<'info, QuizResult>,
+    
+    pub participant: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct FinalizeTournament<'info> {
+    #[account(
+        mut,
+        seeds = [b"tournament", &id_seed(&tournament.tournament_id)],
+        bump = tournament.bump
+    )]
+    pub tournament: Box<Account<'info, Tournament>>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ProtocolConfig {
+    pub authority: Pubkey,         // 32
+    pub achievement_authority: Pubkey, // 32
+    pub xp_per_correct: u64,       // 8
+    pub perfect_score_bonus: u64,  // 8
+    pub level_curve: LevelCurve, // 10
+    pub streak_window_secs: i64,   // 8
+    pub streak_grace_secs: i64,    // 8
+    pub tier_bonuses: [u64; 5],    // 8 * 5
+    pub reward_mint: Pubkey,       // 32
+    pub tokens_per_xp: u64,        // 8
+    pub mint_authority_bump: u8,   // 1
+    pub paused: bool,              // 1
+    pub quiz_cooldown_secs: i64,   // 8
+    pub streak_milestones: [u64; 8], // 8 * 8
+    pub streak_bonus_xp: [u64; 8], // 8 * 8
+    pub streak_freeze_cost_xp: u64, // 8
+    pub daily_bonus_xp: u64,       // 8
+    pub speed_threshold_seconds: u32, // 4
+    pub speed_bonus_xp: u64,       // 8
+    pub hint_penalty_xp: u64,      // 8
+    pub retake_decay_bps: u16,     // 2
+    pub min_retake_multiplier_bps: u16, // 2
+    pub difficulty_multipliers_bps: [u16; 4], // 2 * 4
+    pub min_time_taken_seconds: u32, // 4
+    pub min_mentor_level: u64,     // 8
+    pub session_fee_bps: u16,      // 2
+    pub xp_event_multiplier: u16,  // 2, basis points
+    pub xp_event_start: i64,       // 8
+    pub xp_event_end: i64,         // 8
+    pub min_reward_score_bps: u16, // 2
+    pub daily_token_cap: u64,      // 8, 0 = uncapped
+    pub referral_bonus_xp: u64,    // 8
+    pub min_reward_claim: u64,     // 8
+    pub tier_royalty_bps: [u16; 5], // 2 * 5
+    pub stake_boost_bps_per_thousand: u16, // 2
+    pub max_stake_boost_bps: u16,  // 2
+    pub stake_boost_secs: i64,     // 8
+    pub stake_min_lock_secs: i64,  // 8
+    pub attestor: Pubkey,          // 32, default = no attestation required
+    pub version: u8,               // 1, layout version, see `migrate_config`
+    pub max_course_reward_xp: u64, // 8
+    pub course_approval_xp: u64,   // 8, larger course rewards need approval
+}
+
+impl ProtocolConfig {
+    /// Fail with `ProgramPaused` while `set_pause` has halted user instructions
+    pub fn ensure_not_paused(&self) -> Result<()> {
+        require!(!self.paused, ErrorCode::ProgramPaused);
+        Ok(())
+    }
+
+    /// Whether `signer` may award achievements: the config authority or the
+    /// designated achievement authority
+    pub fn is_awarder(&self, signer: &Pubkey) -> bool {
+        *signer == self.authority || *signer == self.achievement_authority
+    }
+
+    /// Check a course completion reward against the cap; returns whether it
+    /// is approved without the config authority's sign-off
+    pub fn course_reward_approved(&self, completion_reward_xp: u64) -> Result<bool> {
+        require!(completion_reward_xp <= self.max_course_reward_xp, ErrorCode::CourseRewardTooHigh);
+        Ok(completion_reward_xp <= self.course_approval_xp)
+    }
+
+    /// Seconds left at `now` before a user whose last submission was at
+    /// `last_quiz_at` may submit again; 0 once the cooldown has passed
+    pub fn quiz_cooldown_remaining(&self, last_quiz_at: i64, now: i64) -> i64 {
+        self.quiz_cooldown_secs
+            .saturating_sub(now.saturating_sub(last_quiz_at))
+            .max(0)
+    }
+
+    /// Bonus XP granted for an achievement of the given tier
+    pub fn tier_bonus(&self, tier: &AchievementTier) -> u64 {
+        match tier {
+            AchievementTier::Bronze => self.tier_bonuses[0],
+            AchievementTier::Silver => self.tier_bonuses[1],
+            AchievementTier::Gold => self.tier_bonuses[2],
+            AchievementTier::Platinum => self.tier_bonuses[3],
+            AchievementTier::Diamond => self.tier_bonuses[4],
+        }
+    }
+
+    /// Royalty in basis points on certificate NFTs of the given tier
+    pub fn tier_royalty_bps(&self, tier: &AchievementTier) -> u16 {
+        match tier {
+            AchievementTier::Bronze => self.tier_royalty_bps[0],
+            AchievementTier::Silver => self.tier_royalty_bps[1],
+            AchievementTier::Gold => self.tier_royalty_bps[2],
+            AchievementTier::Platinum => self.tier_royalty_bps[3],
+            AchievementTier::Diamond => self.tier_royalty_bps[4],
+        }
+    }
+
+    /// XP multiplier in basis points for a quiz of the given difficulty
+    pub fn difficulty_multiplier_bps(&self, difficulty: &Difficulty) -> u16 {
+        match difficulty {
+            Difficulty::Easy => self.difficulty_multipliers_bps[0],
+            Difficulty::Medium => self.difficulty_multipliers_bps[1],
+            Difficulty::Hard => self.difficulty_multipliers_bps[2],
+            Difficulty::Expert => self.difficulty_multipliers_bps[3],
+        }
+    }
+
+    /// XP for a graded attempt, before retake improvement, events and stakes
+    /// are taken into account: XP per point plus the perfect-score bonus,
+    /// scaled by difficulty and decayed on retakes; a bonus for a fast
+    /// attempt that scored; minus the hint penalty. Multipliers are applied
+    /// in u128 and round down.
+    pub fn attempt_xp(
+        &self,
+        graded: &GradedScore,
+        difficulty_multiplier_bps: u16,
+        attempt: u16,
+        time_taken_seconds: u32,
+        hints_used: u8,
+    ) -> Result<u64> {
+        let perfect_bonus = if graded.points_earned == graded.points_possible {
+            self.perfect_score_bonus
+        } else {
+            0
+        };
+        let speed_bonus = if graded.points_earned > 0 {
+            self.speed_bonus(time_taken_seconds)
+        } else {
+            0
+        };
+        let base_xp =
+            (graded.points_earned as u128) * (self.xp_per_correct as u128) + perfect_bonus as u128;
+        let weighted_xp =
+            base_xp * (difficulty_multiplier_bps as u128) / BASE_MULTIPLIER_BPS as u128;
+        let decayed_xp = weighted_xp
+            * self.retake_multiplier_bps(attempt) as u128
+            / BASE_MULTIPLIER_BPS as u128;
+        let attempt_xp = u64::try_from(decayed_xp)
+            .ok()
+            .and_then(|xp| xp.checked_add(speed_bonus))
+            .ok_or(ErrorCode::NumericOverflow)?
+            .saturating_sub((hints_used as u64).saturating_mul(self.hint_penalty_xp));
+        Ok(attempt_xp)
+    }
+
+    /// Quiz `xp` boosted by the XP event running at `now`, if any, and then
+    /// by a stake boost of `stake_boost_bps`. Rounds down.
+    pub fn boosted_xp(&self, xp: u64, stake_boost_bps: u16, now: i64) -> Result<u64> {
+        let boosted_xp = u64::try_from(
+            xp as u128 * self.xp_event_multiplier_bps(now) as u128 / BASE_MULTIPLIER_BPS as u128
+                * (BASE_MULTIPLIER_BPS as u128 + stake_boost_bps as u128)
+                / BASE_MULTIPLIER_BPS as u128,
+        )
+        .map_err(|_| ErrorCode::NumericOverflow)?;
+        Ok(boosted_xp)
+    }
+
+    /// Quiz XP multiplier in basis points at `now`: the event multiplier
+    /// inside the `[xp_event_start, xp_event_end)` window, otherwise 1x
+    pub fn xp_event_multiplier_bps(&self, now: i64) -> u16 {
+        if (self.xp_event_start..self.xp_event_end).contains(&now) {
+            self.xp_event_multiplier
+        } else {
+            BASE_MULTIPLIER_BPS
+        }
+    }
+
+    /// Reject a reported time below the configured minimum (never below one
+    /// second) or above `MAX_TIME_TAKEN_SECONDS`
+    pub fn ensure_plausible_time(&self, time_taken_seconds: u32) -> Result<()> {
+        let min_time_taken_seconds = self.min_time_taken_seconds.max(1);
+        require!(
+            (min_time_taken_seconds..=MAX_TIME_TAKEN_SECONDS).contains(&time_taken_seconds),
+            ErrorCode::InvalidTimeTaken
+        );
+        Ok(())
+    }
+
+    /// Require the attestor's signature over `message`, made at `attested_at`
+    /// no more than `ATTESTATION_MAX_AGE` seconds before `now`, when an
+    /// attestor is configured. `instructions` is the instructions sysvar.
+    pub fn ensure_attested(
+        &self,
+        instructions: Option<&AccountInfo>,
+        message: &[u8],
+        attestation: &[u8; 64],
+        attested_at: i64,
+        now: i64,
+    ) -> Result<()> {
+        if self.attestor == Pubkey::default() {
+            return Ok(());
+        }
+        require!(
+            attested_at <= now && now - attested_at <= ATTESTATION_MAX_AGE,
+            ErrorCode::InvalidAttestation
+        );
+        let instructions = instructions.ok_or(ErrorCode::InvalidAttestation)?;
+        verify_ed25519_attestation(instructions, &self.attestor, attestation, message)
+    }
+
+    /// Bonus XP for finishing a quiz in `time_taken_seconds`: nothing at or
+    /// above the speed threshold, rising linearly to the full `speed_bonus_xp`
+    /// at the fastest accepted time
+    pub fn speed_bonus(&self, time_taken_seconds: u32) -> u64 {
+        if time_taken_seconds >= self.speed_threshold_seconds {
+            return 0;
+        }
+        let window = self
+            .speed_threshold_seconds
+            .saturating_sub(self.min_time_taken_seconds.max(1))
+            .max(1);
+        let saved = (self.speed_threshold_seconds - time_taken_seconds).min(window);
+        (self.speed_bonus_xp as u128 * saved as u128 / window as u128) as u64
+    }
+
+    /// Bonus XP for reaching a streak of exactly `streak` days, if it is a milestone
+    pub fn streak_milestone_bonus(&self, streak: u64) -> Option<u64> {
+        self.streak_milestones
+            .iter()
+            .position(|&milestone| milestone != 0 && milestone == streak)
+            .map(|i| self.streak_bonus_xp[i])
+    }
+
+    /// XP multiplier in basis points for the given attempt number: full XP on
+    /// the first attempt, then `retake_decay_bps` applied per retake, never
+    /// below `min_retake_multiplier_bps`
+    pub fn retake_multiplier_bps(&self, attempt: u16) -> u16 {
+        let mut multiplier = BASE_MULTIPLIER_BPS as u32;
+        for _ in 0..attempt {
+            if multiplier <= self.min_retake_multiplier_bps as u32 {
+                break;
+            }
+            multiplier = multiplier * self.retake_decay_bps as u32 / BASE_MULTIPLIER_BPS as u32;
+        }
+        multiplier.max(self.min_retake_multiplier_bps as u32) as u16
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct LevelCurve {
+    pub base_xp: u64,              // 8
+    pub growth_factor_bps: u16,    // 2
+}
+
+impl LevelCurve {
+    /// Levels must cost XP and never get cheaper
+    pub fn is_valid(&self) -> bool {
+        self.base_xp > 0 && self.growth_factor_bps >= BASE_MULTIPLIER_BPS
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct UserProfile {
+    pub authority: Pubkey,        // 32
+    #[max_len(32)]
+    pub username: String,          // 4 + 32
+    pub xp: u64,                   // 8
+    pub level: u64,                // 8
+    pub streak: u64,               // 8
+    pub quizzes_completed: u64,    // 8
+    pub achievements_earned: u64,  // 8
+    pub created_at: i64,           // 8
+    pub last_active: i64,          // 8
+    pub streak_frozen: bool,       // 1
+    pub last_streak_day: i64,      // 8
+    #[max_len(256)]
+    pub bio: String,               // 4 + 256
+    pub last_username_change: i64, // 8
+    pub bump: u8,                  // 1
+    pub last_quiz_at: i64,         // 8
+    pub last_daily_claim: i64,     // 8
+    pub weekly_xp: u64,            // 8
+    pub week_index: u32,           // 4
+    pub tokens_minted_today: u64,  // 8
+    pub token_mint_day: i64,       // 8
+    pub referrals_count: u64,      // 8
+    pub pending_rewards: u64,      // 8
+    #[max_len(200)]
+    pub avatar_uri: String,        // 4 + 200
+    #[max_len(100)]
+    pub social_link: String,       // 4 + 100
+    pub referral_code: [u8; 8],    // 8
+    pub referred_by: Option<Pubkey>, // 1 + 32
+    pub referral_claimed: bool,    // 1
+    pub schema_version: u8,        // 1
+    pub guild: Option<Pubkey>,     // 1 + 32
+    pub delegate: Option<Pubkey>,  // 1 + 32, session key allowed to submit quizzes
+    pub submission_nonce: u64,     // 8, expected by the next quiz submission
+    pub open_challenges: u16,      // 2, sent or accepted and not yet resolved
+    pub tournament_locked_until: i64, // 8, end of the latest tournament entered
+    pub monthly_xp: u64,           // 8
+    pub month_index: u32,          // 4
+    pub profile_id: Pubkey,        // 32, wallet that created the profile; seeds its records
+    pub milestone_awards: u32,     // 4, milestone achievements ever awarded (`milestone_award_bit`)
+}
+
+impl UserProfile {
+    /// Record a quiz submission at `now`. Unless `cooldown_exempt`, it is
+    /// rejected while the config's cooldown since the previous submission
+    /// is still running.
+    pub fn mark_quiz_submitted(
+        &mut self,
+        config: &ProtocolConfig,
+        cooldown_exempt: bool,
+        now: i64,
+    ) -> Result<()> {
+        if !cooldown_exempt {
+            let remaining = config.quiz_cooldown_remaining(self.last_quiz_at, now);
+            if remaining > 0 {
+                msg!("Quiz cooldown active: {}s remaining", remaining);
+                return err!(ErrorCode::CooldownActive);
+            }
+        }
+        self.last_quiz_at = now;
+        Ok(())
+    }
+
+    /// Use up `nonce`, which must be the profile's next submission nonce
+    pub fn use_submission_nonce(&mut self, nonce: u64) -> Result<()> {
+        require!(nonce == self.submission_nonce, ErrorCode::InvalidNonce);
+        self.submission_nonce = self
+            .submission_nonce
+            .checked_add(1)
+            .ok_or(ErrorCode::NumericOverflow)?;
+        Ok(())
+    }
+
+    /// Pay the referral bonus once, after the first completed quiz: `bonus_xp`
+    /// to this profile and to its `referrer`, who also counts the referral
+    pub fn claim_referral_reward(
+        &mut self,
+        referrer: &mut UserProfile,
+        bonus_xp: u64,
+        curve: &LevelCurve,
+        now: i64,
+    ) -> Result<()> {
+        require!(!self.referral_claimed, ErrorCode::ReferralAlreadyClaimed);
+        require!(self.quizzes_completed >= 1, ErrorCode::ReferralNotEligible);
+        self.referral_claimed = true;
+        
+        self.earn_xp(bonus_xp, now)?;
+        self.level = level_for_xp(self.xp, curve);
+        referrer.earn_xp(bonus_xp, now)?;
+        referrer.level = level_for_xp(referrer.xp, curve);
+        referrer.referrals_count = referrer
+            .referrals_count
+            .checked_add(1)
+            .ok_or(ErrorCode::NumericOverflow)?;
+        Ok(())
+    }
+
+    /// Fill in the fields a profile from an older layout lacks and mark it
+    /// current. Upgrading a current profile changes nothing.
+    pub fn upgrade(&mut self, bump: u8) {
+        // Profiles that predate calendar-day streaks count from their last activity
+        if self.last_streak_day == 0 && self.streak > 0 {
+            self.last_streak_day = self.last_active / SECONDS_PER_DAY;
+        }
+        // Profiles that predate stored bumps can't pass `bump = user_profile.bump`
+        self.bump = bump;
+        // Profiles that predate profile ids keep the records seeded by their wallet
+        if self.profile_id == Pubkey::default() {
+            self.profile_id = self.authority;
+        }
+        self.schema_version = PROFILE_SCHEMA_VERSION;
+    }
+
+    /// Replace the bio, avatar URI and social link, each within its maximum length
+    pub fn set_metadata(
+        &mut self,
+        bio: String,
+        avatar_uri: String,
+        social_link: String,
+    ) -> Result<()> {
+        require!(bio.len() <= MAX_BIO_LEN, ErrorCode::TextTooLong);
+        require!(avatar_uri.len() <= MAX_AVATAR_URI_LEN, ErrorCode::TextTooLong);
+        require!(social_link.len() <= MAX_SOCIAL_LINK_LEN, ErrorCode::TextTooLong);
+        self.bio = bio;
+        self.avatar_uri = avatar_uri;
+        self.social_link = social_link;
+        Ok(())
+    }
+
+    /// Apply a signed XP correction, stopping at 0 XP, and recompute the level
+    pub fn adjust_xp(&mut self, delta: i64, curve: &LevelCurve) -> Result<()> {
+        self.xp = if delta >= 0 {
+            self.xp.checked_add(delta.unsigned_abs()).ok_or(ErrorCode::NumericOverflow)?
+        } else {
+            self.xp.saturating_sub(delta.unsigned_abs())
+        };
+        self.level = level_for_xp(self.xp, curve);
+        Ok(())
+    }
+
+    /// Take back up to `bonus_xp` and one achievement for a revoked award,
+    /// and recompute the level. Returns the XP actually removed.
+    pub fn revoke_achievement(&mut self, bonus_xp: u64, curve: &LevelCurve) -> u64 {
+        let removed = bonus_xp.min(self.xp);
+        self.xp -= removed;
+        self.achievements_earned = self.achievements_earned.saturating_sub(1);
+        self.level = level_for_xp(self.xp, curve);
+        removed
+    }
+
+    /// Check that nothing still refers to the profile at `now`, so it can be
+    /// closed: no guild membership, no unresolved challenge and no tournament
+    /// still running
+    pub fn ensure_closable(&self, now: i64) -> Result<()> {
+        require!(
+            self.guild.is_none()
+                && self.open_challenges == 0
+                && now > self.tournament_locked_until,
+            ErrorCode::ProfileInUse
+        );
+        Ok(())
+    }
+
+    /// Whether `signer` may act for this profile in `submit_quiz` and
+    /// `update_streak`: the authority itself or its current delegate
+    pub fn is_authorized_signer(&self, signer: &Pubkey) -> bool {
+        *signer == self.authority || Some(*signer) == self.delegate
+    }
+
+    /// Add the reward tokens for `xp` earned to the claimable balance, within
+    /// today's allowance. Nothing accrues while no reward mint is configured.
+    ///
+    /// Returns the number of tokens accrued.
+    pub fn accrue_token_rewards(
+        &mut self,
+        xp: u64,
+        config: &ProtocolConfig,
+        now: i64,
+    ) -> Result<u64> {
+        if config.reward_mint == Pubkey::default() {
+            return Ok(0);
+        }
+        let earned = xp.checked_mul(config.tokens_per_xp).ok_or(ErrorCode::NumericOverflow)?;
+        let accrued = self.claim_token_allowance(earned, config.daily_token_cap, now)?;
+        self.pending_rewards = self
+            .pending_rewards
+            .checked_add(accrued)
+            .ok_or(ErrorCode::NumericOverflow)?;
+        Ok(accrued)
+    }
+
+    /// Empty the claimable balance and return it. A non-zero balance below
+    /// `min_claim` stays put.
+    pub fn take_pending_rewards(&mut self, min_claim: u64) -> Result<u64> {
+        let amount = self.pending_rewards;
+        require!(amount == 0 || amount >= min_claim, ErrorCode::ClaimBelowMinimum);
+        self.pending_rewards = 0;
+        Ok(amount)
+    }
+
+    /// Take up to `amount` reward tokens from today's allowance of
+    /// `daily_cap` (0 = uncapped), returning how many may be accrued
+    fn claim_token_allowance(&mut self, amount: u64, daily_cap: u64, now: i64) -> Result<u64> {
+        let today = now / SECONDS_PER_DAY;
+        if today != self.token_mint_day {
+            self.token_mint_day = today;
+            self.tokens_minted_today = 0;
+        }
+        
+        let allowed = if daily_cap == 0 {
+            amount
+        } else {
+            amount.min(daily_cap.saturating_sub(self.tokens_minted_today))
+        };
+        self.tokens_minted_today = self
+            .tokens_minted_today
+            .checked_add(allowed)
+            .ok_or(ErrorCode::NumericOverflow)?;
+        Ok(allowed)
+    }
+
+    /// Credit earned XP, also counting it towards the current week's and
+    /// month's totals. Each total starts over when `now` falls in a later
+    /// period.
+    pub fn earn_xp(&mut self, amount: u64, now: i64) -> Result<()> {
+        self.xp = self.xp.checked_add(amount).ok_or(ErrorCode::NumericOverflow)?;
+        
+        let week_index = week_index(now);
+        if week_index != self.week_index {
+            self.week_index = week_index;
+            self.weekly_xp = 0;
+        }
+        self.weekly_xp = self.weekly_xp.checked_add(amount).ok_or(ErrorCode::NumericOverflow)?;
+        
+        let month_index = month_index(now);
+        if month_index != self.month_index {
+            self.month_index = month_index;
+            self.monthly_xp = 0;
+        }
+        self.monthly_xp = self.monthly_xp.checked_add(amount).ok_or(ErrorCode::NumericOverflow)?;
+        Ok(())
+    }
+
+    /// XP earned in the week containing `now`
+    pub fn weekly_xp_at(&self, now: i64) -> u64 {
+        if week_index(now) == self.week_index { self.weekly_xp } else { 0 }
+    }
+
+    /// XP earned in the calendar month containing `now`
+    pub fn monthly_xp_at(&self, now: i64) -> u64 {
+        if month_index(now) == self.month_index { self.monthly_xp } else { 0 }
+    }
+
+    /// XP earned in the period of `leaderboard_type` containing `now`
+    pub fn period_xp_at(&self, leaderboard_type: LeaderboardType, now: i64) -> u64 {
+        match leaderboard_type {
+            LeaderboardType::Weekly => self.weekly_xp_at(now),
+            LeaderboardType::Monthly => self.monthly_xp_at(now),
+            LeaderboardType::AllTime => self.xp,
+        }
+    }
+
+    /// Record activity at `now` and advance the streak.
+    ///
+    /// Streaks count windows of `window_secs` (one UTC day by default):
+    /// activity in the same window as the last counted one leaves the streak
+    /// as is, activity in the following window extends it, and skipping a
+    /// window resets it to 1 unless a streak freeze is armed. Longer gaps
+    /// always reset it.
+    pub fn record_activity(&mut self, now: i64, window_secs: i64, grace_secs: i64) -> Result<()> {
+        let next_day = self
+            .last_streak_day
+            .checked_add(1)
+            .ok_or(ErrorCode::NumericOverflow)?;
+        let today = self.streak_day(now, window_secs, grace_secs);
+        
+        if self.streak == 0 {
+            // First activity on a fresh profile
+            self.streak = 1;
+        } else if today == self.last_streak_day {
+            // Same day, streak already counted
+        } else if today == next_day {
+            self.streak = self.streak.checked_add(1).ok_or(ErrorCode::NumericOverflow)?;
+        } else if self.streak_frozen && today == next_day.saturating_add(1) {
+            // A freeze covers the missed day, so the streak carries on
+            self.streak = self.streak.checked_add(1).ok_or(ErrorCode::NumericOverflow)?;
+            self.streak_frozen = false;
+        } else {
+            self.streak = 1;
+        }
+        
+        self.last_streak_day = today;
+        self.last_active = now;
+        Ok(())
+    }
+
+    /// Streak window that activity at `now` counts towards. Activity within
+    /// `grace_secs` after the due window ends still counts for it.
+    pub fn streak_day(&self, now: i64, window_secs: i64, grace_secs: i64) -> i64 {
+        let today = now / window_secs;
+        let late_day = now.saturating_sub(grace_secs) / window_secs;
+        if late_day < today && late_day == self.last_streak_day.saturating_add(1) {
+            late_day
+        } else {
+            today
+        }
+    }
+
+    /// Arm a banked freeze if activity at `now` follows exactly one missed
+    /// window, the only gap a freeze covers. Returns whether one was used.
+    pub fn use_banked_freeze(
+        &mut self,
+        streak_freeze: &mut StreakFreeze,
+        now: i64,
+        window_secs: i64,
+        grace_secs: i64,
+    ) -> bool {
+        let missed_one = self.streak > 0
+            && self.streak_day(now, window_secs, grace_secs)
+                == self.last_streak_day.saturating_add(2);
+        if !missed_one || self.streak_frozen || streak_freeze.count == 0 {
+            return false;
+        }
+        streak_freeze.count -= 1;
+        self.streak_frozen = true;
+        true
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct UsernameRegistry {
+    pub profile: Pubkey,           // 32
+    pub owner: Pubkey,             // 32
+    pub registered_at: i64,        // 8
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct QuizResult {
+    pub user: Pubkey,              // 32, owner's profile id
+    #[max_len(64)]
+    pub quiz_id: String,           // 4 + 64
+    pub attempt: u16,              // 2
+    pub score: u8,                 // 1
+    pub total_questions: u8,       // 1
+    pub xp_earned: u64,            // 8
+    pub difficulty: Difficulty,    // 1
+    pub difficulty_multiplier_used: u16, // 2
+    pub completed_at: i64,         // 8
+    pub bump: u8,                  // 1
+    pub time_taken_seconds: u32,   // 4
+    pub hints_used: u8,            // 1
+    pub duration_secs: i64,        // 8, measured from start_quiz; 0 if not started
+    pub points_earned: u32,        // 4
+    pub points_possible: u32,      // 4
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct QuizProgress {
+    pub user: Pubkey,              // 32, owner's profile id
+    pub attempts: u16,             // 2
+    pub best_score: u8,            // 1
+    pub best_xp: u64,              // 8
+    pub last_attempt_at: i64,      // 8
+    pub total_xp_from_quiz: u64,   // 8
+}
+
+impl QuizProgress {
+    /// Count attempt number `attempt`, which must be the next one, and keep
+    /// the best score and XP.
+    ///
+    /// Returns how far `attempt_xp` improves on the previous best, which is
+    /// all a retake can earn.
+    pub fn record_attempt(
+        &mut self,
+        attempt: u16,
+        score: u8,
+        attempt_xp: u64,
+        now: i64,
+    ) -> Result<u64> {
+        require!(attempt == self.attempts, ErrorCode::InvalidAttempt);
+        let improvement = attempt_xp.saturating_sub(self.best_xp);
+        self.attempts = self.attempts.checked_add(1).ok_or(ErrorCode::NumericOverflow)?;
+        self.best_xp = self.best_xp.max(attempt_xp);
+        self.best_score = self.best_score.max(score);
+        self.last_attempt_at = now;
+        Ok(improvement)
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Achievement {
+    pub user: Pubkey,              // 32, owner's profile id
+    #[max_len(64)]
+    pub achievement_id: String,    // 4 + 64
+    #[max_len(128)]
+    pub achievement_name: String,  // 4 + 128
+    pub tier: AchievementTier,     // 1
+    pub awarded_at: i64,           // 8
+    pub nft_mint: Option<Pubkey>,  // 1 + 32
+    pub bump: u8,                  // 1
+    pub bonus_xp: u64,             // 8
+}
+
+impl Achievement {
+    /// Name of the achievement's NFT certificate: the achievement name and
+    /// tier, cut to the 32 bytes Metaplex allows
+    pub fn certificate_name(&self) -> String {
+        let mut name = format!("{} ({})", self.achievement_name, self.tier);
+        while name.len() > 32 {
+            name.pop();
+        }
+        name
+    }
+
+    /// Fill in a freshly created achievement for `profile`, counting it and
+    /// crediting `bonus_xp`. Fails if the account was already awarded, so
+    /// nothing is counted twice.
+    pub fn award(
+        &mut self,
+        profile: &mut UserProfile,
+        achievement_id: String,
+        achievement_name: String,
+        tier: AchievementTier,
+        bonus_xp: u64,
+        now: i64,
+        bump: u8,
+    ) -> Result<()> {
+        require!(self.awarded_at == 0, ErrorCode::AchievementAlreadyAwarded);
+        self.user = profile.profile_id;
+        self.achievement_id = achievement_id;
+        self.achievement_name = achievement_name;
+        self.tier = tier;
+        self.awarded_at = now;
+        self.bump = bump;
+        self.bonus_xp = bonus_xp;
+        
+        profile.achievements_earned = profile
+            .achievements_earned
+            .checked_add(1)
+            .ok_or(ErrorCode::NumericOverflow)?;
+        profile.earn_xp(bonus_xp, now)
+    }
+
+    /// Whether the owner closed the record, leaving only its tombstone
+    pub fn is_closed(&self) -> bool {
+        self.awarded_at != 0 && self.achievement_name.is_empty()
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct StreakFreeze {
+    pub user: Pubkey,              // 32, owner's profile id
+    pub count: u8,                 // 1
+    pub last_milestone: u64,       // 8
+}
+
+impl StreakFreeze {
+    /// Add a freeze to the bank of the profile `owner`, up to `MAX_STREAK_FREEZES`
+    pub fn bank(&mut self, owner: Pubkey) -> Result<()> {
+        require!(self.count < MAX_STREAK_FREEZES, ErrorCode::TooManyStreakFreezes);
+        self.user = owner;
+        self.count += 1;
+        Ok(())
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct AchievementProgress {
+    pub user: Pubkey,              // 32, owner's profile id
+    #[max_len(64)]
+    pub achievement_id: String,    // 4 + 64
+    pub current: u64,              // 8
+    pub target: u64,               // 8
+    pub completed: bool,           // 1
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct AchievementDefinition {
+    #[max_len(64)]
+    pub achievement_id: String,    // 4 + 64
+    #[max_len(64)]
+    pub prerequisite_achievement_id: Option<String>, // 1 + 4 + 64
+    pub created_at: i64,           // 8
+    #[max_len(200)]
+    pub metadata_uri: String,      // 4 + 200, NFT metadata JSON (IPFS)
+    #[max_len(128)]
+    pub name: String,              // 4 + 128
+    pub tier: AchievementTier,     // 1
+    pub bonus_xp: Option<u64>,     // 1 + 8, overrides the configured tier bonus
+    pub max_supply: u32,           // 4, 0 = unlimited
+    pub remaining_supply: u32,     // 4
+    pub is_active: bool,           // 1
+}
+
+impl AchievementDefinition {
+    /// Bonus XP for an award: the definition's own bonus, or else the
+    /// configured bonus for its tier
+    pub fn award_bonus(&self, config: &ProtocolConfig) -> u64 {
+        self.bonus_xp.unwrap_or_else(|| config.tier_bonus(&self.tier))
+    }
+
+    /// Use up one award of a limited supply. Inactive definitions can't be
+    /// awarded at all.
+    pub fn take_supply(&mut self) -> Result<()> {
+        require!(self.is_active, ErrorCode::AchievementInactive);
+        if self.max_supply > 0 {
+            require!(self.remaining_supply > 0, ErrorCode::AchievementSupplyExhausted);
+            self.remaining_supply -= 1;
+        }
+        Ok(())
+    }
+
+    /// Return the award of a revoked achievement to a limited supply
+    pub fn restore_supply(&mut self) {
+        if self.max_supply > 0 {
+            self.remaining_supply = self.remaining_supply.saturating_add(1).min(self.max_supply);
+        }
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct QuizMetadata {
+    pub creator: Pubkey,           // 32
+    #[max_len(64)]
+    pub quiz_id: String,           // 4 + 64
+    #[max_len(128)]
+    pub title: String,             // 4 + 128
+    #[max_len(256)]
+    pub description: String,       // 4 + 256
+    pub category: QuizCategory,    // 1
+    pub difficulty: Difficulty,    // 1
+    pub created_at: i64,           // 8
+    pub is_active: bool,           // 1
+    pub is_event: bool,            // 1
+    pub total_questions: u8,       // 1
+    #[max_len(50)]
+    pub answer_hashes: Vec<[u8; 32]>, // 4 + 32 * 50
+    pub answers_merkle_root: [u8; 32], // 32
+    pub time_limit_secs: i64,      // 8, 0 for untimed
+    #[max_len(50)]
+    pub question_points: Vec<u16>, // 4 + 2 * 50
+}
+
+impl QuizMetadata {
+    /// Whether scores for this quiz are computed on-chain rather than
+    /// reported by the client
+    pub fn is_graded_on_chain(&self) -> bool {
+        !self.answer_hashes.is_empty() || self.answers_merkle_root != [0u8; 32]
+    }
+
+    /// Seconds from `started_at` to `now`, or 0 for an attempt that wasn't
+    /// started. Timed quizzes must have been started and finished in time.
+    pub fn measure_duration(&self, started_at: Option<i64>, now: i64) -> Result<i64> {
+        if self.time_limit_secs > 0 {
+            let started_at = started_at.ok_or(ErrorCode::QuizNotStarted)?;
+            require!(
+                now.saturating_sub(started_at) <= self.time_limit_secs,
+                ErrorCode::TimeLimitExceeded
+            );
+        }
+        Ok(started_at.map_or(0, |started_at| now.saturating_sub(started_at)))
+    }
+
+    /// Require the quiz to be active and to have `total_questions` questions
+    pub fn ensure_accepts(&self, total_questions: u8) -> Result<()> {
+        require!(self.is_active, ErrorCode::QuizInactive);
+        require!(total_questions == self.total_questions, ErrorCode::QuizMismatch);
+        Ok(())
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Course {
+    pub creator: Pubkey,           // 32
+    #[max_len(64)]
+    pub course_id: String,         // 4 + 64
+    #[max_len(128)]
+    pub title: String,             // 4 + 128
+    #[max_len(20, 64)]
+    pub lesson_quiz_ids: Vec<String>, // 4 + 20 * (4 + 64)
+    pub required_pass_score: u8,   // 1
+    pub completion_reward_xp: u64, // 8
+    pub created_at: i64,           // 8
+    pub approved: bool,            // 1, false while the reward awaits approval
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct CourseEnrollment {
+    pub user: Pubkey,              // 32, owner's profile id
+    pub course: Pubkey,            // 32
+    pub lessons_completed: u64,    // 8
+    pub enrolled_at: i64,          // 8
+    pub completed_at: i64,         // 8
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct LearningPath {
+    pub creator: Pubkey,           // 32
+    #[max_len(64)]
+    pub path_id: String,           // 4 + 64
+    #[max_len(128)]
+    pub title: String,             // 4 + 128
+    #[max_len(10, 64)]
+    pub courses: Vec<String>,      // 4 + 10 * (4 + 64)
+    #[max_len(64)]
+    pub completion_badge_achievement_id: String, // 4 + 64
+    pub created_at: i64,           // 8
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct LearningPathEnrollment {
+    pub user: Pubkey,              // 32, owner's profile id
+    pub learning_path: Pubkey,     // 32
+    pub courses_completed: u8,     // 1
+    pub enrolled_at: i64,          // 8
+    pub completed_at: i64,         // 8
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct QuizStats {
+    pub quiz: Pubkey,              // 32
+    pub attempts: u64,             // 8
+    pub total_score: u64,          // 8
+    pub total_questions_answered: u64, // 8
+    pub perfect_scores: u64,       // 8
+    pub best_points: u32,          // 4
+    pub best_score_holder: Pubkey, // 32
+}
+
+impl QuizStats {
+    /// Add a graded attempt by `user` to the aggregates. On a tie for the
+    /// best score the earlier holder keeps it.
+    pub fn record(&mut self, graded: &GradedScore, user: Pubkey) -> Result<()> {
+        self.attempts = self.attempts.checked_add(1).ok_or(ErrorCode::NumericOverflow)?;
+        self.total_score = self
+            .total_score
+            .checked_add(graded.score as u64)
+            .ok_or(ErrorCode::NumericOverflow)?;
+        self.total_questions_answered = self
+            .total_questions_answered
+            .checked_add(graded.total_questions as u64)
+            .ok_or(ErrorCode::NumericOverflow)?;
+        if graded.points_earned == graded.points_possible {
+            self.perfect_scores = self
+                .perfect_scores
+                .checked_add(1)
+                .ok_or(ErrorCode::NumericOverflow)?;
+        }
+        if graded.points_earned > self.best_points {
+            self.best_points = graded.points_earned;
+            self.best_score_holder = user;
+        }
+        Ok(())
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct MentorProfile {
+    pub authority: Pubkey,         // 32
+    #[max_len(64)]
+    pub display_name: String,      // 4 + 64
+    #[max_len(256)]
+    pub bio: String,               // 4 + 256
+    pub expertise_tags: [u8; 16],  // 16, one bit per skill category
+    pub hourly_rate_lamports: u64, // 8
+    pub total_sessions: u64,       // 8
+    pub average_rating_basis_points: u16, // 2, 0-10000
+    pub is_available: bool,        // 1
+    pub registered_at: i64,        // 8
+    pub bump: u8,                  // 1
+    pub total_reviews: u64,        // 8
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct GlobalStats {
+    pub total_users: u64,          // 8
+    pub total_quizzes_completed: u64, // 8
+    pub total_xp_minted: u64,      // 8
+    pub total_achievements_awarded: u64, // 8
+    pub created_at: i64,           // 8
+}
+
+impl GlobalStats {
+    /// Count a newly created profile
+    pub fn record_user(&mut self) -> Result<()> {
+        self.total_users = self.total_users.checked_add(1).ok_or(ErrorCode::NumericOverflow)?;
+        Ok(())
+    }
+
+    /// Count `achievements` new achievements and `xp` newly minted XP
+    pub fn record_awards(&mut self, achievements: u64, xp: u64) -> Result<()> {
+        self.total_achievements_awarded = self
+            .total_achievements_awarded
+            .checked_add(achievements)
+            .ok_or(ErrorCode::NumericOverflow)?;
+        self.total_xp_minted = self
+            .total_xp_minted
+            .checked_add(xp)
+            .ok_or(ErrorCode::NumericOverflow)?;
+        Ok(())
+    }
+
+    /// Count a completed quiz, with the achievements and XP it earned
+    pub fn record_quiz(&mut self, achievements: u64, xp: u64) -> Result<()> {
+        self.total_quizzes_completed = self
+            .total_quizzes_completed
+            .checked_add(1)
+            .ok_or(ErrorCode::NumericOverflow)?;
+        self.record_awards(achievements, xp)
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Session {
+    pub student: Pubkey,           // 32
+    pub mentor: Pubkey,            // 32, the mentor's authority
+    pub session_nonce: u64,        // 8
+    pub scheduled_at: i64,         // 8
+    pub duration_minutes: u16,     // 2
+    pub escrow_lamports: u64,      // 8
+    pub status: SessionStatus,     // 1
+    #[max_len(64)]
+    pub session_notes_cid: String, // 4 + 64, IPFS CID
+    pub booked_at: i64,            // 8
+    pub bump: u8,                  // 1
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct SessionReview {
+    pub session: Pubkey,           // 32
+    pub reviewer: Pubkey,          // 32
+    pub mentor: Pubkey,            // 32
+    pub rating: u8,                // 1, 1-5
+    #[max_len(256)]
+    pub comment: String,           // 4 + 256
+    pub reviewed_at: i64,          // 8
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct StakePosition {
+    pub user: Pubkey,              // 32
+    pub staked_amount: u64,        // 8
+    pub boost_bps: u16,            // 2, extra XP while the boost lasts
+    pub staked_at: i64,            // 8
+    pub boost_expires_at: i64,     // 8
+    pub bump: u8,                  // 1
+}
+
+impl StakePosition {
+    /// Add `amount` base units of a mint with `decimals` to the stake at
+    /// `now`. The boost is recomputed per 1,000 whole tokens, capped, and it
+    /// and the lock restart.
+    pub fn add_stake(
+        &mut self,
+        amount: u64,
+        decimals: u8,
+        config: &ProtocolConfig,
+        now: i64,
+    ) -> Result<()> {
+        self.staked_amount = self
+            .staked_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::NumericOverflow)?;
+        
+        let thousand_tokens = 1_000u128 * 10u128.pow(decimals as u32);
+        let boost_bps = self.staked_amount as u128 * config.stake_boost_bps_per_thousand as u128
+            / thousand_tokens;
+        self.boost_bps = boost_bps.min(config.max_stake_boost_bps as u128) as u16;
+        self.staked_at = now;
+        self.boost_expires_at = now
+            .checked_add(config.stake_boost_secs)
+            .ok_or(ErrorCode::NumericOverflow)?;
+        Ok(())
+    }
+
+    /// Fail with `StakeLocked` until `min_lock_secs` after the last stake
+    pub fn ensure_unlocked(&self, min_lock_secs: i64, now: i64) -> Result<()> {
+        let unlocks_at = self
+            .staked_at
+            .checked_add(min_lock_secs)
+            .ok_or(ErrorCode::NumericOverflow)?;
+        require!(now >= unlocks_at, ErrorCode::StakeLocked);
+        Ok(())
+    }
+
+    /// Extra XP in basis points at `now`; nothing once the boost expired
+    pub fn boost_bps_at(&self, now: i64) -> u16 {
+        if now < self.boost_expires_at { self.boost_bps } else { 0 }
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Guild {
+    #[max_len(64)]
+    pub guild_id: String,          // 4 + 64
+    #[max_len(64)]
+    pub name: String,              // 4 + 64
+    pub founder: Pubkey,           // 32
+    pub member_count: u16,         // 2
+    pub total_guild_xp: u64,       // 8
+    pub created_at: i64,           // 8
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct GuildMembership {
+    pub user: Pubkey,              // 32
+    pub guild: Pubkey,             // 32
+    pub joined_at: i64,            // 8
+    pub synced_xp: u64,            // 8, profile XP at the last sync
+    pub contributed_xp: u64,       // 8
+}
+
+/// Head-to-head quiz challenge; both sides stake the same XP wager
+#[account]
+#[derive(InitSpace)]
+pub struct Challenge {
+    pub challenger: Pubkey,        // 32
+    pub challenged: Pubkey,        // 32
+    #[max_len(64)]
+    pub quiz_id: String,           // 4 + 64
+    pub wager_xp: u64,             // 8
+    pub challenger_score: Option<u8>, // 1 + 1
+    pub challenged_score: Option<u8>, // 1 + 1
+    pub challenger_attempt: u16,   // 2, the attempt that counts for the challenger
+    pub challenged_attempt: u16,   // 2, the attempt that counts for the challenged
+    pub status: ChallengeStatus,   // 1
+    pub created_at: i64,           // 8
+    pub accepted_at: i64,          // 8
+    pub expires_at: i64,           // 8
+    pub winner: Option<Pubkey>,    // 1 + 32
+    pub bump: u8,                  // 1
+}
+
+impl Challenge {
+    /// Record `participant`'s score from their result for attempt number
+    /// `attempt`, which must be the first attempt they completed after
+    /// entering the challenge
+    pub fn record_score(&mut self, participant: Pubkey, attempt: u16, score: u8) -> Result<()> {
+        let (slot, expected) = if participant == self.challenger {
+            (&mut self.challenger_score, self.challenger_attempt)
+        } else if participant == self.challenged {
+            (&mut self.challenged_score, self.challenged_attempt)
+        } else {
+            return err!(ErrorCode::Unauthorized);
+        };
+        require!(slot.is_none(), ErrorCode::ChallengeScoreSubmitted);
+        require!(attempt == expected, ErrorCode::InvalidAttempt);
+        *slot = Some(score);
+        Ok(())
+    }
+}
+
+/// Quiz tournament; entry fees are held in the account as the prize pool
+#[account]
+#[derive(InitSpace)]
+pub struct Tournament {
+    #[max_len(64)]
+    pub tournament_id: String,     // 4 + 64
+    pub organizer: Pubkey,         // 32
+    #[max_len(64)]
+    pub quiz_id: String,           // 4 + 64
+    pub max_participants: u8,      // 1, a power of two
+    pub entry_fee_lamports: u64,   // 8
+    pub prize_pool_lamports: u64,  // 8
+    pub starts_at: i64,            // 8
+    pub ends_at: i64,              // 8
+    pub status: TournamentStatus,  // 1
+    #[max_len(64)]
+    pub participants: Vec<Pubkey>, // 4 + 32 * 64
+    #[max_len(64)]
+    pub scores: Vec<Option<u8>>,   // 4 + 2 * 64, parallel to `participants`
+    #[max_len(64)]
+    pub attempts: Vec<u16>,        // 4 + 2 * 64, the attempt that counts for each participant
+    pub bump: u8,                  // 1
+}
+
+impl Tournament {
+    /// Record `participant`'s score from their result for attempt number
+    /// `attempt`, which must be the first attempt they completed after
+    /// registering
+    pub fn record_score(&mut self, participant: Pubkey, attempt: u16, score: u8) -> Result<()> {
+        let index = self
+            .participants
+            .iter()
+            .position(|player| *player == participant)
+            .ok_or(ErrorCode::NotRegistered)?;
+        require!(self.scores[index].is_none(), ErrorCode::TournamentScoreSubmitted);
+        require!(attempt == self.attempts[index], ErrorCode::InvalidAttempt);
+        self.scores[index] = Some(score);
+        Ok(())
+    }
+}
+
+/// Maps a referral code to the id of the profile it belongs to
+#[account]
+#[derive(InitSpace)]
+pub struct ReferralRegistry {
+    pub referrer: Pubkey,          // 32
+    pub code: [u8; 8],             // 8
+}
+
+impl ReferralRegistry {
+    /// Referrer of a new profile `profile_id` that gave `code`, which must be
+    /// this registry's code and not the profile's own
+    pub fn referrer_of(&self, code: [u8; 8], profile_id: &Pubkey) -> Result<Pubkey> {
+        require!(self.code == code, ErrorCode::InvalidReferrer);
+        require_keys_neq!(self.referrer, *profile_id, ErrorCode::SelfReferral);
+        Ok(self.referrer)
+    }
+}
+
+/// Answers committed in `start_quiz`, awaiting `reveal_quiz`
+#[account]
+#[derive(InitSpace)]
+pub struct QuizAttempt {
+    pub user: Pubkey,              // 32, owner's profile id
+    #[max_len(64)]
+    pub quiz_id: String,           // 4 + 64
+    pub attempt: u16,              // 2
+    pub commitment: [u8; 32],      // 32
+    pub started_at: i64,           // 8
+    pub revealed: bool,            // 1
+    pub answered: [u8; 32],        // 32, one bit per question
+    pub answered_count: u8,        // 1
+    pub correct_count: u8,         // 1
+}
+
+impl QuizAttempt {
+    /// Open the commitment with `answers` and `salt`, at most
+    /// `QUIZ_REVEAL_WINDOW` seconds after the attempt started, and mark the
+    /// attempt revealed so it can't be opened again
+    pub fn reveal(&mut self, answers: &[u8], salt: &[u8; 32], now: i64) -> Result<()> {
+        require!(!self.revealed, ErrorCode::AnswersAlreadyRevealed);
+        require!(
+            now.saturating_sub(self.started_at) <= QUIZ_REVEAL_WINDOW,
+            ErrorCode::RevealExpired
+        );
+        require!(
+            answer_commitment(answers, salt) == self.commitment,
+            ErrorCode::CommitmentMismatch
+        );
+        self.revealed = true;
+        Ok(())
+    }
+
+    /// Grade a chunk of answers against the Merkle `root` of a quiz with
+    /// `total_questions` questions. Every proof must hold and no question may
+    /// be answered twice across chunks.
+    pub fn grade_proven(
+        &mut self,
+        quiz_id: &str,
+        root: &[u8; 32],
+        total_questions: u8,
+        answers: &[ProvenAnswer],
+    ) -> Result<()> {
+        for answer in answers {
+            require!(
+                answer.question_index < total_questions,
+                ErrorCode::QuestionOutOfRange
+            );
+            let leaf = answer_leaf(answer.question_index, &answer.correct_answer_hash);
+            require!(
+                verify_merkle_proof(&answer.proof, root, leaf),
+                ErrorCode::InvalidMerkleProof
+            );
+            require!(
+                self.mark_answered(answer.question_index),
+                ErrorCode::QuestionAlreadyAnswered
+            );
+            let hash = answer_hash(quiz_id, answer.question_index, answer.answer);
+            if hash == answer.correct_answer_hash {
+                self.correct_count = self
+                    .correct_count
+                    .checked_add(1)
+                    .ok_or(ErrorCode::NumericOverflow)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Mark `question` as answered; returns false if it already was
+    pub fn mark_answered(&mut self, question: u8) -> bool {
+        let byte = &mut self.answered[(question / 8) as usize];
+        let bit = 1u8 << (question % 8);
+        if *byte & bit != 0 {
+            return false;
+        }
+        *byte |= bit;
+        self.answered_count += 1;
+        true
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct QuizCreator {
+    pub creator: Pubkey,           // 32
+    pub granted_at: i64,           // 8
+}
+
+/// Top learners by XP earned during one ranking period (all time, a week or
+/// a calendar month), kept sorted in descending order. Weekly and monthly
+/// boards are finalized once their period is over so rewards can be paid
+/// against them.
+///
+/// Insertion is a naive O(n) shift over a fixed array, which is fine at
+/// moderate usage; an off-chain index over profiles can replace it if needed.
+#[account(zero_copy)]
+pub struct Leaderboard {
+    pub count: u64,                                         // 8
+    pub reset_at: i64,                                      // 8
+    pub leaderboard_type: u8,                               // 1 (LeaderboardType)
+    pub finalized: u8,                                      // 1 (bool)
+    pub _padding: [u8; 2],                                  // 2
+    pub period_index: u32,                                  // 4
+    pub entries: [LeaderboardEntry; LEADERBOARD_CAPACITY],  // 80 * 100
+}
+
+#[zero_copy]
+#[derive(Default)]
+pub struct LeaderboardEntry {
+    pub user: Pubkey,              // 32
+    pub username: [u8; 32],        // 32
+    pub xp: u64,                   // 8
+    pub level: u64,                // 8
+}
+
+impl LeaderboardEntry {
+    pub fn from_profile(profile: &UserProfile) -> Self {
+        let mut username = [0u8; 32];
+        let name = profile.username.as_bytes();
+        let len = name.len().min(username.len());
+        username[..len].copy_from_slice(&name[..len]);
+        
+        Self {
+            user: profile.authority,
+            username,
+            xp: profile.xp,
+            level: profile.level,
+        }
+    }
+
+    /// Entry ranked by the XP the profile earned in the period of
+    /// `leaderboard_type` containing `now`
+    pub fn for_period(profile: &UserProfile, leaderboard_type: LeaderboardType, now: i64) -> Self {
+        Self {
+            xp: profile.period_xp_at(leaderboard_type, now),
+            ..Self::from_profile(profile)
+        }
+    }
+}
+
+impl Leaderboard {
+    /// Remove every entry and start a new ranking period. A finalized board
+    /// is kept as it stands, since rewards are paid against it.
+    pub fn reset(&mut self, now: i64) -> Result<()> {
+        require!(self.finalized == 0, ErrorCode::LeaderboardFinalized);
+        self.entries = [LeaderboardEntry::default(); LEADERBOARD_CAPACITY];
+        self.count = 0;
+        self.reset_at = now;
+        Ok(())
+    }
+
+    /// Insert `entry`, replacing any existing entry for the same user.
+    ///
+    /// Returns whether the user is on the leaderboard afterwards.
+    pub fn upsert(&mut self, entry: LeaderboardEntry) -> bool {
+        upsert_by_xp(&mut self.entries, &mut self.count, entry)
+    }
+
+    /// Rank `profile` by its XP for this board's period, which must be the
+    /// one containing `now` and not yet finalized.
+    ///
+    /// Returns whether the user is on the leaderboard afterwards.
+    pub fn record(&mut self, profile: &UserProfile, now: i64) -> Result<bool> {
+        let leaderboard_type =
+            LeaderboardType::from_u8(self.leaderboard_type).ok_or(ErrorCode::InvalidPeriod)?;
+        require!(
+            self.finalized == 0 && self.period_index == leaderboard_type.period_at(now),
+            ErrorCode::InvalidPeriod
+        );
+        Ok(self.upsert(LeaderboardEntry::for_period(profile, leaderboard_type, now)))
+    }
+}
+
+/// Top learners by XP earned during one week, kept sorted in descending
+/// order. Finalized once the week is over so rewards can be paid against it.
+#[account(zero_copy)]
+pub struct WeeklyLeaderboard {
+    pub count: u64,                                         // 8
+    pub week_index: u32,                                    // 4
+    pub finalized: u8,                                      // 1 (bool)
+    pub _padding: [u8; 3],                                  // 3
+    pub entries: [LeaderboardEntry; LEADERBOARD_CAPACITY],  // 80 * 100
+}
+
+impl WeeklyLeaderboard {
+    /// Rank `profile` by its weekly XP, which this board only accepts during
+    /// its own week.
+    ///
+    /// Returns whether the user is on the leaderboard afterwards.
+    pub fn record(&mut self, profile: &UserProfile, now: i64) -> Result<bool> {
+        require!(self.week_index == week_index(now), ErrorCode::InvalidWeek);
+        let entry = LeaderboardEntry::for_period(profile, LeaderboardType::Weekly, now);
+        Ok(upsert_by_xp(&mut self.entries, &mut self.count, entry))
+    }
+
+    /// Freeze the board once its week is over
+    pub fn finalize(&mut self, now: i64) -> Result<()> {
+        require!(self.week_index < week_index(now), ErrorCode::WeekNotOver);
+        require!(self.finalized == 0, ErrorCode::WeekFinalized);
+        self.finalized = 1;
+        Ok(())
+    }
+}
+
+/// Insert `entry` into the first `count` of `entries`, sorted by XP in
+/// descending order, replacing any existing entry for the same user.
+///
+/// Returns whether the user is ranked afterwards.
+fn upsert_by_xp(
+    entries: &mut [LeaderboardEntry],
+    count: &mut u64,
+    entry: LeaderboardEntry,
+) -> bool {
+    let capacity = entries.len();
+    let mut len = *count as usize;
+    
+    // Drop the user's previous entry so they never hold two slots
+    if let Some(existing) = entries[..len].iter().position(|e| e.user == entry.user) {
+        entries.copy_within(existing + 1..len, existing);
+        len -= 1;
+    }
+    
+    // Ties keep the earlier entry ahead
+    let position = entries[..len]
+        .iter()
+        .position(|e| e.xp < entry.xp)
+        .unwrap_or(len);
+    if position >= capacity {
+        *count = len as u64;
+        return false;
+    }
+    
+    let new_len = (len + 1).min(capacity);
+    entries.copy_within(position..new_len - 1, position + 1);
+    entries[position] = entry;
+    *count = new_len as u64;
+    true
+}
+
+/// Top scores on a single quiz, kept sorted best first.
+///
+/// Higher points rank first, then the faster attempt, then the earlier one.
+#[account(zero_copy)]
+pub struct QuizLeaderboard {
+    pub quiz: Pubkey,                                            // 32
+    pub count: u64,                                              // 8
+    pub entries: [QuizLeaderboardEntry; QUIZ_LEADERBOARD_CAPACITY], // 56 * 50
+}
+
+#[zero_copy]
+#[derive(Default)]
+pub struct QuizLeaderboardEntry {
+    pub user: Pubkey,              // 32, owner's profile id
+    pub completed_at: i64,         // 8
+    pub xp_earned: u64,            // 8
+    pub duration_secs: u32,        // 4
+    pub score: u16,                // 2, points earned
+    pub _padding: [u8; 2],         // 2
+}
+
+impl QuizLeaderboardEntry {
+    /// Entry for a quiz result, ranked on points and the measured time
+    /// taken; the self-reported `time_taken_seconds` is ignored
+    pub fn from_result(quiz_result: &QuizResult) -> Self {
+        Self {
+            user: quiz_result.user,
+            completed_at: quiz_result.completed_at,
+            xp_earned: quiz_result.xp_earned,
+            duration_secs: u32::try_from(quiz_result.duration_secs.max(0)).unwrap_or(u32::MAX),
+            score: u16::try_from(quiz_result.points_earned).unwrap_or(u16::MAX),
+            _padding: [0; 2],
+        }
+    }
+
+    /// Whether this entry ranks strictly ahead of `other`
+    pub fn outranks(&self, other: &Self) -> bool {
+        use std::cmp::Reverse;
+        (self.score, Reverse(self.duration_secs), Reverse(self.completed_at))
+            > (other.score, Reverse(other.duration_secs), Reverse(other.completed_at))
+    }
+}
+
+impl QuizLeaderboard {
+    /// Insert `entry` if it places, keeping only the user's best entry.
+    ///
+    /// Returns whether the user is on the leaderboard afterwards.
+    pub fn upsert(&mut self, entry: QuizLeaderboardEntry) -> bool {
+        let mut len = self.count as usize;
+        
+        // A user holds a single slot with their best result
+        if let Some(existing) = self.entries[..len].iter().position(|e| e.user == entry.user) {
+            if !entry.outranks(&self.entries[existing]) {
+                return true;
+            }
+            self.entries.copy_within(existing + 1..len, existing);
+            len -= 1;
+        }
+        
+        let position = self.entries[..len]
+            .iter()
+            .position(|e| entry.outranks(e))
+            .unwrap_or(len);
+        if position >= QUIZ_LEADERBOARD_CAPACITY {
+            self.count = len as u64;
+            return false;
+        }
+        
+        // A full board evicts its last entry
+        let new_len = (len + 1).min(QUIZ_LEADERBOARD_CAPACITY);
+        self.entries.copy_within(position..new_len - 1, position + 1);
+        self.entries[position] = entry;
+        self.count = new_len as u64;
+        true
+    }
+}
+
+/// An answer to one question with a proof of the correct answer
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ProvenAnswer {
+    pub question_index: u8,
+    pub answer: u8,
+    pub correct_answer_hash: [u8; 32],
+    pub proof: Vec<[u8; 32]>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum QuizCategory {
+    Blockchain,
+    DeFi,
+    Rust,
+    General,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+    Expert,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+pub enum AchievementTier {
+    Bronze,
+    Silver,
+    Gold,
+    Platinum,
+    // Appended so stored Bronze to Platinum tiers keep their encoding
+    Diamond,
+}
+
+impl AchievementTier {
+    /// Whether achievements of this tier can be minted as NFT certificates
+    pub fn has_certificate(&self) -> bool {
+        matches!(
+            self,
+            AchievementTier::Gold | AchievementTier::Platinum | AchievementTier::Diamond
+        )
+    }
+}
+
+impl std::fmt::Display for AchievementTier {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            AchievementTier::Bronze => write!(f, "Bronze"),
+            AchievementTier::Silver => write!(f, "Silver"),
+            AchievementTier::Gold => write!(f, "Gold"),
+            AchievementTier::Platinum => write!(f, "Platinum"),
+            AchievementTier::Diamond => write!(f, "Diamond"),
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LeaderboardType {
+    Weekly,
+    Monthly,
+    AllTime,
+}
+
+impl LeaderboardType {
+    /// Type stored in a `Leaderboard`'s `leaderboard_type`
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(LeaderboardType::Weekly),
+            1 => Some(LeaderboardType::Monthly),
+            2 => Some(LeaderboardType::AllTime),
+            _ => None,
+        }
+    }
+
+    /// Ranking period containing `timestamp`: the week or calendar month
+    /// index, or 0 for the all-time board
+    pub fn period_at(&self, timestamp: i64) -> u32 {
+        match self {
+            LeaderboardType::Weekly => week_index(timestamp),
+            LeaderboardType::Monthly => month_index(timestamp),
+            LeaderboardType::AllTime => 0,
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum SessionStatus {
+    Booked,
+    Completed,
+    Disputed,
+    Refunded,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum TournamentStatus {
+    Registration,
+    Completed,
+    Cancelled,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum ChallengeStatus {
+    Pending,
+    Active,
+    Completed,
+    Expired,
+}
+
+#[event]
+pub struct ProfileInitialized {
+    pub user: Pubkey,
+    pub username: String,
+    pub created_at: i64,
+}
+
+#[event]
+pub struct ProfileClosed {
+    pub user: Pubkey,
+    pub username: String,
+    pub xp: u64,
+    pub closed_at: i64,
+}
+
+#[event]
+pub struct TournamentFinalized {
+    pub tournament_id: String,
+    pub quiz_id: String,
+    pub winners: Vec<Pubkey>,
+    pub prize_pool_lamports: u64,
+    pub finalized_at: i64,
+}
+
+#[event]
+pub struct ChallengeResolved {
+    pub challenger: Pubkey,
+    pub challenged: Pubkey,
+    pub quiz_id: String,
+    pub wager_xp: u64,
+    pub status: ChallengeStatus,
+    pub winner: Option<Pubkey>,
+    pub resolved_at: i64,
+}
+
+#[event]
+pub struct QuizSubmitted {
+    pub user: Pubkey,
+    pub username: String,
+    pub quiz_id: String,
+    pub attempt: u16,
+    pub score: u8,
+    pub total_questions: u8,
+    pub difficulty: Difficulty,
+    pub xp_earned: u64,
+    pub total_xp: u64,
+    pub new_level: u64,
+    pub streak: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AchievementAwarded {
+    pub user: Pubkey,
+    pub username: String,
+    pub achievement_id: String,
+    pub achievement_name: String,
+    pub tier: AchievementTier,
+    pub bonus_xp: u64,
+    pub total_xp: u64,
+    pub new_level: u64,
+    pub awarded_at: i64,
+}
+
+#[event]
+pub struct StreakUpdated {
+    pub user: Pubkey,
+    pub username: String,
+    pub streak: u64,
+    pub last_active: i64,
+}
+
+#[event]
+pub struct LevelUp {
+    pub user: Pubkey,
+    pub old_level: u64,
+    pub new_level: u64,
+}
+
+/// A user reached a quiz count milestone that earns an achievement.
+///
+/// Submissions that passed the milestone's definition and achievement PDA
+/// in `remaining_accounts` have already awarded it. Otherwise an indexer or
+/// the achievement authority picks this up and calls `award_achievement`
+/// with `achievement_id`, which needs the catalog definition for that id.
+#[event]
+pub struct AchievementEligible {
+    pub user: Pubkey,
+    pub achievement_id: String,
+}
+
+#[event]
+pub struct StreakMilestoneReached {
+    pub user: Pubkey,
+    pub streak: u64,
+    pub bonus_xp: u64,
+}
+
+#[event]
+pub struct DailyBonusClaimed {
+    pub user: Pubkey,
+    pub bonus_xp: u64,
+    pub streak: u64,
+    pub claimed_at: i64,
+}
+
+#[event]
+pub struct AchievementRevoked {
+    pub user: Pubkey,
+    pub achievement_id: String,
+    pub xp_removed: u64,
+    pub old_level: u64,
+    pub new_level: u64,
+}
+
+/// An admin corrected a user's XP
+#[event]
+pub struct XpAdjusted {
+    pub user: Pubkey,
+    pub delta: i64,
+    pub reason_code: u16,
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardsClaimed {
+    pub user: Pubkey,
+    pub amount: u64,
+    pub claimed_at: i64,
+}
+
+#[error_code]
+pub enum ErrorCode {
+    #[msg("Invalid score: score cannot exceed total questions")]
+    InvalidScore,
+    #[msg("Streak milestone not reached: keep your streak going to earn a freeze")]
+    StreakMilestoneNotReached,
+    #[msg("Too many streak freezes banked")]
+    TooManyStreakFreezes,
+    #[msg("No streak freezes available")]
+    NoStreakFreezes,
+    #[msg("Streak is already frozen")]
+    StreakAlreadyFrozen,
+    #[msg("Invalid config: a protocol parameter is out of range")]
+    InvalidConfig,
+    #[msg("Numeric overflow")]
+    NumericOverflow,
+    #[msg("Unauthorized: signer may not act on this account")]
+    Unauthorized,
+    #[msg("Unauthorized awarder: signer may not award achievements")]
+    UnauthorizedAwarder,
+    #[msg("Achievement has already been awarded to this user")]
+    AchievementAlreadyAwarded,
+    #[msg("Id too long: quiz and achievement ids are limited to 64 bytes")]
+    IdTooLong,
+    #[msg("Quiz has already been completed")]
+    QuizAlreadyCompleted,
+    #[msg("Invalid quiz metadata: quiz ids must be non-empty letters, digits, '_' or '-'")]
+    InvalidQuizMetadata,
+    #[msg("Empty quiz: a quiz must have at least one question")]
+    EmptyQuiz,
+    #[msg("Text too long")]
+    TextTooLong,
+    #[msg("Quiz is no longer active")]
+    QuizInactive,
+    #[msg("Invalid attempt: attempt must be the next attempt number for this quiz")]
+    InvalidAttempt,
+    #[msg("Invalid username: usernames must be 3-32 letters, digits, '_' or '-'")]
+    InvalidUsername,
+    #[msg("Username is already taken")]
+    UsernameTaken,
+    #[msg("Invalid reward mint")]
+    InvalidRewardMint,
+    #[msg("Certificates are only minted for Gold, Platinum and Diamond achievements")]
+    CertificateTierTooLow,
+    #[msg("The profile's username registry entry must be supplied")]
+    UsernameRegistryRequired,
+    #[msg("Username was changed too recently")]
+    UsernameChangeTooSoon,
+    #[msg("Profile still has funds staked")]
+    ProfileHasActiveStake,
+    #[msg("Record is too recent to close")]
+    RecordTooRecent,
+    #[msg("Prerequisite achievement has not been earned")]
+    PrerequisiteNotMet,
+    #[msg("Achievement progress is not complete")]
+    AchievementProgressIncomplete,
+    #[msg("Program is paused")]
+    ProgramPaused,
+    #[msg("Quiz cooldown is still active")]
+    CooldownActive,
+    #[msg("Streak freezes are not for sale")]
+    StreakFreezeUnavailable,
+    #[msg("Not enough XP")]
+    InsufficientXp,
+    #[msg("Daily bonus was already claimed in the last 24 hours")]
+    DailyBonusAlreadyClaimed,
+    #[msg("Submission does not match the quiz definition")]
+    QuizMismatch,
+    #[msg("This quiz is graded on-chain; use start_quiz and reveal_quiz")]
+    QuizGradedOnChain,
+    #[msg("No answer commitment found for this attempt")]
+    QuizNotStarted,
+    #[msg("Answers for this attempt were already revealed")]
+    AnswersAlreadyRevealed,
+    #[msg("Revealed answers do not match the commitment")]
+    CommitmentMismatch,
+    #[msg("Reveal window has expired")]
+    RevealExpired,
+    #[msg("Time taken must be between 1 second and the maximum")]
+    InvalidTimeTaken,
+    #[msg("Too many answers in one call")]
+    TooManyAnswers,
+    #[msg("Question index is out of range")]
+    QuestionOutOfRange,
+    #[msg("Invalid Merkle proof")]
+    InvalidMerkleProof,
+    #[msg("Question was already answered")]
+    QuestionAlreadyAnswered,
+    #[msg("Hints used cannot exceed the score")]
+    InvalidHintCount,
+    #[msg("Quiz time limit exceeded")]
+    TimeLimitExceeded,
+    #[msg("Invalid course definition")]
+    InvalidCourse,
+    #[msg("Not every lesson quiz in the course has been passed")]
+    CourseIncomplete,
+    #[msg("Course was already completed")]
+    CourseAlreadyCompleted,
+    #[msg("Invalid learning path definition")]
+    InvalidLearningPath,
+    #[msg("Not every course in the learning path has been completed")]
+    LearningPathIncomplete,
+    #[msg("Learning path was already completed")]
+    LearningPathAlreadyCompleted,
+    #[msg("Quiz stats account does not belong to this quiz")]
+    QuizStatsMismatch,
+    #[msg("Profile level is too low to register as a mentor")]
+    MentorLevelTooLow,
+    #[msg("Invalid mentor profile")]
+    InvalidMentorProfile,
+    #[msg("Mentor is not accepting sessions")]
+    MentorUnavailable,
+    #[msg("Invalid session booking")]
+    InvalidSession,
+    #[msg("Session is not in the required status")]
+    InvalidSessionStatus,
+    #[msg("Session has not started yet")]
+    SessionNotStarted,
+    #[msg("Session has already started")]
+    SessionAlreadyStarted,
+    #[msg("Rating must be between 1 and 5")]
+    InvalidRating,
+    #[msg("Leaderboard is not for the current period")]
+    InvalidPeriod,
+    #[msg("Leaderboard period has not ended yet")]
+    PeriodNotOver,
+    #[msg("Leaderboard was already finalized")]
+    LeaderboardFinalized,
+    #[msg("A profile cannot refer itself")]
+    SelfReferral,
+    #[msg("Referrer profile is missing or does not match the referrer")]
+    InvalidReferrer,
+    #[msg("Pending rewards are below the minimum claim amount")]
+    ClaimBelowMinimum,
+    #[msg("Referral reward was already claimed")]
+    ReferralAlreadyClaimed,
+    #[msg("Referral reward requires a referrer and a completed quiz")]
+    ReferralNotEligible,
+    #[msg("Stake amount must be positive")]
+    InvalidStakeAmount,
+    #[msg("Stake is still locked")]
+    StakeLocked,
+    #[msg("Invalid guild")]
+    InvalidGuild,
+    #[msg("Profile already belongs to a guild")]
+    AlreadyInGuild,
+    #[msg("Achievement is not active")]
+    AchievementInactive,
+    #[msg("Achievement supply is exhausted")]
+    AchievementSupplyExhausted,
+    #[msg("Invalid challenge")]
+    InvalidChallenge,
+    #[msg("Challenge is not in the required status")]
+    InvalidChallengeStatus,
+    #[msg("Challenge has expired")]
+    ChallengeExpired,
+    #[msg("Score already submitted for this challenge")]
+    ChallengeScoreSubmitted,
+    #[msg("Challenge cannot be resolved yet")]
+    ChallengeNotResolvable,
+    #[msg("Invalid tournament")]
+    InvalidTournament,
+    #[msg("Tournament is not in the required status")]
+    InvalidTournamentStatus,
+    #[msg("Tournament is full")]
+    TournamentFull,
+    #[msg("Already registered for this tournament")]
+    AlreadyRegistered,
+    #[msg("Not registered for this tournament")]
+    NotRegistered,
+    #[msg("Score already submitted for this tournament")]
+    TournamentScoreSubmitted,
+    #[msg("Tournament has not ended yet")]
+    TournamentNotEnded,
+    #[msg("Prize recipients do not match the tournament ranking")]
+    InvalidPrizeRecipient,
+    #[msg("Submission nonce does not match the profile's next nonce")]
+    InvalidNonce,
+    #[msg("Quiz score attestation is missing or invalid")]
+    InvalidAttestation,
+    #[msg("Invalid achievement definition: it needs a name and cannot be its own prerequisite")]
+    InvalidAchievementDefinition,
+    #[msg("Achievement progress target must be positive")]
+    InvalidProgressTarget,
+    #[msg("Invalid delegate: the profile owner cannot be its own delegate")]
+    InvalidDelegate,
+    #[msg("Too many questions: a quiz has at most 50 questions")]
+    TooManyQuestions,
+    #[msg("Profile is still in a guild, an unresolved challenge or a running tournament")]
+    ProfileInUse,
+    #[msg("Course completion reward is above the configured cap")]
+    CourseRewardTooHigh,
+    #[msg("Course reward is awaiting approval by the config authority")]
+    CourseNotApproved,
+    #[msg("The profile's referral registry entry must be closed with it")]
+    ReferralRegistryRequired,
+    #[msg("Leaderboard is not for the requested week")]
+    InvalidWeek,
+    #[msg("Week has not ended yet")]
+    WeekNotOver,
+    #[msg("Week was already finalized")]
+    WeekFinalized,
+    #[msg("Achievement record was closed by its owner")]
+    AchievementClosed,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::entrypoint::{deserialize, MAX_PERMITTED_DATA_INCREASE};
+    use anchor_lang::solana_program::instruction::{BorrowedInstruction, Instruction};
+    use anchor_lang::solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+    use anchor_lang::solana_program::sysvar::{
+        self,
+        instructions::{construct_instructions_data, store_current_index},
+    };
+    use anchor_lang::{AccountsClose, AccountsExit};
+    use std::collections::BTreeSet;
+
+    fn profile() -> UserProfile {
+        UserProfile {
+            authority: Pubkey::new_unique(),
+            username: "learner".to_string(),
+            xp: 0,
+            level: 1,
+            streak: 0,
+            quizzes_completed: 0,
+            achievements_earned: 0,
+            created_at: 0,
+            last_active: 0,
+            streak_frozen: false,
+            last_streak_day: 0,
+            bio: String::new(),
+            last_username_change: 0,
+            bump: 255,
+            last_quiz_at: 0,
+            last_daily_claim: 0,
+            weekly_xp: 0,
+            week_index: 0,
+            tokens_minted_today: 0,
+            token_mint_day: 0,
+            referrals_count: 0,
+            pending_rewards: 0,
+            avatar_uri: String::new(),
+            social_link: String::new(),
+            referral_code: [0; 8],
+            referred_by: None,
+            referral_claimed: false,
+            schema_version: PROFILE_SCHEMA_VERSION,
+            guild: None,
+            delegate: None,
+            submission_nonce: 0,
+            open_challenges: 0,
+            tournament_locked_until: 0,
+            monthly_xp: 0,
+            month_index: 0,
+            profile_id: Pubkey::new_unique(),
+            milestone_awards: 0,
+        }
+    }
+
+    fn config() -> ProtocolConfig {
+        let authority = Pubkey::new_unique();
+        ProtocolConfig {
+            authority,
+            achievement_authority: authority,
+            xp_per_correct: 10,
+            perfect_score_bonus: 50,
+            level_curve: LevelCurve { base_xp: 100, growth_factor_bps: BASE_MULTIPLIER_BPS },
+            streak_window_secs: SECONDS_PER_DAY,
+            streak_grace_secs: 0,
+            tier_bonuses: [50, 100, 250, 500, 1_000],
+            reward_mint: Pubkey::default(),
+            tokens_per_xp: 0,
+            mint_authority_bump: 0,
+            paused: false,
+            quiz_cooldown_secs: 0,
+            streak_milestones: DEFAULT_STREAK_MILESTONES,
+            streak_bonus_xp: DEFAULT_STREAK_BONUS_XP,
+            streak_freeze_cost_xp: 0,
+            daily_bonus_xp: 0,
+            speed_threshold_seconds: 0,
+            speed_bonus_xp: 0,
+            hint_penalty_xp: DEFAULT_HINT_PENALTY_XP,
+            retake_decay_bps: DEFAULT_RETAKE_DECAY_BPS,
+            min_retake_multiplier_bps: DEFAULT_MIN_RETAKE_MULTIPLIER_BPS,
+            difficulty_multipliers_bps: DEFAULT_DIFFICULTY_MULTIPLIERS_BPS,
+            min_time_taken_seconds: 0,
+            min_mentor_level: DEFAULT_MIN_MENTOR_LEVEL,
+            session_fee_bps: DEFAULT_SESSION_FEE_BPS,
+            xp_event_multiplier: BASE_MULTIPLIER_BPS,
+            xp_event_start: 0,
+            xp_event_end: 0,
+            min_reward_score_bps: 0,
+            daily_token_cap: 0,
+            referral_bonus_xp: DEFAULT_REFERRAL_BONUS_XP,
+            min_reward_claim: 0,
+            tier_royalty_bps: [0; 5],
+            stake_boost_bps_per_thousand: 0,
+            max_stake_boost_bps: 0,
+            stake_boost_secs: 0,
+            stake_min_lock_secs: 0,
+            attestor: Pubkey::default(),
+            version: CONFIG_VERSION,
+            max_course_reward_xp: DEFAULT_MAX_COURSE_REWARD_XP,
+            course_approval_xp: DEFAULT_COURSE_APPROVAL_XP,
+        }
+    }
+
+    const DAY: i64 = SECONDS_PER_DAY;
+
+    fn assert_error<T: std::fmt::Debug>(result: Result<T>, code: ErrorCode) {
+        match result {
+            Err(anchor_lang::error::Error::AnchorError(error)) => {
+                assert_eq!(error.error_code_number, u32::from(code))
+            }
+            other => panic!("expected {:?}, got {:?}", code, other),
+        }
+    }
+
+    #[test]
+    fn first_activity_starts_a_streak() {
+        let mut profile = profile();
+        profile.record_activity(10 * DAY + 5, DAY, 0).unwrap();
+        assert_eq!(profile.streak, 1);
+        assert_eq!(profile.last_streak_day, 10);
+        assert_eq!(profile.last_active, 10 * DAY + 5);
+    }
+
+    #[test]
+    fn activity_later_the_same_day_keeps_the_streak() {
+        let mut profile = profile();
+        profile.record_activity(10 * DAY, DAY, 0).unwrap();
+        profile.record_activity(11 * DAY - 1, DAY, 0).unwrap();
+        assert_eq!(profile.streak, 1);
+        assert_eq!(profile.last_streak_day, 10);
+    }
+
+    #[test]
+    fn activity_at_the_day_boundary_extends_the_streak() {
+        let mut profile = profile();
+        profile.record_activity(11 * DAY - 1, DAY, 0).unwrap();
+        // One second later is a new UTC day, even though far less than 24h passed
+        profile.record_activity(11 * DAY, DAY, 0).unwrap();
+        assert_eq!(profile.streak, 2);
+        assert_eq!(profile.last_streak_day, 11);
+    }
+
+    #[test]
+    fn missing_a_day_resets_the_streak() {
+        let mut profile = profile();
+        profile.record_activity(10 * DAY, DAY, 0).unwrap();
+        profile.record_activity(11 * DAY, DAY, 0).unwrap();
+        // Just under 48h after the last activity, but a whole day was skipped
+        profile.record_activity(13 * DAY, DAY, 0).unwrap();
+        assert_eq!(profile.streak, 1);
+        assert_eq!(profile.last_streak_day, 13);
+    }
+
+    #[test]
+    fn exactly_one_window_later_extends_the_streak() {
+        let mut profile = profile();
+        profile.record_activity(10 * DAY + 3_600, DAY, 0).unwrap();
+        profile.record_activity(10 * DAY + 3_600 + 86_400, DAY, 0).unwrap();
+        assert_eq!(profile.streak, 2);
+    }
+
+    #[test]
+    fn a_month_away_resets_the_streak() {
+        let mut profile = profile();
+        for day in 10..15 {
+            profile.record_activity(day * DAY, DAY, 0).unwrap();
+        }
+        assert_eq!(profile.streak, 5);
+        profile.record_activity(45 * DAY, DAY, 0).unwrap();
+        assert_eq!(profile.streak, 1);
+    }
+
+    #[test]
+    fn earning_xp_past_u64_max_is_an_error() {
+        let mut profile = profile();
+        profile.xp = u64::MAX - 10;
+        profile.earn_xp(10, 0).unwrap();
+        assert_eq!(profile.xp, u64::MAX);
+        assert_error(profile.earn_xp(1, 0), ErrorCode::NumericOverflow);
+    }
+
+    #[test]
+    fn weekly_xp_overflow_is_an_error() {
+        let mut profile = profile();
+        profile.weekly_xp = u64::MAX;
+        assert_error(profile.earn_xp(1, 0), ErrorCode::NumericOverflow);
+    }
+
+    #[test]
+    fn streak_overflow_is_an_error() {
+        let mut profile = profile();
+        profile.streak = u64::MAX;
+        profile.last_streak_day = 10;
+        assert_error(profile.record_activity(11 * DAY, DAY, 0), ErrorCode::NumericOverflow);
+    }
+
+    #[test]
+    fn weighted_grading_counts_full_credit_questions() {
+        let graded = GradedScore::weighted(&[3, 1, 2], &[3, 2, 2], 3).unwrap();
+        assert_eq!(graded.score, 2);
+        assert_eq!(graded.points_earned, 6);
+        assert_eq!(graded.points_possible, 7);
+        assert_error(GradedScore::weighted(&[3], &[2], 1), ErrorCode::InvalidScore);
+    }
+
+    #[test]
+    fn weighted_grading_score_overflow_is_an_error() {
+        let points = vec![1u16; 256];
+        assert_error(GradedScore::weighted(&points, &points, 255), ErrorCode::NumericOverflow);
+    }
+
+    #[test]
+    fn level_curve_flat_gives_one_level_per_base_xp() {
+        let curve = LevelCurve { base_xp: 100, growth_factor_bps: BASE_MULTIPLIER_BPS };
+        assert_eq!(level_for_xp(0, &curve), 1);
+        assert_eq!(level_for_xp(99, &curve), 1);
+        assert_eq!(level_for_xp(100, &curve), 2);
+        assert_eq!(level_for_xp(1_050, &curve), 11);
+    }
+
+    #[test]
+    fn level_curve_growth_makes_levels_costlier() {
+        // 100 XP to level 2, 150 more to level 3, 225 more to level 4
+        let curve = LevelCurve { base_xp: 100, growth_factor_bps: 15_000 };
+        assert_eq!(level_for_xp(99, &curve), 1);
+        assert_eq!(level_for_xp(100, &curve), 2);
+        assert_eq!(level_for_xp(249, &curve), 2);
+        assert_eq!(level_for_xp(250, &curve), 3);
+        assert_eq!(level_for_xp(475, &curve), 4);
+    }
+
+    #[test]
+    fn level_curve_is_capped_and_never_panics() {
+        let steep = LevelCurve { base_xp: 1, growth_factor_bps: 10_001 };
+        assert_eq!(level_for_xp(u64::MAX, &steep), MAX_LEVEL);
+        let flat = LevelCurve { base_xp: 1, growth_factor_bps: BASE_MULTIPLIER_BPS };
+        assert_eq!(level_for_xp(u64::MAX, &flat), u64::MAX);
+    }
+
+    #[test]
+    fn level_curve_validation() {
+        assert!(LevelCurve { base_xp: 100, growth_factor_bps: BASE_MULTIPLIER_BPS }.is_valid());
+        assert!(!LevelCurve { base_xp: 0, growth_factor_bps: BASE_MULTIPLIER_BPS }.is_valid());
+        assert!(!LevelCurve { base_xp: 100, growth_factor_bps: 9_999 }.is_valid());
+    }
+
+    #[test]
+    fn only_the_admin_may_award_achievements() {
+        let config = config();
+        let user = profile();
+        assert!(config.is_awarder(&config.authority));
+        assert!(!config.is_awarder(&user.authority));
+    }
+
+    #[test]
+    fn a_designated_achievement_authority_may_award_but_users_may_not() {
+        let mut config = config();
+        let user = profile();
+        let achievement_authority = Pubkey::new_unique();
+        config.achievement_authority = achievement_authority;
+        assert!(config.is_awarder(&achievement_authority));
+        assert!(config.is_awarder(&config.authority));
+        // Self-awards: the profile owner signing as awarder is rejected
+        assert!(!config.is_awarder(&user.authority));
+    }
+
+    fn blank_achievement() -> Achievement {
+        Achievement {
+            user: Pubkey::default(),
+            achievement_id: String::new(),
+            achievement_name: String::new(),
+            tier: AchievementTier::Bronze,
+            awarded_at: 0,
+            nft_mint: None,
+            bump: 0,
+            bonus_xp: 0,
+        }
+    }
+
+    fn award_gold(
+        achievement: &mut Achievement,
+        profile: &mut UserProfile,
+        now: i64,
+    ) -> Result<()> {
+        achievement.award(
+            profile,
+            "quiz_master".to_string(),
+            "Quiz master".to_string(),
+            AchievementTier::Gold,
+            250,
+            now,
+            254,
+        )
+    }
+
+    #[test]
+    fn an_achievement_cannot_be_awarded_twice() {
+        let mut profile = profile();
+        let mut achievement = blank_achievement();
+        award_gold(&mut achievement, &mut profile, 1_000).unwrap();
+        assert_eq!(achievement.user, profile.profile_id);
+        assert_eq!(achievement.awarded_at, 1_000);
+        assert_eq!(profile.achievements_earned, 1);
+        assert_eq!(profile.xp, 250);
+        
+        assert_error(
+            award_gold(&mut achievement, &mut profile, 2_000),
+            ErrorCode::AchievementAlreadyAwarded,
+        );
+        assert_eq!(profile.achievements_earned, 1);
+        assert_eq!(profile.xp, 250);
+        assert_eq!(achievement.awarded_at, 1_000);
+    }
+
+    #[test]
+    fn long_ids_derive_stable_pdas() {
+        let user = Pubkey::new_unique();
+        for len in [33, MAX_ID_LEN] {
+            let quiz_id = "q".repeat(len);
+            let seed = id_seed(&quiz_id);
+            let seeds: &[&[u8]] = &[b"quiz_result", user.as_ref(), &seed, &0u16.to_le_bytes()];
+            let (first, _) = Pubkey::find_program_address(seeds, &crate::ID);
+            let (second, _) = Pubkey::find_program_address(seeds, &crate::ID);
+            assert_eq!(first, second);
+        }
+        assert_ne!(id_seed(&"q".repeat(33)), id_seed(&"q".repeat(MAX_ID_LEN)));
+    }
+
+    #[test]
+    fn quiz_results_close_only_after_the_minimum_age() {
+        let completed_at = 100 * DAY;
+        assert!(!is_record_closable(completed_at, completed_at));
+        assert!(!is_record_closable(completed_at, completed_at + MIN_RECORD_AGE - 1));
+        assert!(is_record_closable(completed_at, completed_at + MIN_RECORD_AGE));
+    }
+
+    #[test]
+    fn profiles_in_use_cannot_be_closed() {
+        let mut profile = profile();
+        profile.ensure_closable(100).unwrap();
+        
+        profile.guild = Some(Pubkey::new_unique());
+        assert_error(profile.ensure_closable(100), ErrorCode::ProfileInUse);
+        profile.guild = None;
+        
+        profile.open_challenges = 1;
+        assert_error(profile.ensure_closable(100), ErrorCode::ProfileInUse);
+        profile.open_challenges = 0;
+        
+        profile.tournament_locked_until = 200;
+        assert_error(profile.ensure_closable(200), ErrorCode::ProfileInUse);
+        profile.ensure_closable(201).unwrap();
+    }
+
+    #[test]
+    fn quiz_ids_are_restricted_to_a_safe_alphabet() {
+        assert!(is_valid_id("solana-basics_101"));
+        assert!(is_valid_id(&"a".repeat(MAX_ID_LEN)));
+        assert!(!is_valid_id(""));
+        assert!(!is_valid_id("   "));
+        assert!(!is_valid_id("intro quiz"));
+        assert!(!is_valid_id("quiz/1"));
+        assert!(!is_valid_id("quiz\u{0}"));
+        assert!(!is_valid_id("quiz\u{e9}"));
+    }
+
+    #[test]
+    fn quiz_submitted_events_decode_from_their_log_data() {
+        let user = Pubkey::new_unique();
+        let event = QuizSubmitted {
+            user,
+            username: "learner".to_string(),
+            quiz_id: "rust-101".to_string(),
+            attempt: 2,
+            score: 9,
+            total_questions: 10,
+            difficulty: Difficulty::Hard,
+            xp_earned: 180,
+            total_xp: 1_180,
+            new_level: 12,
+            streak: 4,
+            timestamp: 1_700_000_000,
+        };
+        let data = anchor_lang::Event::data(&event);
+        assert_eq!(data[..8], <QuizSubmitted as anchor_lang::Discriminator>::DISCRIMINATOR);
+        
+        let decoded = QuizSubmitted::try_from_slice(&data[8..]).unwrap();
+        assert_eq!(decoded.user, user);
+        assert_eq!(decoded.quiz_id, "rust-101");
+        assert_eq!(decoded.attempt, 2);
+        assert_eq!(decoded.score, 9);
+        assert_eq!(decoded.total_questions, 10);
+        assert_eq!(decoded.xp_earned, 180);
+        assert_eq!(decoded.new_level, 12);
+        assert_eq!(decoded.timestamp, 1_700_000_000);
+    }
+
+    #[test]
+    fn difficulty_scales_attempt_xp() {
+        let config = config();
+        // 10 correct at 10 XP plus the 50 XP perfect bonus
+        let graded = GradedScore::flat(10, 10);
+        let expected = [
+            (Difficulty::Easy, 150),
+            (Difficulty::Medium, 225),
+            (Difficulty::Hard, 300),
+            (Difficulty::Expert, 450),
+        ];
+        for (difficulty, xp) in expected {
+            let multiplier_bps = config.difficulty_multiplier_bps(&difficulty);
+            assert_eq!(config.attempt_xp(&graded, multiplier_bps, 0, 60, 0).unwrap(), xp);
+        }
+    }
+
+    #[test]
+    fn difficulty_multipliers_come_from_the_config() {
+        let mut config = config();
+        config.difficulty_multipliers_bps = [10_000, 12_000, 14_000, 16_000];
+        assert_eq!(config.difficulty_multiplier_bps(&Difficulty::Hard), 14_000);
+        let graded = GradedScore::flat(5, 10);
+        assert_eq!(config.attempt_xp(&graded, 14_000, 0, 60, 0).unwrap(), 70);
+    }
+
+    #[test]
+    fn many_quizzes_in_one_day_count_once() {
+        let mut profile = profile();
+        for minute in 0..10 {
+            profile.record_activity(20 * DAY + minute * 60, DAY, 0).unwrap();
+        }
+        assert_eq!(profile.streak, 1);
+        profile.record_activity(21 * DAY + 30, DAY, 0).unwrap();
+        assert_eq!(profile.streak, 2);
+    }
+
+    #[test]
+    fn late_activity_within_the_grace_period_counts_for_the_due_day() {
+        let grace = 2 * 60 * 60;
+        let mut profile = profile();
+        profile.record_activity(20 * DAY, DAY, grace).unwrap();
+        // Day 21 ended an hour ago, which is still within its grace period
+        profile.record_activity(22 * DAY + 3_600, DAY, grace).unwrap();
+        assert_eq!(profile.streak, 2);
+        assert_eq!(profile.last_streak_day, 21);
+    }
+
+    #[test]
+    fn config_changes_apply_to_later_awards() {
+        let mut config = config();
+        let graded = GradedScore::flat(8, 10);
+        assert_eq!(config.attempt_xp(&graded, BASE_MULTIPLIER_BPS, 0, 60, 0).unwrap(), 80);
+        
+        config.xp_per_correct = 25;
+        assert_eq!(config.attempt_xp(&graded, BASE_MULTIPLIER_BPS, 0, 60, 0).unwrap(), 200);
+        
+        config.perfect_score_bonus = 100;
+        let perfect = GradedScore::flat(10, 10);
+        assert_eq!(config.attempt_xp(&perfect, BASE_MULTIPLIER_BPS, 0, 60, 0).unwrap(), 350);
+        
+        config.tier_bonuses = [1, 2, 3, 4, 5];
+        assert_eq!(config.tier_bonus(&AchievementTier::Platinum), 4);
+    }
+
+    #[test]
+    fn level_thresholds_are_inclusive_on_a_doubling_curve() {
+        // Levels cost 100, 200, 400, ... XP, so levels start at 100, 300 and 700 XP
+        let curve = LevelCurve { base_xp: 100, growth_factor_bps: 20_000 };
+        for (threshold, level) in [(100, 2), (300, 3), (700, 4), (1_500, 5)] {
+            assert_eq!(level_for_xp(threshold - 1, &curve), level - 1);
+            assert_eq!(level_for_xp(threshold, &curve), level);
+        }
+    }
+
+    #[test]
+    fn token_rewards_accrue_in_proportion_to_xp() {
+        let mut config = config();
+        let mut profile = profile();
+        assert_eq!(profile.accrue_token_rewards(100, &config, 0).unwrap(), 0);
+        
+        config.reward_mint = Pubkey::new_unique();
+        config.tokens_per_xp = 1_000;
+        assert_eq!(profile.accrue_token_rewards(150, &config, 0).unwrap(), 150_000);
+        assert_eq!(profile.pending_rewards, 150_000);
+    }
+
+    #[test]
+    fn token_rewards_respect_the_daily_cap() {
+        let mut config = config();
+        config.reward_mint = Pubkey::new_unique();
+        config.tokens_per_xp = 10;
+        config.daily_token_cap = 1_500;
+        let mut profile = profile();
+        assert_eq!(profile.accrue_token_rewards(100, &config, DAY).unwrap(), 1_000);
+        assert_eq!(profile.accrue_token_rewards(100, &config, DAY + 60).unwrap(), 500);
+        assert_eq!(profile.accrue_token_rewards(100, &config, DAY + 120).unwrap(), 0);
+        // A new day brings a fresh allowance
+        assert_eq!(profile.accrue_token_rewards(100, &config, 2 * DAY).unwrap(), 1_000);
+        assert_eq!(profile.pending_rewards, 2_500);
+        
+        config.tokens_per_xp = u64::MAX;
+        assert_error(profile.accrue_token_rewards(2, &config, 2 * DAY), ErrorCode::NumericOverflow);
+    }
+
+    #[test]
+    fn level_curves_start_at_one_and_never_decrease() {
+        let curves = [
+            LevelCurve { base_xp: 1, growth_factor_bps: BASE_MULTIPLIER_BPS },
+            LevelCurve { base_xp: 100, growth_factor_bps: BASE_MULTIPLIER_BPS },
+            LevelCurve { base_xp: 100, growth_factor_bps: 11_000 },
+            LevelCurve { base_xp: 250, growth_factor_bps: 30_000 },
+        ];
+        for curve in &curves {
+            assert_eq!(level_for_xp(0, curve), 1);
+            let mut previous = 1;
+            for xp in (0..200_000u64).step_by(97) {
+                let level = level_for_xp(xp, curve);
+                assert!(level >= previous);
+                previous = level;
+            }
+        }
+    }
+
+    fn leaderboard(leaderboard_type: LeaderboardType, period_index: u32) -> Leaderboard {
+        Leaderboard {
+            count: 0,
+            reset_at: 0,
+            leaderboard_type: leaderboard_type as u8,
+            finalized: 0,
+            _padding: [0; 2],
+            period_index,
+            entries: [LeaderboardEntry::default(); LEADERBOARD_CAPACITY],
+        }
+    }
+
+    fn weekly_leaderboard(week_index: u32) -> WeeklyLeaderboard {
+        WeeklyLeaderboard {
+            count: 0,
+            week_index,
+            finalized: 0,
+            _padding: [0; 3],
+            entries: [LeaderboardEntry::default(); LEADERBOARD_CAPACITY],
+        }
+    }
+
+    #[test]
+    fn month_index_follows_the_calendar() {
+        assert_eq!(month_index(0), 0);
+        assert_eq!(month_index(31 * DAY - 1), 0);
+        assert_eq!(month_index(31 * DAY), 1);
+        // 2024-02-29 23:59:59 and 2024-03-01 00:00:00 UTC
+        assert_eq!(month_index(1_709_251_199), 649);
+        assert_eq!(month_index(1_709_251_200), 650);
+    }
+
+    #[test]
+    fn period_xp_starts_over_each_week_and_month() {
+        // 2024-03-01 is a Friday; epoch weeks start on Thursdays
+        let march_1 = 1_709_251_200;
+        let mut profile = profile();
+        profile.earn_xp(100, march_1 - DAY).unwrap();
+        profile.earn_xp(50, march_1).unwrap();
+        assert_eq!(profile.xp, 150);
+        assert_eq!(profile.weekly_xp_at(march_1), 150);
+        assert_eq!(profile.monthly_xp_at(march_1), 50);
+        assert_eq!(profile.period_xp_at(LeaderboardType::AllTime, march_1), 150);
+        // Nothing earned yet in the following week
+        assert_eq!(profile.weekly_xp_at(march_1 + 7 * DAY), 0);
+    }
+
+    #[test]
+    fn period_leaderboards_rank_by_xp_earned_in_the_period() {
+        let now = 1_709_251_200;
+        let mut veteran = profile();
+        veteran.earn_xp(10_000, now - 60 * DAY).unwrap();
+        veteran.earn_xp(10, now).unwrap();
+        let mut newcomer = profile();
+        newcomer.earn_xp(500, now).unwrap();
+        
+        let mut monthly = leaderboard(LeaderboardType::Monthly, month_index(now));
+        monthly.record(&veteran, now).unwrap();
+        monthly.record(&newcomer, now).unwrap();
+        assert_eq!(monthly.entries[0].user, newcomer.authority);
+        assert_eq!(monthly.entries[0].xp, 500);
+        assert_eq!(monthly.entries[1].xp, 10);
+        
+        let mut all_time = leaderboard(LeaderboardType::AllTime, 0);
+        all_time.record(&newcomer, now).unwrap();
+        all_time.record(&veteran, now).unwrap();
+        assert_eq!(all_time.entries[0].user, veteran.authority);
+    }
+
+    #[test]
+    fn period_leaderboards_reject_other_periods_and_finalized_boards() {
+        let now = 1_709_251_200;
+        let profile = profile();
+        let mut last_week = leaderboard(LeaderboardType::Weekly, week_index(now) - 1);
+        assert_error(last_week.record(&profile, now), ErrorCode::InvalidPeriod);
+        
+        let mut this_week = leaderboard(LeaderboardType::Weekly, week_index(now));
+        this_week.finalized = 1;
+        assert_error(this_week.record(&profile, now), ErrorCode::InvalidPeriod);
+    }
+
+    #[test]
+    fn finalized_leaderboards_cannot_be_reset() {
+        let now = 1_709_251_200;
+        let mut board = leaderboard(LeaderboardType::Weekly, week_index(now) - 1);
+        board.upsert(LeaderboardEntry::from_profile(&profile()));
+        board.finalized = 1;
+        assert_error(board.reset(now), ErrorCode::LeaderboardFinalized);
+        assert_eq!(board.count, 1);
+        
+        board.finalized = 0;
+        board.reset(now).unwrap();
+        assert_eq!(board.count, 0);
+    }
+
+    #[test]
+    fn only_high_tiers_get_certificates() {
+        assert!(!AchievementTier::Bronze.has_certificate());
+        assert!(!AchievementTier::Silver.has_certificate());
+        assert!(AchievementTier::Gold.has_certificate());
+        assert!(AchievementTier::Platinum.has_certificate());
+        assert!(AchievementTier::Diamond.has_certificate());
+    }
+
+    #[test]
+    fn a_profile_with_every_text_field_full_fits_its_account() {
+        let mut profile = profile();
+        profile.username = "u".repeat(MAX_USERNAME_LEN);
+        profile.bio = "b".repeat(MAX_BIO_LEN);
+        profile.avatar_uri = "a".repeat(MAX_AVATAR_URI_LEN);
+        profile.social_link = "s".repeat(MAX_SOCIAL_LINK_LEN);
+        profile.referred_by = Some(Pubkey::new_unique());
+        profile.guild = Some(Pubkey::new_unique());
+        profile.delegate = Some(Pubkey::new_unique());
+        
+        let mut data = Vec::new();
+        profile.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), 8 + UserProfile::INIT_SPACE);
+    }
+
+    #[test]
+    fn a_grown_profile_reads_new_fields_as_zero() {
+        let mut profile = profile();
+        profile.bio = "Learning Anchor".to_string();
+        profile.submission_nonce = 7;
+        let mut data = Vec::new();
+        profile.try_serialize(&mut data).unwrap();
+        
+        // An account from before the trailing fields existed can't be read...
+        let legacy_len = data.len() - (2 + 8 + 8 + 4 + 32 + 4);
+        assert!(UserProfile::try_deserialize(&mut &data[..legacy_len]).is_err());
+        
+        // ...until it is reallocated with zeroed space, as the realloc does
+        let mut grown = data[..legacy_len].to_vec();
+        grown.resize(8 + UserProfile::INIT_SPACE, 0);
+        let migrated = UserProfile::try_deserialize(&mut &grown[..]).unwrap();
+        assert_eq!(migrated.bio, "Learning Anchor");
+        assert_eq!(migrated.submission_nonce, 7);
+        assert_eq!(migrated.open_challenges, 0);
+        assert_eq!(migrated.monthly_xp, 0);
+        assert_eq!(migrated.profile_id, Pubkey::default());
+        assert_eq!(migrated.milestone_awards, 0);
+    }
+
+    #[test]
+    fn moved_profile_keeps_its_records() {
+        let mut original = profile();
+        original.referral_code = referral_code_for(&original.profile_id);
+        let mut achievement = blank_achievement();
+        award_gold(&mut achievement, &mut original, DAY).unwrap();
+        
+        // A transfer copies the profile under a new authority
+        let mut moved = original.clone();
+        moved.authority = Pubkey::new_unique();
+        
+        assert_eq!(achievement.user, moved.profile_id);
+        assert_eq!(moved.referral_code, referral_code_for(&moved.profile_id));
+        assert_error(
+            award_gold(&mut achievement, &mut moved, 2 * DAY),
+            ErrorCode::AchievementAlreadyAwarded,
+        );
+        assert_eq!(moved.achievements_earned, 1);
+    }
+
+    #[test]
+    fn usernames_are_trimmed_and_length_checked() {
+        assert_error(normalize_username(""), ErrorCode::InvalidUsername);
+        assert_error(normalize_username("ab"), ErrorCode::InvalidUsername);
+        assert_error(normalize_username(&"a".repeat(33)), ErrorCode::InvalidUsername);
+        assert_error(normalize_username("     "), ErrorCode::InvalidUsername);
+        assert_eq!(normalize_username("  learner  ").unwrap(), "learner");
+        assert_eq!(normalize_username(&"a".repeat(32)).unwrap(), "a".repeat(32));
+    }
+
+    /// Lay `(key, owner, lamports, data)` accounts out the way the runtime
+    /// passes them to a program, so `deserialize` yields `AccountInfo`s that
+    /// can be closed and reallocated. Kept in `u64` words for alignment.
+    fn runtime_input(accounts: Vec<(Pubkey, Pubkey, u64, Vec<u8>)>) -> Vec<u64> {
+        let mut input = (accounts.len() as u64).to_le_bytes().to_vec();
+        for (key, owner, lamports, data) in accounts {
+            input.extend_from_slice(&[u8::MAX, 1, 1, 0]);
+            input.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            input.extend_from_slice(key.as_ref());
+            input.extend_from_slice(owner.as_ref());
+            input.extend_from_slice(&lamports.to_le_bytes());
+            input.extend_from_slice(&(data.len() as u64).to_le_bytes());
+            input.extend_from_slice(&data);
+            input.resize((input.len() + MAX_PERMITTED_DATA_INCREASE).next_multiple_of(8), 0);
+            input.extend_from_slice(&0u64.to_le_bytes());
+        }
+        input.extend_from_slice(&0u64.to_le_bytes());
+        input.extend_from_slice(crate::ID.as_ref());
+        input
+            .chunks(8)
+            .map(|word| {
+                let mut bytes = [0; 8];
+                bytes[..word.len()].copy_from_slice(word);
+                u64::from_le_bytes(bytes)
+            })
+            .collect()
+    }
+
+    fn serialized<T: AccountSerialize>(account: &T) -> Vec<u8> {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn closing_a_profile_refunds_its_rent_once() {
+        let mut input = runtime_input(vec![
+            (Pubkey::new_unique(), crate::ID, 5_000, serialized(&profile())),
+            (Pubkey::new_unique(), system_program::ID, 1_000, Vec::new()),
+        ]);
+        let (_, accounts, _) = unsafe { deserialize(input.as_mut_ptr() as *mut u8) };
+        let (profile_info, authority) = (&accounts[0], &accounts[1]);
+        
+        let user_profile = Account::<UserProfile>::try_from(profile_info).unwrap();
+        user_profile.close(authority.clone()).unwrap();
+        assert_eq!(authority.lamports(), 6_000);
+        assert_eq!(profile_info.lamports(), 0);
+        
+        // A second close can't even load the account
+        assert!(Account::<UserProfile>::try_from(profile_info).is_err());
+        assert_eq!(authority.lamports(), 6_000);
+    }
+
+    /// Accounts for `rename_profile`: the profile, the registry entry of its
+    /// current name, an unclaimed entry for the new name and its authority
+    fn rename_input(profile: &UserProfile) -> Vec<u64> {
+        let profile_key = Pubkey::new_unique();
+        let current = UsernameRegistry {
+            profile: profile_key,
+            owner: profile.authority,
+            registered_at: 0,
+        };
+        let unclaimed = UsernameRegistry {
+            profile: Pubkey::default(),
+            owner: Pubkey::default(),
+            registered_at: 0,
+        };
+        runtime_input(vec![
+            (profile_key, crate::ID, 5_000, serialized(profile)),
+            (Pubkey::new_unique(), crate::ID, 1_000, serialized(&current)),
+            (Pubkey::new_unique(), crate::ID, 1_000, serialized(&unclaimed)),
+            (profile.authority, system_program::ID, 0, Vec::new()),
+        ])
+    }
+
+    #[test]
+    fn renames_are_validated_and_rate_limited() {
+        let mut input = rename_input(&profile());
+        let (_, accounts, _) = unsafe { deserialize(input.as_mut_ptr() as *mut u8) };
+        let mut user_profile = Account::<UserProfile>::try_from(&accounts[0]).unwrap();
+        let current = Account::<UsernameRegistry>::try_from(&accounts[1]).unwrap();
+        let mut new_registry = Account::<UsernameRegistry>::try_from(&accounts[2]).unwrap();
+        let authority = &accounts[3];
+        let now = 100 * DAY;
+        
+        assert_error(
+            rename_profile(
+                &mut user_profile,
+                Some(&current),
+                Some(&mut new_registry),
+                authority,
+                "a!",
+                now,
+            ),
+            ErrorCode::InvalidUsername,
+        );
+        
+        rename_profile(
+            &mut user_profile,
+            Some(&current),
+            Some(&mut new_registry),
+            authority,
+            " scholar ",
+            now,
+        )
+        .unwrap();
+        assert_eq!(user_profile.username, "scholar");
+        assert_eq!(user_profile.last_username_change, now);
+        
+        assert_error(
+            rename_profile(
+                &mut user_profile,
+                None,
+                Some(&mut new_registry),
+                authority,
+                "Scholar",
+                now + USERNAME_CHANGE_COOLDOWN - 1,
+            ),
+            ErrorCode::UsernameChangeTooSoon,
+        );
+    }
+
+    /// The profile's PDA, storing its bump on the profile
+    fn profile_address(profile: &mut UserProfile) -> Pubkey {
+        let (address, bump) = Pubkey::find_program_address(
+            &[b"user_profile", profile.authority.as_ref()],
+            &crate::ID,
+        );
+        profile.bump = bump;
+        address
+    }
+
+    #[test]
+    fn only_the_owner_can_close_an_achievement() {
+        let mut owner = profile();
+        let mut stranger = profile();
+        let mut achievement = blank_achievement();
+        award_gold(&mut achievement, &mut owner, DAY).unwrap();
+        let (achievement_key, _) = Pubkey::find_program_address(
+            &[b"achievement", owner.profile_id.as_ref(), &id_seed("quiz_master")],
+            &crate::ID,
+        );
+        let ix_data = "quiz_master".to_string().try_to_vec().unwrap();
+        
+        let close_as = |signer: Pubkey, profile_key: Pubkey, profile: &UserProfile| {
+            let mut input = runtime_input(vec![
+                (profile_key, crate::ID, 5_000, serialized(profile)),
+                (achievement_key, crate::ID, 1_000, serialized(&achievement)),
+                (signer, system_program::ID, 0, Vec::new()),
+            ]);
+            let (_, accounts, _) = unsafe { deserialize(input.as_mut_ptr() as *mut u8) };
+            CloseAchievement::try_accounts(
+                &crate::ID,
+                &mut &accounts[..],
+                &ix_data,
+                &mut CloseAchievementBumps::default(),
+                &mut BTreeSet::new(),
+            )
+            .is_ok()
+        };
+        
+        let owner_key = profile_address(&mut owner);
+        let stranger_key = profile_address(&mut stranger);
+        assert!(close_as(owner.authority, owner_key, &owner));
+        // Neither with their own profile nor with the owner's
+        assert!(!close_as(stranger.authority, stranger_key, &stranger));
+        assert!(!close_as(stranger.authority, owner_key, &owner));
+    }
+
+    #[test]
+    fn a_rename_releases_the_old_name_and_cannot_take_a_claimed_one() {
+        let mut input = rename_input(&profile());
+        let (_, accounts, _) = unsafe { deserialize(input.as_mut_ptr() as *mut u8) };
+        let mut user_profile = Account::<UserProfile>::try_from(&accounts[0]).unwrap();
+        let current = Account::<UsernameRegistry>::try_from(&accounts[1]).unwrap();
+        let mut new_registry = Account::<UsernameRegistry>::try_from(&accounts[2]).unwrap();
+        let authority = &accounts[3];
+        
+        rename_profile(
+            &mut user_profile,
+            Some(&current),
+            Some(&mut new_registry),
+            authority,
+            "scholar",
+            DAY,
+        )
+        .unwrap();
+        assert_eq!(new_registry.profile, user_profile.key());
+        assert_eq!(new_registry.owner, user_profile.authority);
+        // The old entry is closed and its rent refunded, so "learner" is free again
+        assert_eq!(accounts[1].lamports(), 0);
+        assert_eq!(authority.lamports(), 1_000);
+        
+        // Another profile can't rename onto the name just claimed
+        let mut other_input = rename_input(&profile());
+        let (_, other, _) = unsafe { deserialize(other_input.as_mut_ptr() as *mut u8) };
+        let mut other_profile = Account::<UserProfile>::try_from(&other[0]).unwrap();
+        let other_current = Account::<UsernameRegistry>::try_from(&other[1]).unwrap();
+        assert_error(
+            rename_profile(
+                &mut other_profile,
+                Some(&other_current),
+                Some(&mut new_registry),
+                &other[3],
+                "Scholar",
+                DAY,
+            ),
+            ErrorCode::UsernameTaken,
+        );
+    }
+
+    #[test]
+    fn the_leaderboard_stays_sorted_and_capped() {
+        let mut board = leaderboard(LeaderboardType::AllTime, 0);
+        let mut profiles: Vec<_> = (0..150u64)
+            .map(|i| {
+                let mut profile = profile();
+                // 919 is coprime to 1000, so this visits distinct XP values out of order
+                profile.xp = i * 919 % 1000 + 1;
+                profile
+            })
+            .collect();
+        for profile in &profiles {
+            board.upsert(LeaderboardEntry::from_profile(profile));
+        }
+        
+        // Re-ranking a user moves their entry instead of adding another
+        profiles[0].xp = 5_000;
+        assert!(board.upsert(LeaderboardEntry::from_profile(&profiles[0])));
+        
+        assert_eq!(board.count as usize, LEADERBOARD_CAPACITY);
+        assert!(board.entries.windows(2).all(|pair| pair[0].xp >= pair[1].xp));
+        assert_eq!(board.entries[0].user, profiles[0].authority);
+        let mut expected: Vec<_> = profiles.iter().map(|profile| profile.xp).collect();
+        expected.sort_unstable_by(|a, b| b.cmp(a));
+        let ranked: Vec<_> = board.entries.iter().map(|entry| entry.xp).collect();
+        assert_eq!(ranked, expected[..LEADERBOARD_CAPACITY]);
+        
+        // Too little XP to place leaves the board untouched
+        let mut newcomer = profile();
+        newcomer.xp = 1;
+        assert!(!board.upsert(LeaderboardEntry::from_profile(&newcomer)));
+        assert_eq!(board.count as usize, LEADERBOARD_CAPACITY);
+    }
+
+    #[test]
+    fn a_stored_bump_derives_the_pda_in_one_step() {
+        let mut profile = profile();
+        let address = profile_address(&mut profile);
+        
+        // `bump = user_profile.bump` costs one hash; `find_program_address`
+        // hashes once per bump it tries, starting from 255
+        let seeds: &[&[u8]] = &[b"user_profile", profile.authority.as_ref(), &[profile.bump]];
+        assert_eq!(Pubkey::create_program_address(seeds, &crate::ID).unwrap(), address);
+        
+        // Profiles from before stored bumps read 0 until `migrate_profile` runs
+        let legacy: &[&[u8]] = &[b"user_profile", profile.authority.as_ref(), &[0]];
+        assert_ne!(Pubkey::create_program_address(legacy, &crate::ID).ok(), Some(address));
+    }
+
+    #[test]
+    fn an_existing_progress_tracker_must_be_completed() {
+        let tracker = |current, completed| AchievementProgress {
+            user: Pubkey::new_unique(),
+            achievement_id: "quiz_master".to_string(),
+            current,
+            target: 10,
+            completed,
+        };
+        let mut input = runtime_input(vec![
+            (Pubkey::new_unique(), system_program::ID, 0, Vec::new()),
+            (Pubkey::new_unique(), crate::ID, 1_000, serialized(&tracker(4, false))),
+            (Pubkey::new_unique(), crate::ID, 1_000, serialized(&tracker(10, true))),
+        ]);
+        let (_, accounts, _) = unsafe { deserialize(input.as_mut_ptr() as *mut u8) };
+        
+        // No tracker was ever created for this achievement
+        ensure_progress_completed(&accounts[0]).unwrap();
+        assert_error(
+            ensure_progress_completed(&accounts[1]),
+            ErrorCode::AchievementProgressIncomplete,
+        );
+        ensure_progress_completed(&accounts[2]).unwrap();
+    }
+
+    #[test]
+    fn pausing_halts_user_instructions_until_resumed() {
+        let mut config = config();
+        config.ensure_not_paused().unwrap();
+        
+        config.paused = true;
+        assert_error(config.ensure_not_paused(), ErrorCode::ProgramPaused);
+        
+        config.paused = false;
+        config.ensure_not_paused().unwrap();
+    }
+
+    fn quiz_progress() -> QuizProgress {
+        QuizProgress {
+            user: Pubkey::new_unique(),
+            attempts: 0,
+            best_score: 0,
+            best_xp: 0,
+            last_attempt_at: 0,
+            total_xp_from_quiz: 0,
+        }
+    }
+
+    #[test]
+    fn retakes_only_earn_their_improvement() {
+        let mut progress = quiz_progress();
+        
+        assert_eq!(progress.record_attempt(0, 6, 60, DAY).unwrap(), 60);
+        // An improved retake earns the difference
+        assert_eq!(progress.record_attempt(1, 9, 90, 2 * DAY).unwrap(), 30);
+        // A worse one earns nothing but still counts
+        assert_eq!(progress.record_attempt(2, 4, 40, 3 * DAY).unwrap(), 0);
+        assert_eq!(progress.attempts, 3);
+        assert_eq!((progress.best_score, progress.best_xp), (9, 90));
+        assert_eq!(progress.last_attempt_at, 3 * DAY);
+        
+        // Attempt numbers can't be skipped or reused
+        assert_error(progress.record_attempt(5, 10, 100, 4 * DAY), ErrorCode::InvalidAttempt);
+        assert_error(progress.record_attempt(2, 10, 100, 4 * DAY), ErrorCode::InvalidAttempt);
+    }
+
+    #[test]
+    fn a_custom_window_and_grace_move_the_streak_boundary() {
+        const HALF_DAY: i64 = DAY / 2;
+        let mut profile = profile();
+        profile.record_activity(20 * HALF_DAY, HALF_DAY, 0).unwrap();
+        // The next 12-hour window extends the streak...
+        profile.record_activity(21 * HALF_DAY, HALF_DAY, 0).unwrap();
+        assert_eq!(profile.streak, 2);
+        // ...and skipping one resets it
+        profile.record_activity(23 * HALF_DAY, HALF_DAY, 0).unwrap();
+        assert_eq!(profile.streak, 1);
+        
+        // With an hour of grace, activity just after the due window still counts for it
+        profile.record_activity(25 * HALF_DAY + 3_599, HALF_DAY, 3_600).unwrap();
+        assert_eq!(profile.streak, 2);
+        assert_eq!(profile.last_streak_day, 24);
+        profile.record_activity(27 * HALF_DAY + 3_600, HALF_DAY, 3_600).unwrap();
+        assert_eq!(profile.streak, 1);
+    }
+
+    #[test]
+    fn back_to_back_submissions_wait_out_the_cooldown() {
+        let mut config = config();
+        config.quiz_cooldown_secs = 60;
+        let last_quiz_at = 10 * DAY;
+        
+        assert_eq!(config.quiz_cooldown_remaining(last_quiz_at, last_quiz_at), 60);
+        assert_eq!(config.quiz_cooldown_remaining(last_quiz_at, last_quiz_at + 59), 1);
+        assert_eq!(config.quiz_cooldown_remaining(last_quiz_at, last_quiz_at + 60), 0);
+        // A profile that never submitted isn't held back
+        assert_eq!(config.quiz_cooldown_remaining(0, last_quiz_at), 0);
+    }
+
+    #[test]
+    fn a_banked_freeze_covers_exactly_one_missed_day() {
+        let mut profile = profile();
+        let mut bank = StreakFreeze { user: Pubkey::default(), count: 0, last_milestone: 0 };
+        for _ in 0..MAX_STREAK_FREEZES {
+            bank.bank(profile.profile_id).unwrap();
+        }
+        assert_error(bank.bank(profile.profile_id), ErrorCode::TooManyStreakFreezes);
+        assert_eq!(bank.count, MAX_STREAK_FREEZES);
+        
+        profile.record_activity(10 * DAY, DAY, 0).unwrap();
+        profile.record_activity(11 * DAY, DAY, 0).unwrap();
+        // Day 12 is missed; a freeze is spent and the streak carries on
+        assert!(profile.use_banked_freeze(&mut bank, 13 * DAY, DAY, 0));
+        profile.record_activity(13 * DAY, DAY, 0).unwrap();
+        assert_eq!(profile.streak, 3);
+        assert_eq!(bank.count, MAX_STREAK_FREEZES - 1);
+        
+        // Two missed days reset the streak and leave the bank alone
+        assert!(!profile.use_banked_freeze(&mut bank, 16 * DAY, DAY, 0));
+        profile.record_activity(16 * DAY, DAY, 0).unwrap();
+        assert_eq!(profile.streak, 1);
+        assert_eq!(bank.count, MAX_STREAK_FREEZES - 1);
+    }
+
+    fn quiz_metadata() -> QuizMetadata {
+        QuizMetadata {
+            creator: Pubkey::new_unique(),
+            quiz_id: "rust_basics".to_string(),
+            title: "Rust basics".to_string(),
+            description: String::new(),
+            category: QuizCategory::Rust,
+            difficulty: Difficulty::Easy,
+            created_at: 0,
+            is_active: true,
+            is_event: false,
+            total_questions: 10,
+            answer_hashes: Vec::new(),
+            answers_merkle_root: [0; 32],
+            time_limit_secs: 0,
+            question_points: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn submissions_need_an_active_matching_quiz() {
+        let mut quiz = quiz_metadata();
+        quiz.ensure_accepts(10).unwrap();
+        assert_error(quiz.ensure_accepts(12), ErrorCode::QuizMismatch);
+        
+        quiz.is_active = false;
+        assert_error(quiz.ensure_accepts(10), ErrorCode::QuizInactive);
+    }
+
+    #[test]
+    fn a_seven_day_streak_earns_its_catalog_achievement() {
+        let config = config();
+        let mut profile = profile();
+        let mut achievement = blank_achievement();
+        let mut streak_7 = definition(AchievementTier::Bronze, 0);
+        streak_7.achievement_id = streak_achievement_id(7);
+        streak_7.name = "7-day streak".to_string();
+        for day in 10..16 {
+            profile.record_activity(day * DAY, DAY, 0).unwrap();
+            assert!(config.streak_milestone_bonus(profile.streak).is_none());
+        }
+        profile.record_activity(16 * DAY, DAY, 0).unwrap();
+        assert_eq!(profile.streak, 7);
+        assert!(config.streak_milestone_bonus(profile.streak).is_some());
+        
+        let xp_before = profile.xp;
+        let granted = grant_milestone_achievement(
+            &mut achievement,
+            &mut streak_7,
+            &mut profile,
+            &config,
+            16 * DAY,
+            254,
+        );
+        assert!(granted.unwrap());
+        assert_eq!(achievement.achievement_id, streak_achievement_id(7));
+        assert_eq!(achievement.achievement_name, "7-day streak");
+        assert!(achievement.tier == AchievementTier::Bronze);
+        assert_eq!(achievement.user, profile.profile_id);
+        assert_eq!(achievement.bonus_xp, config.tier_bonus(&AchievementTier::Bronze));
+        assert_eq!(profile.xp, xp_before + achievement.bonus_xp);
+        assert_eq!(profile.achievements_earned, 1);
+        
+        // The milestone is only granted once
+        let regranted = grant_milestone_achievement(
+            &mut achievement,
+            &mut streak_7,
+            &mut profile,
+            &config,
+            16 * DAY,
+            254,
+        );
+        assert!(!regranted.unwrap());
+        assert_eq!(profile.achievements_earned, 1);
+    }
+
+    fn started_attempt(answers: &[u8], salt: &[u8; 32], started_at: i64) -> QuizAttempt {
+        QuizAttempt {
+            user: Pubkey::new_unique(),
+            quiz_id: "rust_basics".to_string(),
+            attempt: 0,
+            commitment: answer_commitment(answers, salt),
+            started_at,
+            revealed: false,
+            answered: [0; 32],
+            answered_count: 0,
+            correct_count: 0,
+        }
+    }
+
+    #[test]
+    fn a_commitment_opens_once_with_its_salt_inside_the_window() {
+        let (answers, salt) = ([1, 0, 3], [7; 32]);
+        
+        let mut attempt = started_attempt(&answers, &salt, DAY);
+        assert_error(attempt.reveal(&answers, &[8; 32], DAY + 60), ErrorCode::CommitmentMismatch);
+        assert_error(attempt.reveal(&[1, 0, 2], &salt, DAY + 60), ErrorCode::CommitmentMismatch);
+        attempt.reveal(&answers, &salt, DAY + 60).unwrap();
+        assert_error(
+            attempt.reveal(&answers, &salt, DAY + 120),
+            ErrorCode::AnswersAlreadyRevealed,
+        );
+        
+        let mut late = started_attempt(&answers, &salt, DAY);
+        assert_error(
+            late.reveal(&answers, &salt, DAY + QUIZ_REVEAL_WINDOW + 1),
+            ErrorCode::RevealExpired,
+        );
+        late.reveal(&answers, &salt, DAY + QUIZ_REVEAL_WINDOW).unwrap();
+    }
+
+    #[test]
+    fn the_gap_is_measured_from_the_previous_activity() {
+        let mut profile = profile();
+        profile.record_activity(10 * DAY + 100, DAY, 0).unwrap();
+        profile.record_activity(11 * DAY + 100, DAY, 0).unwrap();
+        assert_eq!(profile.streak, 2);
+        
+        // Well over 86400 seconds later: the streak resets rather than growing
+        profile.record_activity(13 * DAY + 200, DAY, 0).unwrap();
+        assert_eq!(profile.streak, 1);
+        assert_eq!(profile.last_active, 13 * DAY + 200);
+    }
+
+    fn definition(tier: AchievementTier, max_supply: u32) -> AchievementDefinition {
+        AchievementDefinition {
+            achievement_id: "quiz_master".to_string(),
+            prerequisite_achievement_id: None,
+            created_at: 0,
+            metadata_uri: String::new(),
+            name: "Quiz master".to_string(),
+            tier,
+            bonus_xp: None,
+            max_supply,
+            remaining_supply: max_supply,
+            is_active: true,
+        }
+    }
+
+    #[test]
+    fn revoking_an_achievement_undoes_its_award() {
+        let config = config();
+        let mut profile = profile();
+        profile.earn_xp(300, DAY).unwrap();
+        profile.level = level_for_xp(profile.xp, &config.level_curve);
+        let before = (profile.xp, profile.level, profile.achievements_earned);
+        
+        let mut platinum = definition(AchievementTier::Platinum, 2);
+        platinum.take_supply().unwrap();
+        let bonus_xp = config.tier_bonus(&platinum.tier);
+        let mut achievement = blank_achievement();
+        achievement
+            .award(
+                &mut profile,
+                platinum.achievement_id.clone(),
+                platinum.name.clone(),
+                AchievementTier::Platinum,
+                bonus_xp,
+                DAY,
+                254,
+            )
+            .unwrap();
+        profile.level = level_for_xp(profile.xp, &config.level_curve);
+        assert_eq!(platinum.remaining_supply, 1);
+        
+        assert_eq!(profile.revoke_achievement(achievement.bonus_xp, &config.level_curve), 500);
+        platinum.restore_supply();
+        assert_eq!((profile.xp, profile.level, profile.achievements_earned), before);
+        assert_eq!(platinum.remaining_supply, 2);
+        
+        // Restoring never exceeds the supply, and spent XP is not clawed back below zero
+        platinum.restore_supply();
+        assert_eq!(platinum.remaining_supply, 2);
+        profile.xp = 100;
+        assert_eq!(profile.revoke_achievement(1_000, &config.level_curve), 100);
+        assert_eq!((profile.xp, profile.level), (0, 1));
+    }
+
+    /// Hash a sorted pair the way `verify_merkle_proof` does
+    fn merkle_parent(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+        if a <= b {
+            hashv(&[a, b]).to_bytes()
+        } else {
+            hashv(&[b, a]).to_bytes()
+        }
+    }
+
+    #[test]
+    fn proven_answers_are_graded_across_chunks() {
+        let quiz_id = "rust_basics";
+        let correct = [2u8, 0, 1, 3];
+        let hashes: Vec<_> = (0..4).map(|q| answer_hash(quiz_id, q, correct[q as usize])).collect();
+        let leaves: Vec<_> = (0..4).map(|q| answer_leaf(q, &hashes[q as usize])).collect();
+        let left = merkle_parent(&leaves[0], &leaves[1]);
+        let right = merkle_parent(&leaves[2], &leaves[3]);
+        let root = merkle_parent(&left, &right);
+        let proven = |question: u8, answer: u8| {
+            let q = question as usize;
+            ProvenAnswer {
+                question_index: question,
+                answer,
+                correct_answer_hash: hashes[q],
+                proof: vec![leaves[q ^ 1], if q < 2 { right } else { left }],
+            }
+        };
+        let mut attempt = started_attempt(&[], &[0; 32], DAY);
+        
+        // Two chunks of two answers, one of them wrong
+        attempt.grade_proven(quiz_id, &root, 4, &[proven(0, 2), proven(1, 3)]).unwrap();
+        assert_eq!((attempt.answered_count, attempt.correct_count), (2, 1));
+        attempt.grade_proven(quiz_id, &root, 4, &[proven(2, 1), proven(3, 3)]).unwrap();
+        assert_eq!((attempt.answered_count, attempt.correct_count), (4, 3));
+        
+        let mut fresh = started_attempt(&[], &[0; 32], DAY);
+        let mut forged = proven(1, 0);
+        forged.correct_answer_hash = answer_hash(quiz_id, 1, 0);
+        assert_error(
+            fresh.grade_proven(quiz_id, &root, 4, &[forged]),
+            ErrorCode::InvalidMerkleProof,
+        );
+        let mut out_of_range = proven(3, 3);
+        out_of_range.question_index = 4;
+        assert_error(
+            fresh.grade_proven(quiz_id, &root, 4, &[out_of_range]),
+            ErrorCode::QuestionOutOfRange,
+        );
+        assert_error(
+            fresh.grade_proven(quiz_id, &root, 4, &[proven(0, 2), proven(0, 1)]),
+            ErrorCode::QuestionAlreadyAnswered,
+        );
+    }
+
+    #[test]
+    fn timed_quizzes_must_finish_within_the_limit() {
+        let mut quiz = quiz_metadata();
+        assert_eq!(quiz.measure_duration(None, DAY).unwrap(), 0);
+        assert_eq!(quiz.measure_duration(Some(DAY - 90), DAY).unwrap(), 90);
+        
+        quiz.time_limit_secs = 600;
+        assert_error(quiz.measure_duration(None, DAY), ErrorCode::QuizNotStarted);
+        assert_eq!(quiz.measure_duration(Some(DAY), DAY + 600).unwrap(), 600);
+        assert_error(quiz.measure_duration(Some(DAY), DAY + 601), ErrorCode::TimeLimitExceeded);
+    }
+
+    #[test]
+    fn progress_keeps_the_best_of_every_attempt() {
+        let config = config();
+        let mut progress = quiz_progress();
+        for (attempt, score) in [5u8, 8, 6].into_iter().enumerate() {
+            let attempt = attempt as u16;
+            let attempt_xp = config
+                .attempt_xp(&GradedScore::flat(score, 10), BASE_MULTIPLIER_BPS, attempt, 300, 0)
+                .unwrap();
+            progress.record_attempt(attempt, score, attempt_xp, DAY + attempt as i64).unwrap();
+        }
+        assert_eq!(progress.best_score, 8);
+        assert_eq!(progress.attempts, 3);
+        assert_eq!(progress.last_attempt_at, DAY + 2);
+    }
+
+    #[test]
+    fn retakes_earn_decaying_xp_down_to_the_floor() {
+        let mut config = config();
+        let perfect = GradedScore::flat(10, 10);
+        // 10 x 10 XP plus the 50 XP perfect bonus, all of which decays
+        assert_eq!(config.attempt_xp(&perfect, BASE_MULTIPLIER_BPS, 0, 60, 0).unwrap(), 150);
+        assert_eq!(config.attempt_xp(&perfect, BASE_MULTIPLIER_BPS, 1, 60, 0).unwrap(), 75);
+        assert_eq!(config.attempt_xp(&perfect, BASE_MULTIPLIER_BPS, 2, 60, 0).unwrap(), 37);
+        assert_eq!(config.attempt_xp(&perfect, BASE_MULTIPLIER_BPS, 9, 60, 0).unwrap(), 37);
+        
+        config.retake_decay_bps = 9_000;
+        config.min_retake_multiplier_bps = 0;
+        assert_eq!(config.retake_multiplier_bps(1), 9_000);
+        assert_eq!(config.retake_multiplier_bps(2), 8_100);
+    }
+
+    #[test]
+    fn weighted_points_must_match_the_quiz_question_for_question() {
+        assert_error(GradedScore::weighted(&[3, 1], &[3, 2, 2], 3), ErrorCode::QuizMismatch);
+        assert_error(GradedScore::weighted(&[3, 1, 2, 1], &[3, 2, 2], 3), ErrorCode::QuizMismatch);
+        assert_error(GradedScore::weighted(&[], &[3, 2, 2], 3), ErrorCode::QuizMismatch);
+        
+        // XP follows points rather than questions answered
+        let config = config();
+        let graded = GradedScore::weighted(&[5, 0, 1], &[5, 1, 1], 3).unwrap();
+        assert_eq!(graded.score, 2);
+        assert_eq!(config.attempt_xp(&graded, BASE_MULTIPLIER_BPS, 0, 60, 0).unwrap(), 60);
+    }
+
+    #[test]
+    fn a_second_submission_inside_the_cooldown_is_rejected() {
+        let mut config = config();
+        config.quiz_cooldown_secs = 60;
+        let mut profile = profile();
+        
+        profile.mark_quiz_submitted(&config, false, 10 * DAY).unwrap();
+        assert_error(
+            profile.mark_quiz_submitted(&config, false, 10 * DAY + 30),
+            ErrorCode::CooldownActive,
+        );
+        // The rejected attempt doesn't restart the cooldown
+        assert_eq!(profile.last_quiz_at, 10 * DAY);
+        profile.mark_quiz_submitted(&config, false, 10 * DAY + 60).unwrap();
+        assert_eq!(profile.last_quiz_at, 10 * DAY + 60);
+        
+        // Event quizzes skip the wait
+        profile.mark_quiz_submitted(&config, true, 10 * DAY + 61).unwrap();
+    }
+
+    #[test]
+    fn large_course_rewards_are_capped_or_wait_for_approval() {
+        let mut config = config();
+        assert!(config.course_reward_approved(0).unwrap());
+        assert!(config.course_reward_approved(DEFAULT_COURSE_APPROVAL_XP).unwrap());
+        assert!(!config.course_reward_approved(DEFAULT_COURSE_APPROVAL_XP + 1).unwrap());
+        assert!(!config.course_reward_approved(DEFAULT_MAX_COURSE_REWARD_XP).unwrap());
+        assert_error(
+            config.course_reward_approved(DEFAULT_MAX_COURSE_REWARD_XP + 1),
+            ErrorCode::CourseRewardTooHigh,
+        );
+        
+        // With no cap set only reward-free courses can be published
+        config.max_course_reward_xp = 0;
+        config.course_approval_xp = 0;
+        assert!(config.course_reward_approved(0).unwrap());
+        assert_error(config.course_reward_approved(1), ErrorCode::CourseRewardTooHigh);
+    }
+
+    #[test]
+    fn difficulty_multipliers_round_down_at_every_tier() {
+        let mut config = config();
+        config.xp_per_correct = 7;
+        // 1 of 3 correct is 7 XP before the multiplier
+        let graded = GradedScore::flat(1, 3);
+        let expected = [
+            (Difficulty::Easy, 7),
+            (Difficulty::Medium, 10),
+            (Difficulty::Hard, 14),
+            (Difficulty::Expert, 21),
+        ];
+        for (difficulty, xp) in expected {
+            let multiplier_bps = config.difficulty_multiplier_bps(&difficulty);
+            assert_eq!(config.attempt_xp(&graded, multiplier_bps, 0, 60, 0).unwrap(), xp);
+        }
+        // 7 x 1.4999 is just under 10.5
+        assert_eq!(config.attempt_xp(&graded, 14_999, 0, 60, 0).unwrap(), 10);
+    }
+
+    fn quiz_result(difficulty: Difficulty, points_earned: u32, points_possible: u32) -> QuizResult {
+        QuizResult {
+            user: Pubkey::new_unique(),
+            quiz_id: "rust_basics".to_string(),
+            attempt: 0,
+            score: points_earned as u8,
+            total_questions: points_possible as u8,
+            xp_earned: 0,
+            difficulty,
+            difficulty_multiplier_used: BASE_MULTIPLIER_BPS,
+            completed_at: DAY,
+            bump: 255,
+            time_taken_seconds: 60,
+            hints_used: 0,
+            duration_secs: 0,
+            points_earned,
+            points_possible,
+        }
+    }
+
+    #[test]
+    fn an_expert_perfect_score_multiplies_the_bonus_too() {
+        let mut config = config();
+        config.difficulty_multipliers_bps = [10_000, 20_000, 30_000, 50_000];
+        let multiplier_bps = config.difficulty_multiplier_bps(&Difficulty::Expert);
+        // (10 x 10 XP + 50 XP perfect bonus) x 5
+        let xp = config.attempt_xp(&GradedScore::flat(10, 10), multiplier_bps, 0, 60, 0).unwrap();
+        assert_eq!(xp, 750);
+        
+        let mut result = quiz_result(Difficulty::Expert, 10, 10);
+        result.xp_earned = xp;
+        result.difficulty_multiplier_used = multiplier_bps;
+        let stored = QuizResult::try_deserialize(&mut &serialized(&result)[..]).unwrap();
+        assert!(stored.difficulty == Difficulty::Expert);
+        assert_eq!(stored.difficulty_multiplier_used, 50_000);
+        assert_eq!(stored.xp_earned, 750);
+    }
+
+    #[test]
+    fn a_definition_bonus_overrides_the_tier_bonus() {
+        let config = config();
+        let mut badge = definition(AchievementTier::Silver, 0);
+        assert_eq!(badge.award_bonus(&config), config.tier_bonuses[1]);
+        badge.bonus_xp = Some(42);
+        assert_eq!(badge.award_bonus(&config), 42);
+    }
+
+    #[test]
+    fn quiz_stats_aggregate_attempts_and_keep_the_first_best() {
+        let mut stats = QuizStats {
+            quiz: Pubkey::new_unique(),
+            attempts: 0,
+            total_score: 0,
+            total_questions_answered: 0,
+            perfect_scores: 0,
+            best_points: 0,
+            best_score_holder: Pubkey::default(),
+        };
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let carol = Pubkey::new_unique();
+        
+        stats.record(&GradedScore::flat(6, 10), alice).unwrap();
+        stats.record(&GradedScore::flat(10, 10), bob).unwrap();
+        // Carol ties Bob's perfect score, so Bob keeps the top spot
+        stats.record(&GradedScore::flat(10, 10), carol).unwrap();
+        stats.record(&GradedScore::flat(3, 10), alice).unwrap();
+        
+        assert_eq!(stats.attempts, 4);
+        assert_eq!(stats.total_score, 29);
+        assert_eq!(stats.total_questions_answered, 40);
+        assert_eq!(stats.perfect_scores, 2);
+        assert_eq!(stats.best_points, 10);
+        assert_eq!(stats.best_score_holder, bob);
+        
+        stats.total_score = u64::MAX;
+        assert_error(stats.record(&GradedScore::flat(1, 10), alice), ErrorCode::NumericOverflow);
+    }
+
+    #[test]
+    fn fast_finishes_earn_a_speed_bonus_and_slow_ones_do_not() {
+        let mut config = config();
+        config.speed_threshold_seconds = 120;
+        config.speed_bonus_xp = 100;
+        config.min_time_taken_seconds = 20;
+        let graded = GradedScore::flat(5, 10);
+        
+        // 5 x 10 XP, plus a bonus growing from 0 at the threshold to 100 at the minimum
+        assert_eq!(config.attempt_xp(&graded, BASE_MULTIPLIER_BPS, 0, 20, 0).unwrap(), 150);
+        assert_eq!(config.attempt_xp(&graded, BASE_MULTIPLIER_BPS, 0, 70, 0).unwrap(), 100);
+        assert_eq!(config.attempt_xp(&graded, BASE_MULTIPLIER_BPS, 0, 120, 0).unwrap(), 50);
+        assert_eq!(config.attempt_xp(&graded, BASE_MULTIPLIER_BPS, 0, 600, 0).unwrap(), 50);
+        // Speed alone earns nothing
+        let zero_score = GradedScore::flat(0, 10);
+        assert_eq!(config.attempt_xp(&zero_score, BASE_MULTIPLIER_BPS, 0, 20, 0).unwrap(), 0);
+        
+        // Times below the minimum are rejected rather than rewarded
+        config.ensure_plausible_time(20).unwrap();
+        assert_error(config.ensure_plausible_time(19), ErrorCode::InvalidTimeTaken);
+        config.min_time_taken_seconds = 0;
+        assert_error(config.ensure_plausible_time(0), ErrorCode::InvalidTimeTaken);
+        assert_error(
+            config.ensure_plausible_time(MAX_TIME_TAKEN_SECONDS + 1),
+            ErrorCode::InvalidTimeTaken,
+        );
+    }
+
+    fn quiz_entry(
+        user: Pubkey,
+        score: u16,
+        duration_secs: u32,
+        completed_at: i64,
+    ) -> QuizLeaderboardEntry {
+        QuizLeaderboardEntry {
+            user,
+            completed_at,
+            xp_earned: 0,
+            duration_secs,
+            score,
+            _padding: [0; 2],
+        }
+    }
+
+    #[test]
+    fn a_full_quiz_leaderboard_evicts_its_last_entry() {
+        let mut board = QuizLeaderboard {
+            quiz: Pubkey::new_unique(),
+            count: 0,
+            entries: [QuizLeaderboardEntry::default(); QUIZ_LEADERBOARD_CAPACITY],
+        };
+        let users: Vec<Pubkey> =
+            (0..QUIZ_LEADERBOARD_CAPACITY).map(|_| Pubkey::new_unique()).collect();
+        for (i, user) in users.iter().enumerate() {
+            assert!(board.upsert(quiz_entry(*user, 100 + i as u16, 60, DAY)));
+        }
+        assert_eq!(board.count as usize, QUIZ_LEADERBOARD_CAPACITY);
+        let last = users[0];
+        assert_eq!(board.entries[QUIZ_LEADERBOARD_CAPACITY - 1].user, last);
+        
+        // Tying the last entry doesn't place; beating it on time does
+        assert!(!board.upsert(quiz_entry(Pubkey::new_unique(), 100, 60, DAY)));
+        let faster = Pubkey::new_unique();
+        assert!(board.upsert(quiz_entry(faster, 100, 59, DAY)));
+        assert_eq!(board.count as usize, QUIZ_LEADERBOARD_CAPACITY);
+        assert_eq!(board.entries[QUIZ_LEADERBOARD_CAPACITY - 1].user, faster);
+        assert!(board.entries.iter().all(|e| e.user != last));
+        
+        // A user's better result moves their single entry up
+        assert!(board.upsert(quiz_entry(faster, 500, 59, 2 * DAY)));
+        assert_eq!(board.entries[0].user, faster);
+        assert_eq!(board.entries.iter().filter(|e| e.user == faster).count(), 1);
+        assert!(board.entries.windows(2).all(|pair| !pair[1].outranks(&pair[0])));
+    }
+
+    #[test]
+    fn global_stats_add_up_users_quizzes_and_xp() {
+        let mut stats = GlobalStats {
+            total_users: 0,
+            total_quizzes_completed: 0,
+            total_xp_minted: 0,
+            total_achievements_awarded: 0,
+            created_at: 0,
+        };
+        stats.record_user().unwrap();
+        stats.record_user().unwrap();
+        for (achievements, xp) in [(0, 150), (1, 300), (0, 0)] {
+            stats.record_quiz(achievements, xp).unwrap();
+        }
+        stats.record_awards(1, 250).unwrap();
+        
+        assert_eq!(stats.total_users, 2);
+        assert_eq!(stats.total_quizzes_completed, 3);
+        assert_eq!(stats.total_achievements_awarded, 2);
+        assert_eq!(stats.total_xp_minted, 700);
+        
+        stats.total_xp_minted = u64::MAX;
+        assert_error(stats.record_quiz(0, 1), ErrorCode::NumericOverflow);
+    }
+
+    #[test]
+    fn a_sequence_of_xp_gains_reorders_the_all_time_board() {
+        let mut board = leaderboard(LeaderboardType::AllTime, 0);
+        let mut learners = [profile(), profile(), profile()];
+        // Each step: which learner gains how much XP
+        let gains = [(0, 100), (1, 150), (2, 50), (2, 200), (0, 10), (1, 5)];
+        for (step, &(learner, xp)) in gains.iter().enumerate() {
+            learners[learner].xp += xp;
+            assert!(board.record(&learners[learner], DAY + step as i64).unwrap());
+        }
+        
+        // 250, 155 and 110 XP, each learner listed once
+        assert_eq!(board.count, 3);
+        let ranked: Vec<_> =
+            board.entries[..3].iter().map(|entry| (entry.user, entry.xp)).collect();
+        assert_eq!(
+            ranked,
+            [
+                (learners[2].authority, 250),
+                (learners[1].authority, 155),
+                (learners[0].authority, 110),
+            ]
+        );
+    }
+
+    #[test]
+    fn xp_adjustments_grant_deduct_and_stop_at_zero() {
+        let curve = config().level_curve;
+        let mut profile = profile();
+        
+        profile.adjust_xp(250, &curve).unwrap();
+        assert_eq!((profile.xp, profile.level), (250, level_for_xp(250, &curve)));
+        profile.adjust_xp(-100, &curve).unwrap();
+        assert_eq!((profile.xp, profile.level), (150, level_for_xp(150, &curve)));
+        profile.adjust_xp(i64::MIN, &curve).unwrap();
+        assert_eq!((profile.xp, profile.level), (0, 1));
+        
+        profile.xp = u64::MAX;
+        assert_error(profile.adjust_xp(1, &curve), ErrorCode::NumericOverflow);
+    }
+
+    #[test]
+    fn the_first_submission_exactly_at_the_week_edge_starts_a_new_week() {
+        let edge = 2_800 * SECONDS_PER_WEEK;
+        let mut profile = profile();
+        profile.earn_xp(100, edge - 1).unwrap();
+        assert_eq!(profile.weekly_xp_at(edge - 1), 100);
+        
+        profile.earn_xp(30, edge).unwrap();
+        assert_eq!(profile.week_index, 2_800);
+        assert_eq!(profile.weekly_xp, 30);
+        assert_eq!(profile.xp, 130);
+        
+        // The entry lands on the new week's board, not the old one
+        let mut last_week = leaderboard(LeaderboardType::Weekly, 2_799);
+        assert_error(last_week.record(&profile, edge), ErrorCode::InvalidPeriod);
+        let mut this_week = leaderboard(LeaderboardType::Weekly, 2_800);
+        assert!(this_week.record(&profile, edge).unwrap());
+        assert_eq!(this_week.entries[0].xp, 30);
+        
+        let mut last_weekly = weekly_leaderboard(2_799);
+        assert_error(last_weekly.record(&profile, edge), ErrorCode::InvalidWeek);
+        let mut this_weekly = weekly_leaderboard(2_800);
+        assert!(this_weekly.record(&profile, edge).unwrap());
+        assert_eq!(this_weekly.entries[0].xp, 30);
+    }
+
+    #[test]
+    fn a_week_can_be_finalized_once_it_is_over() {
+        let edge = 2_800 * SECONDS_PER_WEEK;
+        let mut board = weekly_leaderboard(2_800);
+        assert_error(board.finalize(edge), ErrorCode::WeekNotOver);
+        assert_error(board.finalize(edge + SECONDS_PER_WEEK - 1), ErrorCode::WeekNotOver);
+        
+        board.finalize(edge + SECONDS_PER_WEEK).unwrap();
+        assert_eq!(board.finalized, 1);
+        assert_error(board.finalize(edge + SECONDS_PER_WEEK), ErrorCode::WeekFinalized);
+    }
+
+    #[test]
+    fn xp_events_boost_only_inside_their_window() {
+        let mut config = config();
+        config.xp_event_multiplier = 20_000;
+        config.xp_event_start = 10 * DAY;
+        config.xp_event_end = 12 * DAY;
+        
+        assert_eq!(config.boosted_xp(100, 0, 10 * DAY - 1).unwrap(), 100);
+        assert_eq!(config.boosted_xp(100, 0, 10 * DAY).unwrap(), 200);
+        assert_eq!(config.boosted_xp(100, 0, 12 * DAY - 1).unwrap(), 200);
+        // The window is over once its end is reached
+        assert_eq!(config.boosted_xp(100, 0, 12 * DAY).unwrap(), 100);
+        
+        // A stake boost applies on top of the event
+        assert_eq!(config.boosted_xp(100, 5_000, 11 * DAY).unwrap(), 300);
+        assert_error(config.boosted_xp(u64::MAX, 0, 11 * DAY), ErrorCode::NumericOverflow);
+    }
+
+    #[test]
+    fn rewards_are_minted_only_with_a_mint_and_claimed_above_the_minimum() {
+        let mut config = config();
+        config.tokens_per_xp = 10;
+        config.daily_token_cap = 1_500;
+        let mut profile = profile();
+        
+        // Without a reward mint nothing accrues or counts towards the cap
+        assert_eq!(profile.accrue_token_rewards(100, &config, DAY).unwrap(), 0);
+        assert_eq!((profile.pending_rewards, profile.tokens_minted_today), (0, 0));
+        assert_eq!(profile.take_pending_rewards(500).unwrap(), 0);
+        
+        config.reward_mint = Pubkey::new_unique();
+        assert_eq!(profile.accrue_token_rewards(30, &config, DAY).unwrap(), 300);
+        assert_error(profile.take_pending_rewards(500), ErrorCode::ClaimBelowMinimum);
+        assert_eq!(profile.pending_rewards, 300);
+        assert_eq!(profile.accrue_token_rewards(300, &config, DAY).unwrap(), 1_200);
+        assert_eq!(profile.take_pending_rewards(500).unwrap(), 1_500);
+        assert_eq!(profile.pending_rewards, 0);
+    }
+
+    #[test]
+    fn a_referral_pays_both_sides_once_and_never_oneself() {
+        let config = config();
+        let mut referrer = profile();
+        let registry = ReferralRegistry {
+            referrer: referrer.profile_id,
+            code: referral_code_for(&referrer.profile_id),
+        };
+        let mut referee = profile();
+        
+        // A code must match its registry and can't be the profile's own
+        let code = registry.code;
+        assert_eq!(registry.referrer_of(code, &referee.profile_id).unwrap(), referrer.profile_id);
+        assert_error(registry.referrer_of([0; 8], &referee.profile_id), ErrorCode::InvalidReferrer);
+        assert_error(registry.referrer_of(code, &referrer.profile_id), ErrorCode::SelfReferral);
+        
+        // Profiles without a referrer start out the same as everyone else
+        assert!(referrer.referred_by.is_none());
+        assert_eq!((referrer.xp, referrer.referrals_count), (0, 0));
+        
+        referee.referred_by = Some(referrer.profile_id);
+        let bonus_xp = config.referral_bonus_xp;
+        assert_error(
+            referee.claim_referral_reward(&mut referrer, bonus_xp, &config.level_curve, DAY),
+            ErrorCode::ReferralNotEligible,
+        );
+        referee.quizzes_completed = 1;
+        referee.claim_referral_reward(&mut referrer, bonus_xp, &config.level_curve, DAY).unwrap();
+        assert_eq!((referee.xp, referrer.xp), (bonus_xp, bonus_xp));
+        assert_eq!(referrer.referrals_count, 1);
+        assert_error(
+            referee.claim_referral_reward(&mut referrer, bonus_xp, &config.level_curve, DAY),
+            ErrorCode::ReferralAlreadyClaimed,
+        );
+        assert_eq!(referrer.xp, bonus_xp);
+    }
+
+    #[test]
+    fn a_second_immediate_claim_gets_nothing() {
+        let mut config = config();
+        config.reward_mint = Pubkey::new_unique();
+        config.tokens_per_xp = 10;
+        let mut profile = profile();
+        profile.accrue_token_rewards(40, &config, DAY).unwrap();
+        profile.accrue_token_rewards(60, &config, DAY).unwrap();
+        
+        assert_eq!(profile.take_pending_rewards(config.min_reward_claim).unwrap(), 1_000);
+        assert_eq!(profile.take_pending_rewards(config.min_reward_claim).unwrap(), 0);
+        assert_eq!(profile.pending_rewards, 0);
+    }
+
+    #[test]
+    fn metadata_is_length_checked_and_survives_a_realloc() {
+        let mut profile = profile();
+        profile
+            .set_metadata(
+                "a".repeat(MAX_BIO_LEN),
+                "ipfs://avatar".to_string(),
+                "x.com/learner".to_string(),
+            )
+            .unwrap();
+        let too_long = [
+            ("a".repeat(MAX_BIO_LEN + 1), String::new(), String::new()),
+            (String::new(), "a".repeat(MAX_AVATAR_URI_LEN + 1), String::new()),
+            (String::new(), String::new(), "a".repeat(MAX_SOCIAL_LINK_LEN + 1)),
+        ];
+        for (bio, avatar_uri, social_link) in too_long {
+            let result = profile.set_metadata(bio, avatar_uri, social_link);
+            assert_error(result, ErrorCode::TextTooLong);
+        }
+        assert_eq!(profile.avatar_uri, "ipfs://avatar");
+        
+        // Growing an account from before profile ids keeps every existing field
+        profile.xp = 1_234;
+        let data = serialized(&profile);
+        let short_len = data.len() - 32;
+        let legacy = data[..short_len].to_vec();
+        let mut input = runtime_input(vec![(Pubkey::new_unique(), crate::ID, 1, legacy)]);
+        let (_, accounts, _) = unsafe { deserialize(input.as_mut_ptr() as *mut u8) };
+        accounts[0].realloc(8 + UserProfile::INIT_SPACE, true).unwrap();
+        let grown = UserProfile::try_deserialize(&mut &accounts[0].data.borrow()[..]).unwrap();
+        assert_eq!(grown.xp, 1_234);
+        assert_eq!(grown.bio, profile.bio);
+        assert_eq!(grown.social_link, "x.com/learner");
+        assert_eq!(grown.profile_id, Pubkey::default());
+    }
+
+    #[test]
+    fn staking_boosts_xp_up_to_the_cap_until_it_expires() {
+        let mut config = config();
+        config.stake_boost_bps_per_thousand = 2_500;
+        config.max_stake_boost_bps = 5_000;
+        config.stake_boost_secs = 7 * DAY;
+        config.stake_min_lock_secs = 3 * DAY;
+        let mut position = StakePosition {
+            user: Pubkey::new_unique(),
+            staked_amount: 0,
+            boost_bps: 0,
+            staked_at: 0,
+            boost_expires_at: 0,
+            bump: 255,
+        };
+        
+        // 1,500 tokens with 6 decimals: +37.5%, so 100 XP becomes 137
+        position.add_stake(1_500_000_000, 6, &config, DAY).unwrap();
+        assert_eq!(position.boost_bps_at(DAY), 3_750);
+        assert_eq!(config.boosted_xp(100, position.boost_bps_at(DAY), DAY).unwrap(), 137);
+        
+        // Staking more restarts the boost, which is capped
+        position.add_stake(8_500_000_000, 6, &config, 2 * DAY).unwrap();
+        assert_eq!(position.boost_bps_at(2 * DAY), 5_000);
+        assert_eq!(position.boost_bps_at(9 * DAY - 1), 5_000);
+        assert_eq!(position.boost_bps_at(9 * DAY), 0);
+        
+        // The lock runs from the last stake, and the whole deposit comes back
+        assert_error(
+            position.ensure_unlocked(config.stake_min_lock_secs, 5 * DAY - 1),
+            ErrorCode::StakeLocked,
+        );
+        position.ensure_unlocked(config.stake_min_lock_secs, 5 * DAY).unwrap();
+        assert_eq!(position.staked_amount, 10_000_000_000);
+    }
+
+    #[test]
+    fn migrating_an_old_profile_fills_it_in_once() {
+        let mut profile = profile();
+        profile.xp = 900;
+        profile.streak = 4;
+        profile.last_streak_day = 0;
+        profile.last_active = 30 * DAY + 600;
+        profile.schema_version = 0;
+        let data = serialized(&profile);
+        let mut grown = data[..data.len() - 32].to_vec();
+        grown.resize(8 + UserProfile::INIT_SPACE, 0);
+        
+        let mut migrated = UserProfile::try_deserialize(&mut &grown[..]).unwrap();
+        migrated.upgrade(253);
+        assert_eq!((migrated.xp, migrated.streak), (900, 4));
+        assert_eq!(migrated.last_streak_day, 30);
+        assert_eq!(migrated.profile_id, migrated.authority);
+        assert_eq!(migrated.bump, 253);
+        assert_eq!(migrated.schema_version, PROFILE_SCHEMA_VERSION);
+        
+        // A second migration is a no-op
+        let once = serialized(&migrated);
+        migrated.upgrade(253);
+        assert_eq!(serialized(&migrated), once);
+    }
+
+    #[test]
+    fn certificate_names_fit_metaplex() {
+        let mut achievement = blank_achievement();
+        achievement.achievement_name = "Quiz master".to_string();
+        achievement.tier = AchievementTier::Gold;
+        assert_eq!(achievement.certificate_name(), "Quiz master (Gold)");
+        
+        // Long names are cut on a character boundary
+        achievement.achievement_name = "Ünïcödé lover of very long names".to_string();
+        achievement.tier = AchievementTier::Platinum;
+        let name = achievement.certificate_name();
+        assert!(name.len() <= 32);
+        assert!(name.starts_with("Ünïcödé lover"));
+    }
+
+    #[test]
+    fn a_limited_definition_runs_out_and_an_inactive_one_awards_nothing() {
+        let mut limited = definition(AchievementTier::Gold, 2);
+        limited.take_supply().unwrap();
+        limited.take_supply().unwrap();
+        assert_eq!(limited.remaining_supply, 0);
+        assert_error(limited.take_supply(), ErrorCode::AchievementSupplyExhausted);
+        
+        let mut unlimited = definition(AchievementTier::Bronze, 0);
+        for _ in 0..10 {
+            unlimited.take_supply().unwrap();
+        }
+        unlimited.is_active = false;
+        assert_error(unlimited.take_supply(), ErrorCode::AchievementInactive);
+    }
+
+    #[test]
+    fn after_a_transfer_only_the_new_wallet_can_use_the_profile() {
+        let mut original = profile();
+        let mut achievement = blank_achievement();
+        award_gold(&mut achievement, &mut original, DAY).unwrap();
+        let (achievement_key, _) = Pubkey::find_program_address(
+            &[b"achievement", original.profile_id.as_ref(), &id_seed("quiz_master")],
+            &crate::ID,
+        );
+        let ix_data = "quiz_master".to_string().try_to_vec().unwrap();
+        let act_as = |signer: Pubkey, profile_key: Pubkey, profile: &UserProfile| {
+            let mut input = runtime_input(vec![
+                (profile_key, crate::ID, 5_000, serialized(profile)),
+                (achievement_key, crate::ID, 1_000, serialized(&achievement)),
+                (signer, system_program::ID, 0, Vec::new()),
+            ]);
+            let (_, accounts, _) = unsafe { deserialize(input.as_mut_ptr() as *mut u8) };
+            CloseAchievement::try_accounts(
+                &crate::ID,
+                &mut &accounts[..],
+                &ix_data,
+                &mut CloseAchievementBumps::default(),
+                &mut BTreeSet::new(),
+            )
+            .is_ok()
+        };
+        
+        let old_wallet = original.authority;
+        let mut moved = original.clone();
+        moved.authority = Pubkey::new_unique();
+        let moved_key = profile_address(&mut moved);
+        
+        assert!(act_as(moved.authority, moved_key, &moved));
+        assert!(!act_as(old_wallet, moved_key, &moved));
+    }
+
+    #[test]
+    fn challenge_counts_only_the_first_attempt_after_entry() {
+        let challenger = Pubkey::new_unique();
+        let challenged = Pubkey::new_unique();
+        let mut challenge = Challenge {
+            challenger,
+            challenged,
+            quiz_id: "rust-101".to_string(),
+            wager_xp: 50,
+            challenger_score: None,
+            challenged_score: None,
+            challenger_attempt: 2,
+            challenged_attempt: 0,
+            status: ChallengeStatus::Active,
+            created_at: DAY,
+            accepted_at: DAY,
+            expires_at: 2 * DAY,
+            winner: None,
+            bump: 255,
+        };
+        
+        assert_error(challenge.record_score(challenger, 1, 90), ErrorCode::InvalidAttempt);
+        assert_error(challenge.record_score(challenger, 3, 90), ErrorCode::InvalidAttempt);
+        challenge.record_score(challenger, 2, 70).unwrap();
+        assert_eq!(challenge.challenger_score, Some(70));
+        assert_error(
+            challenge.record_score(challenger, 2, 90),
+            ErrorCode::ChallengeScoreSubmitted
+        );
+        
+        challenge.record_score(challenged, 0, 80).unwrap();
+        assert_eq!(challenge.challenged_score, Some(80));
+        assert_error(
+            challenge.record_score(Pubkey::new_unique(), 0, 100),
+            ErrorCode::Unauthorized
+        );
+    }
     
-    #[account(mut)]
-    pub authority: Signer<'info>,
+    #[test]
+    fn next_quiz_attempt_reads_the_progress_attempt_count() {
+        let empty = Pubkey::new_unique();
+        let tracked = Pubkey::new_unique();
+        let mut progress = quiz_progress();
+        progress.attempts = 3;
+        let mut input = runtime_input(vec![
+            (empty, Pubkey::default(), 0, vec![]),
+            (tracked, crate::ID, 1_000_000, serialized(&progress)),
+        ]);
+        let (_, accounts, _) = unsafe { deserialize(input.as_mut_ptr() as *mut u8) };
+        
+        assert_eq!(next_quiz_attempt(&accounts[0]).unwrap(), 0);
+        assert_eq!(next_quiz_attempt(&accounts[1]).unwrap(), 3);
+    }
+
+    #[test]
+    fn a_delegate_signs_for_the_profile_until_revoked() {
+        let mut profile = profile();
+        let delegate = Pubkey::new_unique();
+        assert!(profile.is_authorized_signer(&profile.authority));
+        assert!(!profile.is_authorized_signer(&delegate));
+        
+        profile.delegate = Some(delegate);
+        assert!(profile.is_authorized_signer(&delegate));
+        assert!(profile.is_authorized_signer(&profile.authority));
+        assert!(!profile.is_authorized_signer(&Pubkey::new_unique()));
+        
+        // revoke_delegate clears the slot
+        profile.delegate = None;
+        assert!(!profile.is_authorized_signer(&delegate));
+        assert!(profile.is_authorized_signer(&profile.authority));
+    }
     
-    pub system_program: Program<'info, System>,
-}
+    #[test]
+    fn only_the_authority_can_set_a_delegate() {
+        let mut owner = profile();
+        let owner_key = profile_address(&mut owner);
+        let (config_key, _) = Pubkey::find_program_address(&[b"protocol_config"], &crate::ID);
+        let ix_data = Pubkey::new_unique().try_to_vec().unwrap();
+        
+        let set_as = |signer: Pubkey, profile: &UserProfile| {
+            let mut input = runtime_input(vec![
+                (config_key, crate::ID, 1_000, serialized(&config())),
+                (owner_key, crate::ID, 5_000, serialized(profile)),
+                (signer, system_program::ID, 0, Vec::new()),
+            ]);
+            let (_, accounts, _) = unsafe { deserialize(input.as_mut_ptr() as *mut u8) };
+            SetDelegate::try_accounts(
+                &crate::ID,
+                &mut &accounts[..],
+                &ix_data,
+                &mut SetDelegateBumps::default(),
+                &mut BTreeSet::new(),
+            )
+            .is_ok()
+        };
+        
+        assert!(set_as(owner.authority, &owner));
+        assert!(!set_as(Pubkey::new_unique(), &owner));
+        // A delegate acts for the profile but can't appoint or revoke one
+        let delegate = Pubkey::new_unique();
+        owner.delegate = Some(delegate);
+        assert!(!set_as(delegate, &owner));
+    }
 
-#[derive(Accounts)]
-pub struct UpdateStreak<'info> {
-    #[account(
-        mut,
-        seeds = [b"user_profile", authority.key().as_ref()],
-        bump,
-        has_one = authority
-    )]
-    pub user_profile: Account<'info, UserProfile>,
+    #[test]
+    fn one_submission_can_reach_two_thresholds() {
+        let config = config();
+        let mut profile = profile();
+        profile.quizzes_completed = 10;
+        assert_eq!(
+            threshold_achievements(&profile, true),
+            vec!["quizzes_completed_1", "quizzes_completed_10", "first_perfect_score"]
+        );
+        
+        let mut first = definition(AchievementTier::Bronze, 0);
+        first.achievement_id = "quizzes_completed_10".to_string();
+        let mut perfect = definition(AchievementTier::Gold, 1);
+        perfect.achievement_id = "first_perfect_score".to_string();
+        perfect.bonus_xp = Some(75);
+        let mut awards = [(blank_achievement(), first), (blank_achievement(), perfect)];
+        
+        for (award, definition) in awards.iter_mut() {
+            let granted = grant_milestone_achievement(
+                award,
+                definition,
+                &mut profile,
+                &config,
+                DAY,
+                1,
+            );
+            assert!(granted.unwrap());
+        }
+        assert_eq!(profile.achievements_earned, 2);
+        assert_eq!(profile.xp, config.tier_bonus(&AchievementTier::Bronze) + 75);
+        let (perfect_award, perfect) = &awards[1];
+        assert!(perfect_award.tier == AchievementTier::Gold);
+        assert_eq!(perfect.remaining_supply, 0);
+    }
     
-    pub authority: Signer<'info>,
-}
+    #[test]
+    fn milestones_skip_definitions_that_cannot_be_awarded() {
+        let config = config();
+        let mut profile = profile();
+        let mut inactive = definition(AchievementTier::Bronze, 0);
+        inactive.is_active = false;
+        let mut exhausted = definition(AchievementTier::Bronze, 1);
+        exhausted.remaining_supply = 0;
+        let mut gated = definition(AchievementTier::Bronze, 0);
+        gated.prerequisite_achievement_id = Some("first_quiz".to_string());
+        
+        for definition in [&mut inactive, &mut exhausted, &mut gated] {
+            let mut achievement = blank_achievement();
+            let granted = grant_milestone_achievement(
+                &mut achievement,
+                definition,
+                &mut profile,
+                &config,
+                DAY,
+                1,
+            );
+            assert!(!granted.unwrap());
+            assert_eq!(achievement.awarded_at, 0);
+        }
+        assert_eq!((profile.xp, profile.achievements_earned), (0, 0));
+    }
+    
+    #[test]
+    fn threshold_awards_need_matching_accounts() {
+        let config = config();
+        let mut profile = profile();
+        profile.quizzes_completed = 1;
+        let stranger = Pubkey::new_unique();
+        let mut input = runtime_input(vec![
+            (profile.authority, system_program::ID, 1_000_000, Vec::new()),
+            (system_program::ID, Pubkey::default(), 1, Vec::new()),
+            (stranger, crate::ID, 1_000, serialized(&definition(AchievementTier::Bronze, 0))),
+            (Pubkey::new_unique(), system_program::ID, 0, Vec::new()),
+        ]);
+        let (_, accounts, _) = unsafe { deserialize(input.as_mut_ptr() as *mut u8) };
+        let (payer, system) = (&accounts[0], &accounts[1]);
+        
+        // Nothing supplied, or a pair that isn't a reached threshold's PDAs
+        for supplied in [&accounts[..0], &accounts[2..]] {
+            let awarded = award_threshold_achievements(
+                supplied,
+                payer,
+                system,
+                &mut profile,
+                &config,
+                false,
+                DAY,
+            );
+            assert!(awarded.unwrap().is_empty());
+        }
+        assert_eq!(profile.achievements_earned, 0);
+    }
 
-#[account]
-#[derive(InitSpace)]
-pub struct UserProfile {
-    pub authority: Pubkey,        // 32
-    #[max_len(32)]
-    pub username: String,          // 4 + 32
-    pub xp: u64,                   // 8
-    pub level: u64,                // 8
-    pub streak: u64,               // 8
-    pub quizzes_completed: u64,    // 8
-    pub achievements_earned: u64,  // 8
-    pub created_at: i64,           // 8
-    pub last_active: i64,          // 8
-}
+    /// Stands in for the runtime so accounts can be created in tests: serves
+    /// the rent sysvar and carries out system program instructions
+    struct SystemProgramStubs;
 
-#[account]
-#[derive(InitSpace)]
-pub struct QuizResult {
-    pub user: Pubkey,              // 32
-    #[max_len(64)]
-    pub quiz_id: String,           // 4 + 64
-    pub score: u8,                 // 1
-    pub total_questions: u8,       // 1
-    pub xp_earned: u64,            // 8
-    pub completed_at: i64,         // 8
-}
+    impl SyscallStubs for SystemProgramStubs {
+        fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+            unsafe { *(var_addr as *mut Rent) = Rent::default() };
+            0
+        }
 
-#[account]
-#[derive(InitSpace)]
-pub struct Achievement {
-    pub user: Pubkey,              // 32
-    #[max_len(64)]
-    pub achievement_id: String,    // 4 + 64
-    #[max_len(128)]
-    pub achievement_name: String,  // 4 + 128
-    pub tier: AchievementTier,     // 1
-    pub awarded_at: i64,           // 8
-}
+        fn sol_invoke_signed(
+            &self,
+            instruction: &Instruction,
+            account_infos: &[AccountInfo],
+            _signers_seeds: &[&[&[u8]]],
+        ) -> ProgramResult {
+            assert_eq!(instruction.program_id, system_program::ID);
+            let info = |index: usize| {
+                let key = instruction.accounts[index].pubkey;
+                account_infos.iter().find(|info| info.key == &key).unwrap()
+            };
+            let data = &instruction.data;
+            let word = |at: usize| u64::from_le_bytes(data[at..at + 8].try_into().unwrap());
+            let owner = |at: usize| Pubkey::try_from(&data[at..at + 32]).unwrap();
+            match u32::from_le_bytes(data[..4].try_into().unwrap()) {
+                // CreateAccount, which fails on an address holding lamports
+                0 => {
+                    if info(1).lamports() > 0 {
+                        return Err(ProgramError::Custom(0));
+                    }
+                    move_lamports(info(0), info(1), word(4)).unwrap();
+                    info(1).realloc(word(12) as usize, true)?;
+                    info(1).assign(&owner(20));
+                }
+                1 => info(0).assign(&owner(4)),
+                2 => move_lamports(info(0), info(1), word(4)).unwrap(),
+                8 => info(0).realloc(word(4) as usize, true)?,
+                other => panic!("unexpected system instruction {}", other),
+            }
+            Ok(())
+        }
+    }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
-pub enum AchievementTier {
-    Bronze,
-    Silver,
-    Gold,
-    Platinum,
-}
+    #[test]
+    fn streak_milestone_accounts_are_created_only_when_awarded() {
+        set_syscall_stubs(Box::new(SystemProgramStubs));
+        let config = config();
+        let mut profile = profile();
+        let achievement_id = streak_achievement_id(7);
+        let (address, bump) = Pubkey::find_program_address(
+            &[b"achievement", profile.profile_id.as_ref(), &id_seed(&achievement_id)],
+            &crate::ID,
+        );
+        let mut input = runtime_input(vec![
+            (profile.authority, system_program::ID, 1_000_000_000, Vec::new()),
+            (system_program::ID, Pubkey::default(), 1, Vec::new()),
+            (address, system_program::ID, 0, Vec::new()),
+        ]);
+        let (_, accounts, _) = unsafe { deserialize(input.as_mut_ptr() as *mut u8) };
+        let (payer, system, account) = (&accounts[0], &accounts[1], &accounts[2]);
+        let award = |definition: &mut AchievementDefinition, profile: &mut UserProfile| {
+            award_milestone_achievement(
+                account, definition, profile, &config, payer, system, DAY, bump,
+            )
+        };
+        
+        // A milestone that can't be awarded leaves nothing behind to pay rent on
+        let mut gated = definition(AchievementTier::Bronze, 0);
+        gated.achievement_id = achievement_id.clone();
+        gated.prerequisite_achievement_id = Some("first_quiz".to_string());
+        assert!(award(&mut gated, &mut profile).unwrap().is_none());
+        assert!(account.data_is_empty());
+        assert_eq!((account.lamports(), payer.lamports()), (0, 1_000_000_000));
+        
+        let mut milestone = definition(AchievementTier::Silver, 0);
+        milestone.achievement_id = achievement_id.clone();
+        let awarded = award(&mut milestone, &mut profile).unwrap().unwrap();
+        assert_eq!(awarded.achievement_id, achievement_id);
+        assert_eq!(account.owner, &crate::ID);
+        let space = 8 + Achievement::INIT_SPACE;
+        assert_eq!(account.lamports(), Rent::default().minimum_balance(space));
+        let stored = Account::<Achievement>::try_from(account).unwrap();
+        assert_eq!((stored.user, stored.awarded_at), (profile.profile_id, DAY));
+        
+        // Once created, the account is never awarded into again
+        assert!(award(&mut milestone, &mut profile).unwrap().is_none());
+        assert_eq!(profile.achievements_earned, 1);
+    }
 
-impl std::fmt::Display for AchievementTier {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self {
-            AchievementTier::Bronze => write!(f, "Bronze"),
-            AchievementTier::Silver => write!(f, "Silver"),
-            AchievementTier::Gold => write!(f, "Gold"),
-            AchievementTier::Platinum => write!(f, "Platinum"),
+    #[test]
+    fn a_prefunded_achievement_address_is_topped_up_rather_than_rejected() {
+        set_syscall_stubs(Box::new(SystemProgramStubs));
+        let profile = profile();
+        let (address, bump) = Pubkey::find_program_address(
+            &[b"achievement", profile.profile_id.as_ref(), &id_seed("xp_1000")],
+            &crate::ID,
+        );
+        let mut input = runtime_input(vec![
+            (profile.authority, system_program::ID, 1_000_000_000, Vec::new()),
+            (system_program::ID, Pubkey::default(), 1, Vec::new()),
+            (address, system_program::ID, 1_000, Vec::new()),
+        ]);
+        let (_, accounts, _) = unsafe { deserialize(input.as_mut_ptr() as *mut u8) };
+        let (payer, system, account) = (&accounts[0], &accounts[1], &accounts[2]);
+        
+        create_achievement_account(account, payer, system, &profile.profile_id, "xp_1000", bump)
+            .unwrap();
+        let rent = Rent::default().minimum_balance(8 + Achievement::INIT_SPACE);
+        assert_eq!(account.lamports(), rent);
+        assert_eq!(payer.lamports(), 1_000_000_000 - (rent - 1_000));
+        assert_eq!(account.data_len(), 8 + Achievement::INIT_SPACE);
+        assert_eq!(account.owner, &crate::ID);
+    }
+
+    #[test]
+    fn every_milestone_has_its_own_award_bit() {
+        let config = config();
+        assert_eq!(milestone_award_bit("quizzes_completed_1", &config), Some(0));
+        assert_eq!(milestone_award_bit("first_perfect_score", &config), Some(4));
+        assert_eq!(milestone_award_bit("xp_50000", &config), Some(8));
+        let first_streak = config.streak_milestones[0];
+        assert_eq!(milestone_award_bit(&streak_achievement_id(first_streak), &config), Some(16));
+        assert_eq!(milestone_award_bit("quiz_master", &config), None);
+        assert_eq!(milestone_award_bit("streak_0", &config), None);
+    }
+
+    /// Accounts for `award_threshold_achievements`: the payer, the system
+    /// program, then a `[definition, achievement]` pair per threshold
+    fn threshold_input(profile: &UserProfile, definitions: &[AchievementDefinition]) -> Vec<u64> {
+        let mut accounts = vec![
+            (profile.authority, system_program::ID, 1_000_000_000, Vec::new()),
+            (system_program::ID, Pubkey::default(), 1, Vec::new()),
+        ];
+        for definition in definitions {
+            let id_hash = id_seed(&definition.achievement_id);
+            let (definition_address, _) =
+                Pubkey::find_program_address(&[b"achievement_def", &id_hash], &crate::ID);
+            let (address, _) = Pubkey::find_program_address(
+                &[b"achievement", profile.profile_id.as_ref(), &id_hash],
+                &crate::ID,
+            );
+            accounts.push((definition_address, crate::ID, 1_000, serialized(definition)));
+            accounts.push((address, system_program::ID, 0, Vec::new()));
         }
+        runtime_input(accounts)
     }
-}
 
-#[error_code]
-pub enum ErrorCode {
-    #[msg("Invalid score: score cannot exceed total questions")]
-    InvalidScore,
+    #[test]
+    fn a_submission_creates_every_supplied_threshold_achievement() {
+        set_syscall_stubs(Box::new(SystemProgramStubs));
+        let config = config();
+        let mut profile = profile();
+        profile.quizzes_completed = 10;
+        let mut first = definition(AchievementTier::Bronze, 0);
+        first.achievement_id = "quizzes_completed_10".to_string();
+        let mut perfect = definition(AchievementTier::Gold, 1);
+        perfect.achievement_id = "first_perfect_score".to_string();
+        let mut input = threshold_input(&profile, &[first, perfect]);
+        let (_, accounts, _) = unsafe { deserialize(input.as_mut_ptr() as *mut u8) };
+        let (payer, system) = (&accounts[0], &accounts[1]);
+        
+        let awarded = award_threshold_achievements(
+            &accounts[2..],
+            payer,
+            system,
+            &mut profile,
+            &config,
+            true,
+            DAY,
+        )
+        .unwrap();
+        let ids: Vec<_> = awarded.iter().map(|a| a.achievement_id.as_str()).collect();
+        assert_eq!(ids, vec!["quizzes_completed_10", "first_perfect_score"]);
+        assert_eq!(profile.achievements_earned, 2);
+        assert_eq!(profile.milestone_awards, (1 << 1) | (1 << 4));
+        
+        for pair in accounts[2..].chunks_exact(2) {
+            let stored = Account::<Achievement>::try_from(&pair[1]).unwrap();
+            assert_eq!((stored.user, stored.awarded_at), (profile.profile_id, DAY));
+        }
+        // The capped definition's supply is written back
+        let perfect = Account::<AchievementDefinition>::try_from(&accounts[4]).unwrap();
+        assert_eq!(perfect.remaining_supply, 0);
+    }
+
+    #[test]
+    fn a_closed_achievement_leaves_a_tombstone_that_blocks_new_awards() {
+        set_syscall_stubs(Box::new(SystemProgramStubs));
+        let mut profile = profile();
+        let mut awarded = blank_achievement();
+        award_gold(&mut awarded, &mut profile, DAY).unwrap();
+        let space = 8 + Achievement::INIT_SPACE;
+        let rent = Rent::default().minimum_balance(space);
+        let mut data = serialized(&awarded);
+        data.resize(space, 0);
+        let mut input = runtime_input(vec![
+            (Pubkey::new_unique(), crate::ID, rent, data),
+            (profile.authority, system_program::ID, 0, Vec::new()),
+        ]);
+        let (_, accounts, _) = unsafe { deserialize(input.as_mut_ptr() as *mut u8) };
+        let (achievement_info, owner) = (&accounts[0], &accounts[1]);
+        
+        let mut achievement = Account::<Achievement>::try_from(achievement_info).unwrap();
+        shrink_to_tombstone(&mut achievement, owner).unwrap();
+        achievement.exit(&crate::ID).unwrap();
+        assert!(achievement_info.data_len() < space);
+        let tombstone_rent = Rent::default().minimum_balance(achievement_info.data_len());
+        assert_eq!(achievement_info.lamports(), tombstone_rent);
+        assert_eq!(owner.lamports(), rent - tombstone_rent);
+        
+        // The record still reads as awarded, so no award path can fill it again
+        let mut tombstone = Account::<Achievement>::try_from(achievement_info).unwrap();
+        assert!(tombstone.is_closed() && !awarded.is_closed());
+        assert_eq!((tombstone.achievement_id.as_str(), tombstone.awarded_at), ("quiz_master", DAY));
+        assert_error(
+            award_gold(&mut tombstone, &mut profile, 2 * DAY),
+            ErrorCode::AchievementAlreadyAwarded,
+        );
+        assert_eq!(profile.achievements_earned, 1);
+    }
+
+    #[test]
+    fn closed_or_revoked_threshold_achievements_are_not_awarded_again() {
+        set_syscall_stubs(Box::new(SystemProgramStubs));
+        let config = config();
+        let mut profile = profile();
+        profile.quizzes_completed = 1;
+        let mut first = definition(AchievementTier::Bronze, 0);
+        first.achievement_id = "quizzes_completed_1".to_string();
+        let mut perfect = definition(AchievementTier::Gold, 5);
+        perfect.achievement_id = "first_perfect_score".to_string();
+        let mut input = threshold_input(&profile, &[first, perfect]);
+        let (_, accounts, _) = unsafe { deserialize(input.as_mut_ptr() as *mut u8) };
+        let (payer, system) = (&accounts[0], &accounts[1]);
+        let submit = |profile: &mut UserProfile| {
+            award_threshold_achievements(&accounts[2..], payer, system, profile, &config, true, DAY)
+                .unwrap()
+                .len()
+        };
+        assert_eq!(submit(&mut profile), 2);
+        let earned = (profile.xp, profile.achievements_earned);
+        
+        // The owner closes one record and the config authority revokes the other
+        let closed = Account::<Achievement>::try_from(&accounts[3]).unwrap();
+        closed.close(payer.clone()).unwrap();
+        let revoked = Account::<Achievement>::try_from(&accounts[5]).unwrap();
+        profile.revoke_achievement(revoked.bonus_xp, &config.level_curve);
+        restore_definition_supply(&accounts[4]).unwrap();
+        revoked.close(payer.clone()).unwrap();
+        assert!(accounts[3].data_is_empty() && accounts[5].data_is_empty());
+        
+        // The next submission still reaches both thresholds but awards neither
+        profile.quizzes_completed = 2;
+        assert_eq!(submit(&mut profile), 0);
+        assert!(accounts[3].data_is_empty() && accounts[5].data_is_empty());
+        assert_eq!(profile.achievements_earned, earned.1 - 1);
+        assert!(profile.xp < earned.0);
+    }
+
+    #[test]
+    fn a_tournament_counts_only_the_first_attempt_after_registering() {
+        let (early, late) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut tournament = Tournament {
+            tournament_id: "spring-cup".to_string(),
+            organizer: Pubkey::new_unique(),
+            quiz_id: "rust-101".to_string(),
+            max_participants: 4,
+            entry_fee_lamports: 0,
+            prize_pool_lamports: 0,
+            starts_at: DAY,
+            ends_at: 2 * DAY,
+            status: TournamentStatus::Registration,
+            participants: vec![early, late],
+            scores: vec![None, None],
+            attempts: vec![0, 4],
+            bump: 255,
+        };
+        
+        // Earlier attempts, and retakes hoping for a better score, don't count
+        assert_error(tournament.record_score(late, 3, 100), ErrorCode::InvalidAttempt);
+        assert_error(tournament.record_score(late, 5, 100), ErrorCode::InvalidAttempt);
+        tournament.record_score(late, 4, 60).unwrap();
+        tournament.record_score(early, 0, 80).unwrap();
+        assert_eq!(tournament.scores, vec![Some(80), Some(60)]);
+        
+        assert_error(tournament.record_score(early, 0, 90), ErrorCode::TournamentScoreSubmitted);
+        assert_error(
+            tournament.record_score(Pubkey::new_unique(), 0, 90),
+            ErrorCode::NotRegistered
+        );
+    }
+
+    #[test]
+    fn quiz_leaderboard_entries_use_the_measured_duration() {
+        let mut result = quiz_result(Difficulty::Easy, 8, 10);
+        result.time_taken_seconds = 1;
+        result.duration_secs = 95;
+        assert_eq!(QuizLeaderboardEntry::from_result(&result).duration_secs, 95);
+        
+        result.duration_secs = i64::MAX;
+        assert_eq!(QuizLeaderboardEntry::from_result(&result).duration_secs, u32::MAX);
+    }
+
+    #[test]
+    fn each_submission_takes_the_next_nonce_once() {
+        let mut profile = profile();
+        for nonce in 0..3 {
+            profile.use_submission_nonce(nonce).unwrap();
+            assert_eq!(profile.submission_nonce, nonce + 1);
+        }
+        
+        // A replayed or skipped-ahead nonce is rejected and changes nothing
+        assert_error(profile.use_submission_nonce(2), ErrorCode::InvalidNonce);
+        assert_error(profile.use_submission_nonce(4), ErrorCode::InvalidNonce);
+        assert_eq!(profile.submission_nonce, 3);
+        
+        profile.submission_nonce = u64::MAX;
+        assert_error(profile.use_submission_nonce(u64::MAX), ErrorCode::NumericOverflow);
+    }
+
+    #[test]
+    fn stored_tiers_keep_their_encoding_after_diamond() {
+        assert_eq!(AchievementTier::INIT_SPACE, 1);
+        let stored = [
+            AchievementTier::Bronze,
+            AchievementTier::Silver,
+            AchievementTier::Gold,
+            AchievementTier::Platinum,
+        ];
+        for (index, tier) in stored.into_iter().enumerate() {
+            // A Bronze to Platinum achievement as written before Diamond existed
+            let mut achievement = blank_achievement();
+            achievement.tier = tier.clone();
+            let data = serialized(&achievement);
+            let tier_offset = 8 + 32 + 4 + 4;
+            assert_eq!(data[tier_offset], index as u8);
+            
+            let loaded = Achievement::try_deserialize(&mut &data[..]).unwrap();
+            assert!(loaded.tier == tier);
+        }
+    }
+    
+    #[test]
+    fn diamond_awards_use_the_configured_bonus() {
+        let mut config = config();
+        config.tier_bonuses = [50, 100, 200, 500, 2_000];
+        let mut profile = profile();
+        let mut achievement = blank_achievement();
+        let diamond = definition(AchievementTier::Diamond, 0);
+        
+        let bonus_xp = diamond.award_bonus(&config);
+        assert_eq!(bonus_xp, 2_000);
+        achievement
+            .award(
+                &mut profile,
+                diamond.achievement_id.clone(),
+                diamond.name.clone(),
+                diamond.tier.clone(),
+                bonus_xp,
+                DAY,
+                1,
+            )
+            .unwrap();
+        assert!(achievement.tier == AchievementTier::Diamond);
+        assert_eq!(profile.xp, 2_000);
+        
+        // Rebalancing the tier takes effect on the next award
+        config.tier_bonuses[4] = 1_500;
+        assert_eq!(definition(AchievementTier::Diamond, 0).award_bonus(&config), 1_500);
+    }
+
+    /// Data of an ed25519 program instruction checking `signature` by
+    /// `signer` over `message`, all stored in the instruction itself
+    fn ed25519_instruction_data(signer: &Pubkey, signature: &[u8; 64], message: &[u8]) -> Vec<u8> {
+        let (key_at, signature_at, message_at) = (16u16, 48u16, 112u16);
+        let own_data = u16::MAX;
+        let mut data = vec![1, 0];
+        for field in [
+            signature_at,
+            own_data,
+            key_at,
+            own_data,
+            message_at,
+            message.len() as u16,
+            own_data,
+        ] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+        data.extend_from_slice(signer.as_ref());
+        data.extend_from_slice(signature);
+        data.extend_from_slice(message);
+        data
+    }
+
+    /// Instructions sysvar data for a transaction of `instructions`, while
+    /// the last one runs
+    fn instructions_sysvar_data(instructions: &[(Pubkey, Vec<u8>)]) -> Vec<u8> {
+        let borrowed: Vec<_> = instructions
+            .iter()
+            .map(|(program_id, data)| BorrowedInstruction {
+                program_id,
+                accounts: Vec::new(),
+                data,
+            })
+            .collect();
+        let mut data = construct_instructions_data(&borrowed);
+        store_current_index(&mut data, instructions.len() as u16 - 1);
+        data
+    }
+
+    #[test]
+    fn self_reported_scores_need_the_attestors_signature() {
+        let mut config = config();
+        let attestor = Pubkey::new_unique();
+        let user = Pubkey::new_unique();
+        let signature = [7u8; 64];
+        let graded = GradedScore::flat(9, 10);
+        let message = attestation_message(&user, "rust-101", 2, &graded, 90, 1, 5, DAY);
+        let now = DAY + 60;
+        
+        // Without an attestor nothing is required
+        config.ensure_attested(None, &message, &signature, 0, now).unwrap();
+        config.attestor = attestor;
+        
+        let check = |transaction: &[(Pubkey, Vec<u8>)], attested_at: i64| {
+            let mut input = runtime_input(vec![(
+                sysvar::instructions::ID,
+                sysvar::ID,
+                1,
+                instructions_sysvar_data(transaction),
+            )]);
+            let (_, accounts, _) = unsafe { deserialize(input.as_mut_ptr() as *mut u8) };
+            config.ensure_attested(Some(&accounts[0]), &message, &signature, attested_at, now)
+        };
+        let signed_by = |signer: &Pubkey, signed: &[u8]| {
+            vec![
+                (ed25519_program::ID, ed25519_instruction_data(signer, &signature, signed)),
+                (crate::ID, Vec::new()),
+            ]
+        };
+        
+        check(&signed_by(&attestor, &message), DAY).unwrap();
+        
+        // Forged by another key, or stale
+        assert_error(check(&signed_by(&user, &message), DAY), ErrorCode::InvalidAttestation);
+        assert_error(
+            check(&signed_by(&attestor, &message), now - ATTESTATION_MAX_AGE - 1),
+            ErrorCode::InvalidAttestation,
+        );
+        
+        // Signed for another attempt, nonce, score, time or hint count
+        for other in [
+            attestation_message(&user, "rust-101", 3, &graded, 90, 1, 5, DAY),
+            attestation_message(&user, "rust-101", 2, &graded, 90, 1, 6, DAY),
+            attestation_message(&user, "rust-101", 2, &GradedScore::flat(10, 10), 90, 1, 5, DAY),
+            attestation_message(&user, "rust-101", 2, &graded, 30, 1, 5, DAY),
+            attestation_message(&user, "rust-101", 2, &graded, 90, 0, 5, DAY),
+        ] {
+            assert_error(check(&signed_by(&attestor, &other), DAY), ErrorCode::InvalidAttestation);
+        }
+        
+        // Missing: no sysvar, no instruction before, or not an ed25519 one
+        assert_error(
+            config.ensure_attested(None, &message, &signature, DAY, now),
+            ErrorCode::InvalidAttestation,
+        );
+        assert_error(check(&[(crate::ID, Vec::new())], DAY), ErrorCode::InvalidAttestation);
+        let mut not_ed25519 = signed_by(&attestor, &message);
+        not_ed25519[0].0 = system_program::ID;
+        assert_error(check(&not_ed25519, DAY), ErrorCode::InvalidAttestation);
+    }
 }